@@ -1,72 +1,232 @@
 //! A simple parser for a tiny subset of CSS.
+//!
+//! The parser never fails: like a browser, it drops whatever it cannot read and goes on,
+//! following the error handling rules of CSS Syntax Level 3.
+//! https://www.w3.org/TR/css-syntax-3/#error-handling
+
+use std::fmt;
 
 use crate::cssom;
 use crate::cssom::{Color, Declaration, Rule, Selector, SimpleSelector, Stylesheet, Unit, Value};
 
-/// Parses a CSS source string into a stylesheet.
+/// Parses a CSS source string into a stylesheet. Rules and declarations that cannot be
+/// parsed are left out; use `parse_with_errors` to find out which.
 pub fn parse(source: String) -> Stylesheet {
+    parse_with_errors(source).stylesheet
+}
+
+/// Parses a CSS source string into a stylesheet, with an error for everything that was left
+/// out.
+pub fn parse_with_errors(source: String) -> ParseResult {
     let mut parser = Parser::new(source);
+    let rules = parser.parse_rules();
+
+    ParseResult {
+        stylesheet: cssom::stylesheet(rules),
+        errors: parser.errors,
+    }
+}
+
+/// A stylesheet and the errors found while parsing it.
+#[derive(Debug)]
+pub struct ParseResult {
+    pub stylesheet: Stylesheet,
+    /// Parse errors, in source order.
+    pub errors: Vec<ParseError>,
+}
+
+/// A part of a stylesheet that was dropped, or a stylesheet that ended too early.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ParseError {
+    pub kind: ParseErrorKind,
+    /// Byte offset in the source where the dropped part starts.
+    pub offset: usize,
+    /// 1-based line number.
+    pub line: usize,
+    /// 1-based column number, counted in characters.
+    pub column: usize,
+}
+
+/// The kind of a CSS parse error.
+#[derive(Debug, Clone, PartialEq)]
+pub enum ParseErrorKind {
+    /// A rule whose selector list is not valid. The whole rule is skipped.
+    InvalidSelector(String),
+    /// A declaration without a property name or `:`. It is dropped.
+    InvalidDeclaration(String),
+    /// A declaration whose value cannot be parsed, such as `10pt`. It is dropped.
+    InvalidValue { property: String, value: String },
+    /// An at-rule such as `@media`, which is not supported. It is skipped.
+    UnsupportedAtRule(String),
+    /// The stylesheet ended inside a rule.
+    UnexpectedEof,
+}
+
+impl fmt::Display for ParseErrorKind {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ParseErrorKind::InvalidSelector(selector) => {
+                write!(f, "invalid selector '{}', rule skipped", selector)
+            }
+            ParseErrorKind::InvalidDeclaration(declaration) => {
+                write!(f, "invalid declaration '{}' dropped", declaration)
+            }
+            ParseErrorKind::InvalidValue { property, value } => {
+                write!(f, "invalid value '{}' for '{}' dropped", value, property)
+            }
+            ParseErrorKind::UnsupportedAtRule(name) => {
+                write!(f, "unsupported at-rule '@{}' skipped", name)
+            }
+            ParseErrorKind::UnexpectedEof => write!(f, "unexpected end of stylesheet"),
+        }
+    }
+}
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}:{}: {}", self.line, self.column, self.kind)
+    }
+}
+
+/// Parses a comma-separated list of selectors, such as `p.note, #main`.
+/// https://drafts.csswg.org/selectors/#parse-selector
+pub fn parse_selector_list(source: &str) -> Result<Vec<Selector>, InvalidSelector> {
+    let mut parser = Parser::new(source.to_string());
+    let invalid = || InvalidSelector(source.to_string());
+    let mut selectors = Vec::new();
+    loop {
+        parser.consume_whitespace();
+        let start = parser.pos;
+        let selector = parser.parse_simple_selector();
+        let empty_name = |name: &String| name.is_empty();
+        if parser.pos == start
+            || selector.id.as_ref().is_some_and(empty_name)
+            || selector.class.iter().any(empty_name)
+        {
+            return Err(invalid());
+        }
+        selectors.push(Selector::Simple(selector));
+
+        parser.consume_whitespace();
+        if parser.eof() {
+            return Ok(selectors);
+        }
+        if parser.consume_char() != Some(',') {
+            return Err(invalid());
+        }
+    }
+}
 
-    cssom::stylesheet(parser.parse_rules())
+/// A selector list that could not be parsed.
+#[derive(Debug, Clone, PartialEq)]
+pub struct InvalidSelector(pub String);
+
+impl fmt::Display for InvalidSelector {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "'{}' is not a valid selector", self.0)
+    }
 }
 
+impl std::error::Error for InvalidSelector {}
+
 pub struct Parser {
     pos: usize,
     input: String,
+    errors: Vec<ParseError>,
+    /// The byte offset of the start of each line, to find the line of an error.
+    line_starts: Vec<usize>,
 }
 
 impl Parser {
     // Create a new parser struct
     fn new(input: String) -> Self {
-        Parser { pos: 0, input }
+        let line_starts = std::iter::once(0)
+            .chain(input.match_indices('\n').map(|(i, _)| i + 1))
+            .collect();
+        Parser {
+            pos: 0,
+            input,
+            errors: Vec::new(),
+            line_starts,
+        }
     }
 
     /// Parses a list of CSS rules.
+    /// https://www.w3.org/TR/css-syntax-3/#consume-list-of-rules
     fn parse_rules(&mut self) -> Vec<Rule> {
         let mut rules = Vec::new();
         loop {
             self.consume_whitespace();
-            if self.eof() {
-                break;
+            // HTML comment markers are allowed around a stylesheet, for old browsers.
+            if self.input[self.pos..].starts_with("<!--") {
+                self.pos += 4;
+                continue;
+            }
+            if self.input[self.pos..].starts_with("-->") {
+                self.pos += 3;
+                continue;
+            }
+            match self.next_char() {
+                None => break,
+                Some('@') => self.skip_at_rule(),
+                Some(_) => rules.extend(self.parse_rule()),
             }
-            rules.push(self.parse_rule());
         }
 
         rules
     }
 
-    /// Parses a single CSS rule.
-    fn parse_rule(&mut self) -> Rule {
-        cssom::rule(self.parse_selectors(), self.parse_declarations())
+    /// Parses a single CSS rule, or skips it if its selectors are not valid.
+    /// https://www.w3.org/TR/css-syntax-3/#consume-qualified-rule
+    fn parse_rule(&mut self) -> Option<Rule> {
+        let start = self.pos;
+        let prelude = self.consume_component_values(|c| c == '{');
+        if self.eof() {
+            self.error(ParseErrorKind::UnexpectedEof, self.pos);
+            return None;
+        }
+        self.consume_char();
+        match self.parse_selectors(prelude.trim()) {
+            Some(selectors) => Some(cssom::rule(selectors, self.parse_declarations())),
+            None => {
+                self.error(
+                    ParseErrorKind::InvalidSelector(prelude.trim().to_string()),
+                    start,
+                );
+                self.skip_block();
+                None
+            }
+        }
     }
 
-    /// Parses a list of CSS selectors.
-    fn parse_selectors(&mut self) -> Vec<Selector> {
-        let mut selectors = Vec::new();
-        loop {
-            selectors.push(Selector::Simple(self.parse_simple_selector()));
-            self.consume_whitespace();
-            match self.next_char() {
-                ',' => {
-                    self.consume_char();
-                    self.consume_whitespace();
-                }
-                '{' => break,
-                c => panic!("Unexpected character {} in selector list", c),
-            }
+    /// Skips an at-rule, up to its `;` or the end of its block.
+    /// https://www.w3.org/TR/css-syntax-3/#consume-at-rule
+    fn skip_at_rule(&mut self) {
+        let start = self.pos;
+        self.consume_char();
+        let name = self.parse_identifier();
+        self.consume_component_values(|c| c == ';' || c == '{');
+        if self.consume_char() == Some('{') {
+            self.skip_block();
         }
+        self.error(ParseErrorKind::UnsupportedAtRule(name), start);
+    }
+
+    /// Parses a list of CSS selectors. Returns `None` if any of them is not valid.
+    fn parse_selectors(&mut self, prelude: &str) -> Option<Vec<Selector>> {
+        let mut selectors = parse_selector_list(prelude).ok()?;
 
         // Return selectors with highest specificity first, for use in matching.
-        selectors.sort_by(|a, b| b.specificity().cmp(&a.specificity()));
-        selectors
+        selectors.sort_by_key(|s| std::cmp::Reverse(s.specificity()));
+        Some(selectors)
     }
 
     /// Parses a simple CSS selector.
     fn parse_simple_selector(&mut self) -> SimpleSelector {
         let mut selector = cssom::simple_selector(None, None, Vec::new());
 
-        while !self.eof() {
-            match self.next_char() {
+        while let Some(c) = self.next_char() {
+            match c {
                 '#' => {
                     self.consume_char();
                     selector.id = Some(self.parse_identifier());
@@ -88,94 +248,104 @@ impl Parser {
         selector
     }
 
-    /// Parses a list of CSS declarations.
+    /// Parses the declarations of a rule, up to the `}` that closes its block. Declarations
+    /// that cannot be parsed are dropped up to the next `;`.
+    /// https://www.w3.org/TR/css-syntax-3/#consume-list-of-declarations
     fn parse_declarations(&mut self) -> Vec<Declaration> {
-        assert_eq!(self.consume_char(), '{');
         let mut declarations = Vec::new();
         loop {
             self.consume_whitespace();
-            if self.next_char() == '}' {
-                self.consume_char();
-                break;
+            match self.next_char() {
+                None => {
+                    self.error(ParseErrorKind::UnexpectedEof, self.pos);
+                    break;
+                }
+                Some('}') => {
+                    self.consume_char();
+                    break;
+                }
+                Some(';') => {
+                    self.consume_char();
+                }
+                Some(_) => {
+                    let start = self.pos;
+                    // The last declaration does not need a `;`.
+                    let source = self.consume_component_values(|c| c == ';' || c == '}');
+                    match parse_declaration(source.trim()) {
+                        Ok(declaration) => declarations.push(declaration),
+                        Err(kind) => self.error(kind, start),
+                    }
+                }
             }
-            declarations.push(self.parse_declaration());
         }
 
         declarations
     }
 
-    /// Parses a single CSS declaration.
-    fn parse_declaration(&mut self) -> Declaration {
-        let property_name = self.parse_identifier();
-        self.consume_whitespace();
-        assert_eq!(self.consume_char(), ':');
-        self.consume_whitespace();
-        let value = self.parse_value();
-        self.consume_whitespace();
-        assert_eq!(self.consume_char(), ';');
-
-        cssom::declaration(property_name, value)
-    }
-
     /// Parses a CSS value.
-    fn parse_value(&mut self) -> Value {
-        match self.next_char() {
-            '0'..='9' => self.parse_length(),
+    fn parse_value(&mut self) -> Option<Value> {
+        match self.next_char()? {
+            '0'..='9' | '.' => self.parse_length(),
             '#' => self.parse_color(),
             '"' => self.parse_string(),
-            _ => Value::Keyword(self.parse_identifier()),
+            _ => {
+                let keyword = self.parse_identifier();
+                (!keyword.is_empty()).then_some(Value::Keyword(keyword))
+            }
         }
     }
 
     /// Parses a CSS string value.
-    fn parse_string(&mut self) -> Value {
+    fn parse_string(&mut self) -> Option<Value> {
         // Example: "example string"
-        assert_eq!(self.consume_char(), '"');
+        self.consume_char();
         let value = self.consume_while(|c| c != '"');
-        assert_eq!(self.consume_char(), '"');
-        Value::StringValue(value)
+        (self.consume_char() == Some('"')).then_some(Value::StringValue(value))
     }
 
     /// Parses a CSS length value.
-    fn parse_length(&mut self) -> Value {
-        Value::Length(self.parse_float(), self.parse_unit())
+    fn parse_length(&mut self) -> Option<Value> {
+        let value = self.parse_float()?;
+        // Zero needs no unit.
+        if value == 0.0 && !self.next_char().is_some_and(valid_identifier_char) {
+            return Some(Value::Length(value, Unit::Px));
+        }
+        Some(Value::Length(value, self.parse_unit()?))
     }
 
     /// Parses a floating-point number.
-    fn parse_float(&mut self) -> f32 {
-        let s = self.consume_while(|c| match c {
-            '0'..='9' | '.' => true,
-            _ => false,
-        });
-        s.parse().unwrap()
+    fn parse_float(&mut self) -> Option<f32> {
+        let s = self.consume_while(|c| matches!(c, '0'..='9' | '.'));
+        s.parse().ok()
     }
 
     /// Parses a CSS unit.
-    fn parse_unit(&mut self) -> Unit {
+    fn parse_unit(&mut self) -> Option<Unit> {
         match &*self.parse_identifier().to_ascii_lowercase() {
-            "px" => Unit::Px,
-            "rem" => Unit::Rem,
-            "em" => Unit::Em,
-            _ => panic!("unrecognized unit"),
+            "px" => Some(Unit::Px),
+            "rem" => Some(Unit::Rem),
+            "em" => Some(Unit::Em),
+            _ => None,
         }
     }
 
     /// Parses a color in CSS.
-    fn parse_color(&mut self) -> Value {
-        assert_eq!(self.consume_char(), '#');
-        Value::ColorValue(Color {
-            r: self.parse_hex_pair(),
-            g: self.parse_hex_pair(),
-            b: self.parse_hex_pair(),
+    fn parse_color(&mut self) -> Option<Value> {
+        self.consume_char();
+        Some(Value::ColorValue(Color {
+            r: self.parse_hex_pair()?,
+            g: self.parse_hex_pair()?,
+            b: self.parse_hex_pair()?,
             a: 255,
-        })
+        }))
     }
 
     /// Parses a pair of hexadecimal digits.
-    fn parse_hex_pair(&mut self) -> u8 {
-        let s = &self.input[self.pos..self.pos + 2];
+    fn parse_hex_pair(&mut self) -> Option<u8> {
+        let s = self.input.get(self.pos..self.pos + 2)?;
+        let value = u8::from_str_radix(s, 16).ok()?;
         self.pos += 2;
-        u8::from_str_radix(s, 16).unwrap()
+        Some(value)
     }
 
     /// Parses a CSS identifier.
@@ -183,9 +353,62 @@ impl Parser {
         self.consume_while(valid_identifier_char)
     }
 
-    /// Consumes whitespace characters.
+    /// Consumes whitespace characters and comments.
     fn consume_whitespace(&mut self) {
-        self.consume_while(char::is_whitespace);
+        loop {
+            self.consume_while(char::is_whitespace);
+            if !self.input[self.pos..].starts_with("/*") {
+                break;
+            }
+            self.pos = match self.input[self.pos + 2..].find("*/") {
+                Some(end) => self.pos + 2 + end + 2,
+                None => self.input.len(),
+            };
+        }
+    }
+
+    /// Consumes source up to a character that matches `stop` outside any brackets, strings
+    /// or comments, and returns it. The stop character itself is not consumed.
+    /// https://www.w3.org/TR/css-syntax-3/#consume-component-value
+    fn consume_component_values<F>(&mut self, stop: F) -> String
+    where
+        F: Fn(char) -> bool,
+    {
+        let start = self.pos;
+        let mut closers = Vec::new();
+        while let Some(c) = self.next_char() {
+            if closers.is_empty() && stop(c) {
+                break;
+            }
+            match c {
+                '{' => closers.push('}'),
+                '(' => closers.push(')'),
+                '[' => closers.push(']'),
+                '}' | ')' | ']' if closers.last() == Some(&c) => {
+                    closers.pop();
+                }
+                '"' | '\'' => {
+                    self.consume_char();
+                    self.consume_while(|next| next != c && next != '\n');
+                }
+                '/' if self.input[self.pos..].starts_with("/*") => {
+                    self.consume_whitespace();
+                    continue;
+                }
+                _ => {}
+            }
+            self.consume_char();
+        }
+
+        self.input[start..self.pos].to_string()
+    }
+
+    /// Skips the rest of a block, up to and including the `}` that closes it.
+    fn skip_block(&mut self) {
+        self.consume_component_values(|c| c == '}');
+        if self.consume_char().is_none() {
+            self.error(ParseErrorKind::UnexpectedEof, self.pos);
+        }
     }
 
     /// Consumes characters while a condition is met.
@@ -194,38 +417,74 @@ impl Parser {
         F: Fn(char) -> bool,
     {
         let mut result = String::new();
-        while !self.eof() && test(self.next_char()) {
-            result.push(self.consume_char());
+        while let Some(c) = self.next_char().filter(|&c| test(c)) {
+            self.consume_char();
+            result.push(c);
         }
 
         result
     }
 
     /// Consumes a single character and advances the position.
-    fn consume_char(&mut self) -> char {
-        let mut iter = self.input[self.pos..].char_indices();
-        let (_, cur_char) = iter.next().unwrap();
-        let (next_pos, _) = iter.next().unwrap_or((1, ' '));
-        self.pos += next_pos;
+    fn consume_char(&mut self) -> Option<char> {
+        let c = self.next_char()?;
+        self.pos += c.len_utf8();
 
-        cur_char
+        Some(c)
     }
 
     /// Returns the next character without consuming it.
-    fn next_char(&self) -> char {
-        self.input[self.pos..].chars().next().unwrap()
+    fn next_char(&self) -> Option<char> {
+        self.input[self.pos..].chars().next()
     }
 
     /// Checks if the end of the input is reached.
     fn eof(&self) -> bool {
         self.pos >= self.input.len()
     }
+
+    /// Record an error for the part of the source that starts at `offset`.
+    fn error(&mut self, kind: ParseErrorKind, offset: usize) {
+        let line = self.line_starts.partition_point(|&start| start <= offset);
+        // Count columns on from the last error when it is earlier on the same line, so that a
+        // long line is not counted again for every error on it.
+        let (from, column) = match self.errors.last() {
+            Some(last) if last.line == line && last.offset <= offset => (last.offset, last.column),
+            _ => (self.line_starts[line - 1], 1),
+        };
+        self.errors.push(ParseError {
+            kind,
+            offset,
+            line,
+            column: column + self.input[from..offset].chars().count(),
+        });
+    }
+}
+
+/// Parses a single CSS declaration, such as `margin: 10px`, without its `;`.
+/// https://www.w3.org/TR/css-syntax-3/#consume-declaration
+fn parse_declaration(source: &str) -> Result<Declaration, ParseErrorKind> {
+    let mut parser = Parser::new(source.to_string());
+    let property_name = parser.parse_identifier();
+    parser.consume_whitespace();
+    if property_name.is_empty() || parser.consume_char() != Some(':') {
+        return Err(ParseErrorKind::InvalidDeclaration(source.to_string()));
+    }
+    parser.consume_whitespace();
+
+    let value_start = parser.pos;
+    let value = parser.parse_value();
+    parser.consume_whitespace();
+    match value {
+        Some(value) if parser.eof() => Ok(cssom::declaration(property_name, value)),
+        _ => Err(ParseErrorKind::InvalidValue {
+            property: property_name,
+            value: source[value_start..].to_string(),
+        }),
+    }
 }
 
 /// Checks if a character is a valid identifier character in CSS.
 fn valid_identifier_char(c: char) -> bool {
-    match c {
-        'a'..='z' | 'A'..='Z' | '0'..='9' | '-' | '_' => true,
-        _ => false,
-    }
+    matches!(c, 'a'..='z' | 'A'..='Z' | '0'..='9' | '-' | '_')
 }
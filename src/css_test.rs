@@ -144,4 +144,94 @@ mod tests {
             cssom::Value::StringValue("Hello, World!".to_string())
         );
     }
+
+    #[test]
+    fn test_parse_selector_list() {
+        let selectors = css::parse_selector_list("div.note , #main").unwrap();
+
+        assert_eq!(
+            selectors,
+            vec![
+                cssom::Selector::Simple(cssom::SimpleSelector {
+                    tag_name: Some("div".to_string()),
+                    id: None,
+                    class: vec!["note".to_string()],
+                }),
+                cssom::Selector::Simple(cssom::SimpleSelector {
+                    tag_name: None,
+                    id: Some("main".to_string()),
+                    class: vec![],
+                }),
+            ]
+        );
+        assert_eq!(
+            css::parse_selector_list("div ~ p"),
+            Err(css::InvalidSelector("div ~ p".to_string()))
+        );
+    }
+
+    #[test]
+    fn test_recover_from_invalid_declarations_and_rules() {
+        let result = css::parse_with_errors(
+            "p { width: 10pt; color: #ff0000; margin }\n\
+             div ~ p { a: { b; } c: 1px }\n\
+             @media print { p { color: red; } }\n\
+             h1 { margin: 0; padding: 2px }"
+                .to_string(),
+        );
+
+        let declarations: Vec<Vec<String>> = result
+            .stylesheet
+            .rules
+            .iter()
+            .map(|rule| {
+                rule.declarations
+                    .iter()
+                    .map(|declaration| declaration.name.clone())
+                    .collect()
+            })
+            .collect();
+        assert_eq!(declarations, vec![vec!["color"], vec!["margin", "padding"]]);
+        assert_eq!(
+            result.stylesheet.rules[1].declarations[0].value,
+            cssom::Value::Length(0.0, cssom::Unit::Px)
+        );
+        let kinds: Vec<css::ParseErrorKind> =
+            result.errors.iter().map(|e| e.kind.clone()).collect();
+        assert_eq!(
+            kinds,
+            vec![
+                css::ParseErrorKind::InvalidValue {
+                    property: "width".to_string(),
+                    value: "10pt".to_string(),
+                },
+                css::ParseErrorKind::InvalidDeclaration("margin".to_string()),
+                css::ParseErrorKind::InvalidSelector("div ~ p".to_string()),
+                css::ParseErrorKind::UnsupportedAtRule("media".to_string()),
+            ]
+        );
+        assert_eq!(result.errors[2].to_string().split(':').next(), Some("2"));
+        assert_eq!((result.errors[1].line, result.errors[1].column), (1, 34));
+    }
+
+    #[test]
+    fn test_errors_on_a_long_line() {
+        let count = 20_000;
+        let result = css::parse_with_errors(format!("\n{}", "p { é; }".repeat(count)));
+
+        assert_eq!(result.errors.len(), count);
+        let last = result.errors.last().unwrap();
+        assert_eq!((last.line, last.column), (2, 8 * (count - 1) + 5));
+    }
+
+    #[test]
+    fn test_unexpected_eof() {
+        let result = css::parse_with_errors("p { color: red; /* x".to_string());
+        assert_eq!(result.stylesheet.rules.len(), 1);
+        assert_eq!(result.errors[0].kind, css::ParseErrorKind::UnexpectedEof);
+
+        for source in ["p", "#", "p { width: 1.2.3px", "p { color: #ff", "{ } }"] {
+            css::parse_with_errors(source.to_string());
+        }
+    }
 }
@@ -1,24 +1,26 @@
+use serde::{Deserialize, Serialize};
+
 /// Represents a parsed stylesheet with rules.
-#[derive(Debug)]
+#[derive(Debug, Serialize, Deserialize)]
 pub struct Stylesheet {
     pub rules: Vec<Rule>,
 }
 
 /// Represents a CSS rule with selectors and declarations.
-#[derive(Debug)]
+#[derive(Debug, Serialize, Deserialize)]
 pub struct Rule {
     pub selectors: Vec<Selector>,
     pub declarations: Vec<Declaration>,
 }
 
 /// Represents a CSS selector.
-#[derive(Debug, PartialEq)]
+#[derive(Debug, PartialEq, Serialize, Deserialize)]
 pub enum Selector {
     Simple(SimpleSelector),
 }
 
 /// Represents a simple CSS selector with tag name, id, and class.
-#[derive(Debug, PartialEq)]
+#[derive(Debug, PartialEq, Serialize, Deserialize)]
 pub struct SimpleSelector {
     pub tag_name: Option<String>,
     pub id: Option<String>,
@@ -26,14 +28,14 @@ pub struct SimpleSelector {
 }
 
 /// Represents a CSS declaration with a property name and value.
-#[derive(Debug, PartialEq)]
+#[derive(Debug, PartialEq, Serialize, Deserialize)]
 pub struct Declaration {
     pub name: String,
     pub value: Value,
 }
 
 /// Represents a CSS value.
-#[derive(Debug, Clone, PartialEq)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub enum Value {
     Keyword(String),
     Length(f32, Unit),
@@ -42,7 +44,7 @@ pub enum Value {
 }
 
 /// Represents a CSS unit.
-#[derive(Debug, Clone, PartialEq)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub enum Unit {
     Px,
     Rem,
@@ -50,7 +52,7 @@ pub enum Unit {
 }
 
 /// Represents a color in CSS.
-#[derive(Debug, Clone, PartialEq, Default)]
+#[derive(Debug, Clone, PartialEq, Default, Serialize, Deserialize)]
 pub struct Color {
     pub r: u8,
     pub g: u8,
@@ -110,4 +110,19 @@ mod tests {
             })
         );
     }
+
+    #[test]
+    fn test_stylesheet_json_round_trip() {
+        let sheet = crate::css::parse("h1, .title { color: #ff0000; margin: 2em; }".to_string());
+
+        let json = serde_json::to_string(&sheet).unwrap();
+        let parsed: crate::cssom::Stylesheet = serde_json::from_str(&json).unwrap();
+
+        assert_eq!(parsed.rules[0].selectors, sheet.rules[0].selectors);
+        assert_eq!(parsed.rules[0].declarations, sheet.rules[0].declarations);
+        assert_eq!(
+            serde_json::to_value(&sheet.rules[0].declarations[1].value).unwrap(),
+            serde_json::json!({ "Length": [2.0, "Em"] })
+        );
+    }
 }
@@ -0,0 +1,1130 @@
+//! A DOM tree stored in an arena, where every node knows its parent and siblings.
+// https://dom.spec.whatwg.org/#trees
+
+use std::cmp::Ordering;
+use std::collections::HashMap;
+use std::fmt;
+
+use crate::dom::{self, AttrMap, ElementData, Namespace, Node, NodeType, Span, TokenList};
+use crate::event::{Event, EventPhase};
+
+/// Identifies a node in a `Document`. Ids stay valid as long as the document does.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct NodeId(usize);
+
+/// A node and its links to the nodes around it.
+#[derive(Debug, Clone)]
+struct NodeData {
+    node_type: NodeType,
+    span: Option<Span>,
+    parent: Option<NodeId>,
+    first_child: Option<NodeId>,
+    last_child: Option<NodeId>,
+    previous_sibling: Option<NodeId>,
+    next_sibling: Option<NodeId>,
+    /// The `DocumentFragment` holding the contents of a `<template>`.
+    template_contents: Option<NodeId>,
+}
+
+/// Where `Document::add_tree` attaches a node.
+enum Place {
+    Child(NodeId),
+    TemplateContents(NodeId),
+}
+
+/// A tree of nodes with constant-time access to each node's parent, first and last child,
+/// and previous and next sibling.
+///
+/// Build one from a `dom::Node` tree, such as one made with `dom::elem` and `dom::text`:
+///
+/// ```ignore
+/// let document = Document::new(dom::elem("p".to_string(), attrs, vec![dom::text(text)]));
+/// let text = document.first_child(document.root()).unwrap();
+/// assert_eq!(document.parent(text), Some(document.root()));
+/// ```
+///
+/// The tree can be changed with methods like `append_child` and `set_attribute`. Each change
+/// is described by a `MutationRecord`, passed to the callbacks registered with `observe`.
+///
+/// Events are sent to nodes with `dispatch_event`, and passed to the listeners registered
+/// with `add_event_listener` on the way.
+pub struct Document {
+    nodes: Vec<NodeData>,
+    root: NodeId,
+    observers: Vec<(ObserverId, Observer)>,
+    next_observer: usize,
+    listeners: Vec<EventListener>,
+    next_listener: usize,
+    indexes: Indexes,
+}
+
+/// Elements in the tree by id, class name and tag name, in no particular order.
+#[derive(Debug, Default)]
+struct Indexes {
+    ids: HashMap<String, Vec<NodeId>>,
+    classes: HashMap<String, Vec<NodeId>>,
+    tags: HashMap<String, Vec<NodeId>>,
+}
+
+impl Indexes {
+    fn update(&mut self, id: NodeId, element: &ElementData, add: bool) {
+        let update = |index: &mut HashMap<String, Vec<NodeId>>, key: &str| {
+            if add {
+                index.entry(key.to_string()).or_default().push(id);
+            } else if let Some(ids) = index.get_mut(key) {
+                ids.retain(|&other| other != id);
+                if ids.is_empty() {
+                    index.remove(key);
+                }
+            }
+        };
+        if let Some(element_id) = element.id().filter(|element_id| !element_id.is_empty()) {
+            update(&mut self.ids, element_id);
+        }
+        for class in class_names(element) {
+            update(&mut self.classes, class);
+        }
+        update(&mut self.tags, &element.tag_name);
+    }
+}
+
+impl fmt::Debug for Document {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("Document")
+            .field("nodes", &self.nodes)
+            .field("root", &self.root)
+            .finish_non_exhaustive()
+    }
+}
+
+/// A callback registered with `Document::observe`.
+type Observer = Box<dyn FnMut(&MutationRecord)>;
+
+/// Identifies a callback registered with `Document::observe`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ObserverId(usize);
+
+/// A callback registered with `Document::add_event_listener`.
+/// https://dom.spec.whatwg.org/#concept-event-listener
+struct EventListener {
+    id: ListenerId,
+    target: NodeId,
+    event_type: String,
+    capture: bool,
+    callback: Box<dyn FnMut(&mut Event)>,
+}
+
+/// Identifies a callback registered with `Document::add_event_listener`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ListenerId(usize);
+
+/// What kind of change a `MutationRecord` describes.
+/// https://dom.spec.whatwg.org/#dom-mutationrecord-type
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MutationType {
+    /// Children were added or removed.
+    ChildList,
+    /// An attribute was set or removed.
+    Attributes,
+    /// The data of a text node, comment or processing instruction changed.
+    CharacterData,
+}
+
+/// A change to a document.
+/// https://dom.spec.whatwg.org/#interface-mutationrecord
+#[derive(Debug, Clone, PartialEq)]
+pub struct MutationRecord {
+    pub kind: MutationType,
+    /// The node whose children, attributes or data changed.
+    pub target: NodeId,
+    pub added_nodes: Vec<NodeId>,
+    pub removed_nodes: Vec<NodeId>,
+    /// The siblings around the added or removed nodes.
+    pub previous_sibling: Option<NodeId>,
+    pub next_sibling: Option<NodeId>,
+    pub attribute_name: Option<String>,
+    /// The attribute value or data before the change.
+    pub old_value: Option<String>,
+}
+
+impl MutationRecord {
+    fn new(kind: MutationType, target: NodeId) -> Self {
+        MutationRecord {
+            kind,
+            target,
+            added_nodes: Vec::new(),
+            removed_nodes: Vec::new(),
+            previous_sibling: None,
+            next_sibling: None,
+            attribute_name: None,
+            old_value: None,
+        }
+    }
+}
+
+/// Why a change to a document was refused.
+/// https://webidl.spec.whatwg.org/#idl-DOMException-error-names
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DomError {
+    /// The change would put a node somewhere it cannot be, such as inside itself or inside a
+    /// text node.
+    HierarchyRequest,
+    /// A node that should be a child of the parent is not.
+    NotFound,
+    /// The node is not the right type, such as setting an attribute on a text node.
+    InvalidNodeType,
+}
+
+impl fmt::Display for DomError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            DomError::HierarchyRequest => write!(f, "HierarchyRequestError"),
+            DomError::NotFound => write!(f, "NotFoundError"),
+            DomError::InvalidNodeType => write!(f, "InvalidNodeTypeError"),
+        }
+    }
+}
+
+impl std::error::Error for DomError {}
+
+impl Document {
+    /// Move a `dom::Node` tree into an arena.
+    pub fn new(root: Node) -> Self {
+        let mut document = Document {
+            nodes: Vec::new(),
+            root: NodeId(0),
+            observers: Vec::new(),
+            next_observer: 0,
+            listeners: Vec::new(),
+            next_listener: 0,
+            indexes: Indexes::default(),
+        };
+        document.root = document.add_tree(root);
+        document.index_subtree(document.root, true);
+        document
+    }
+
+    /// The root of the tree, such as the `html` element of a parsed page.
+    pub fn root(&self) -> NodeId {
+        self.root
+    }
+
+    /// The number of nodes in the arena.
+    pub fn len(&self) -> usize {
+        self.nodes.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.nodes.is_empty()
+    }
+
+    pub fn node_type(&self, id: NodeId) -> &NodeType {
+        &self.nodes[id.0].node_type
+    }
+
+    /// Changes made through this are not reported to observers, and changes to `id` and
+    /// `class` attributes are not indexed.
+    pub fn node_type_mut(&mut self, id: NodeId) -> &mut NodeType {
+        &mut self.nodes[id.0].node_type
+    }
+
+    /// The element data of `id`, if it is an element.
+    pub fn element(&self, id: NodeId) -> Option<&ElementData> {
+        match self.node_type(id) {
+            NodeType::Element(element) => Some(element),
+            _ => None,
+        }
+    }
+
+    /// Where the node came from in the source, if it was parsed.
+    pub fn span(&self, id: NodeId) -> Option<Span> {
+        self.nodes[id.0].span
+    }
+
+    /// https://dom.spec.whatwg.org/#dom-node-parentnode
+    pub fn parent(&self, id: NodeId) -> Option<NodeId> {
+        self.nodes[id.0].parent
+    }
+
+    /// https://dom.spec.whatwg.org/#dom-node-firstchild
+    pub fn first_child(&self, id: NodeId) -> Option<NodeId> {
+        self.nodes[id.0].first_child
+    }
+
+    /// https://dom.spec.whatwg.org/#dom-node-lastchild
+    pub fn last_child(&self, id: NodeId) -> Option<NodeId> {
+        self.nodes[id.0].last_child
+    }
+
+    /// https://dom.spec.whatwg.org/#dom-node-previoussibling
+    pub fn previous_sibling(&self, id: NodeId) -> Option<NodeId> {
+        self.nodes[id.0].previous_sibling
+    }
+
+    /// https://dom.spec.whatwg.org/#dom-node-nextsibling
+    pub fn next_sibling(&self, id: NodeId) -> Option<NodeId> {
+        self.nodes[id.0].next_sibling
+    }
+
+    /// The children of `id`, in order.
+    pub fn children(&self, id: NodeId) -> Siblings<'_> {
+        Siblings {
+            document: self,
+            next: self.first_child(id),
+            forward: true,
+        }
+    }
+
+    /// The siblings after `id`, nearest first.
+    pub fn following_siblings(&self, id: NodeId) -> Siblings<'_> {
+        Siblings {
+            document: self,
+            next: self.next_sibling(id),
+            forward: true,
+        }
+    }
+
+    /// The siblings before `id`, nearest first.
+    pub fn preceding_siblings(&self, id: NodeId) -> Siblings<'_> {
+        Siblings {
+            document: self,
+            next: self.previous_sibling(id),
+            forward: false,
+        }
+    }
+
+    /// The parent of `id`, its parent, and so on up to the root.
+    pub fn ancestors(&self, id: NodeId) -> Ancestors<'_> {
+        Ancestors {
+            document: self,
+            next: self.parent(id),
+        }
+    }
+
+    /// The nodes below `id`, in document order (depth first, parents before children).
+    pub fn descendants(&self, id: NodeId) -> Descendants<'_> {
+        Descendants {
+            document: self,
+            root: id,
+            next: self.first_child(id),
+        }
+    }
+
+    /// Copy the subtree at `id` back out into a `dom::Node` tree.
+    pub fn to_node(&self, id: NodeId) -> Node {
+        // Copy children before their parents, from a stack rather than by recursion, so the
+        // tree can be any depth.
+        let mut copies: HashMap<NodeId, Node> = HashMap::new();
+        let mut stack = vec![(id, false)];
+        while let Some((id, children_copied)) = stack.pop() {
+            let contents = self.template_contents(id);
+            if !children_copied {
+                stack.push((id, true));
+                stack.extend(
+                    self.children(id)
+                        .chain(contents)
+                        .map(|child| (child, false)),
+                );
+                continue;
+            }
+            let mut node_type = self.node_type(id).clone();
+            if let (NodeType::Element(element), Some(contents)) = (&mut node_type, contents) {
+                element.template_contents = copies.remove(&contents).map(Box::new);
+            }
+            let node = Node {
+                children: self
+                    .children(id)
+                    .filter_map(|child| copies.remove(&child))
+                    .collect(),
+                node_type,
+                span: self.span(id),
+            };
+            copies.insert(id, node);
+        }
+        copies.remove(&id).expect("the root is copied last")
+    }
+
+    /// The `DocumentFragment` that holds the contents of a `<template>` element. The contents
+    /// are not part of the tree, so they are not found by `get_element_by_id` and the like.
+    /// https://html.spec.whatwg.org/multipage/scripting.html#dom-template-content
+    pub fn template_contents(&self, template: NodeId) -> Option<NodeId> {
+        self.nodes[template.0].template_contents
+    }
+
+    /// Copy a node, without a parent. A deep copy also copies its descendants and, for a
+    /// template, its contents.
+    /// https://dom.spec.whatwg.org/#dom-node-clonenode
+    pub fn clone_node(&mut self, id: NodeId, deep: bool) -> NodeId {
+        let mut node = if deep {
+            self.to_node(id)
+        } else {
+            Node {
+                children: Vec::new(),
+                node_type: self.node_type(id).clone(),
+                span: self.span(id),
+            }
+        };
+        if let NodeType::Element(element) = &mut node.node_type {
+            if !deep && self.template_contents(id).is_some() {
+                element.template_contents = Some(Box::new(dom::document_fragment(Vec::new())));
+            }
+        }
+        self.add_tree(node)
+    }
+
+    /// Copy the contents of a `<template>` into a new `DocumentFragment`, ready to be inserted
+    /// into the tree with `append_child` or `insert_before`.
+    /// https://html.spec.whatwg.org/multipage/scripting.html#the-template-element
+    pub fn clone_template_contents(&mut self, template: NodeId) -> Result<NodeId, DomError> {
+        let contents = self
+            .template_contents(template)
+            .ok_or(DomError::InvalidNodeType)?;
+        Ok(self.clone_node(contents, true))
+    }
+
+    /// The text of a node: its data, or for an element the text of every descendant text
+    /// node, in order.
+    /// https://dom.spec.whatwg.org/#dom-node-textcontent
+    pub fn text_content(&self, id: NodeId) -> String {
+        match self.node_type(id) {
+            NodeType::Element(_) | NodeType::DocumentFragment => self
+                .descendants(id)
+                .filter_map(|id| match self.node_type(id) {
+                    NodeType::Text(data) => Some(data.as_str()),
+                    _ => None,
+                })
+                .collect(),
+            NodeType::Text(data) | NodeType::Comment(data) => data.clone(),
+            NodeType::ProcessingInstruction(pi) => pi.data.clone(),
+            NodeType::Doctype(_) => String::new(),
+        }
+    }
+
+    // Mutation.
+
+    /// Add a copy of a `dom::Node` tree to the document, without a parent. Use
+    /// `append_child` or `insert_before` to put it in the tree.
+    pub fn create_node(&mut self, node: Node) -> NodeId {
+        self.add_tree(node)
+    }
+
+    /// Create an HTML element without a parent.
+    pub fn create_element(&mut self, name: &str, attributes: AttrMap) -> NodeId {
+        self.add_tree(dom::elem(name.to_string(), attributes, Vec::new()))
+    }
+
+    /// Create a text node without a parent.
+    pub fn create_text(&mut self, data: &str) -> NodeId {
+        self.add_tree(dom::text(data.to_string()))
+    }
+
+    /// Call `callback` with a record of every later change to the document.
+    /// https://dom.spec.whatwg.org/#dom-mutationobserver-observe
+    pub fn observe<F>(&mut self, callback: F) -> ObserverId
+    where
+        F: FnMut(&MutationRecord) + 'static,
+    {
+        let id = ObserverId(self.next_observer);
+        self.next_observer += 1;
+        self.observers.push((id, Box::new(callback)));
+        id
+    }
+
+    /// Stop calling a callback registered with `observe`.
+    pub fn unobserve(&mut self, id: ObserverId) {
+        self.observers.retain(|(observer, _)| *observer != id);
+    }
+
+    /// Add `child` as the last child of `parent`, moving it from where it was.
+    /// https://dom.spec.whatwg.org/#dom-node-appendchild
+    pub fn append_child(&mut self, parent: NodeId, child: NodeId) -> Result<(), DomError> {
+        self.insert_before(parent, child, None)
+    }
+
+    /// Add `child` to `parent` just before `reference`, or last if there is no reference,
+    /// moving it from where it was. The children of a `DocumentFragment` are moved instead
+    /// of the fragment itself.
+    /// https://dom.spec.whatwg.org/#dom-node-insertbefore
+    pub fn insert_before(
+        &mut self,
+        parent: NodeId,
+        child: NodeId,
+        reference: Option<NodeId>,
+    ) -> Result<(), DomError> {
+        self.check_insert(parent, child, reference)?;
+        // Inserting a node just before itself leaves it where it is.
+        let reference = match reference {
+            Some(reference) if reference == child => self.next_sibling(child),
+            reference => reference,
+        };
+        // As in the spec, the previous sibling is taken before `child` is removed from where it
+        // was, so it can be `child` itself when a node moves within its parent.
+        let previous_sibling = match reference {
+            Some(reference) => self.previous_sibling(reference),
+            None => self.last_child(parent),
+        };
+        let nodes = self.take_for_insertion(child);
+        if nodes.is_empty() {
+            return Ok(());
+        }
+        let mut record = MutationRecord::new(MutationType::ChildList, parent);
+        record.previous_sibling = previous_sibling;
+        for &node in &nodes {
+            self.attach(parent, node, reference);
+        }
+        record.added_nodes = nodes;
+        record.next_sibling = reference;
+        self.notify(record);
+        Ok(())
+    }
+
+    /// Remove `child` from `parent`. The node stays in the document, without a parent, so it
+    /// can be inserted again.
+    /// https://dom.spec.whatwg.org/#dom-node-removechild
+    pub fn remove_child(&mut self, parent: NodeId, child: NodeId) -> Result<(), DomError> {
+        if self.parent(child) != Some(parent) {
+            return Err(DomError::NotFound);
+        }
+        self.remove(child);
+        Ok(())
+    }
+
+    /// Put `new_child` in the place of `old_child`, a child of `parent`.
+    /// https://dom.spec.whatwg.org/#dom-node-replacechild
+    pub fn replace_child(
+        &mut self,
+        parent: NodeId,
+        new_child: NodeId,
+        old_child: NodeId,
+    ) -> Result<(), DomError> {
+        if self.parent(old_child) != Some(parent) {
+            return Err(DomError::NotFound);
+        }
+        self.check_insert(parent, new_child, None)?;
+        if new_child == old_child {
+            return Ok(());
+        }
+
+        let mut reference = self.next_sibling(old_child);
+        if reference == Some(new_child) {
+            reference = self.next_sibling(new_child);
+        }
+        let nodes = self.take_for_insertion(new_child);
+        let previous_sibling = self.previous_sibling(old_child);
+        self.detach(old_child);
+        for &node in &nodes {
+            self.attach(parent, node, reference);
+        }
+
+        let mut record = MutationRecord::new(MutationType::ChildList, parent);
+        record.added_nodes = nodes;
+        record.removed_nodes.push(old_child);
+        record.previous_sibling = previous_sibling;
+        record.next_sibling = reference;
+        self.notify(record);
+        Ok(())
+    }
+
+    /// Set an attribute of an element. Names are lowercased on HTML elements.
+    /// https://dom.spec.whatwg.org/#dom-element-setattribute
+    pub fn set_attribute(
+        &mut self,
+        element: NodeId,
+        name: &str,
+        value: &str,
+    ) -> Result<(), DomError> {
+        let name = match self.element_mut(element)?.namespace {
+            Namespace::Html => name.to_ascii_lowercase(),
+            _ => name.to_string(),
+        };
+        self.index_attribute_change(element, &name, false);
+        let data = self.element_mut(element)?;
+        // The value no longer comes from the source.
+        data.attribute_spans.remove(&name);
+        let old_value = data.attributes.insert(name.clone(), value.to_string());
+        self.index_attribute_change(element, &name, true);
+
+        let mut record = MutationRecord::new(MutationType::Attributes, element);
+        record.attribute_name = Some(name);
+        record.old_value = old_value;
+        self.notify(record);
+        Ok(())
+    }
+
+    /// Remove an attribute of an element, if it has it.
+    /// https://dom.spec.whatwg.org/#dom-element-removeattribute
+    pub fn remove_attribute(&mut self, element: NodeId, name: &str) -> Result<(), DomError> {
+        let name = match self.element_mut(element)?.namespace {
+            Namespace::Html => name.to_ascii_lowercase(),
+            _ => name.to_string(),
+        };
+        self.index_attribute_change(element, &name, false);
+        let data = self.element_mut(element)?;
+        data.attribute_spans.remove(&name);
+        let old_value = data.attributes.remove(&name);
+        self.index_attribute_change(element, &name, true);
+        if let Some(old_value) = old_value {
+            let mut record = MutationRecord::new(MutationType::Attributes, element);
+            record.attribute_name = Some(name);
+            record.old_value = Some(old_value);
+            self.notify(record);
+        }
+        Ok(())
+    }
+
+    /// Change the tokens of an attribute such as `class` or `rel` through a `TokenList`. The
+    /// attribute is set, and observers told, only if its value changed.
+    /// https://dom.spec.whatwg.org/#interface-domtokenlist
+    pub fn update_token_list<R>(
+        &mut self,
+        element: NodeId,
+        name: &str,
+        update: impl FnOnce(&mut TokenList) -> R,
+    ) -> Result<R, DomError> {
+        let data = self.element_mut(element)?;
+        let name = match data.namespace {
+            Namespace::Html => name.to_ascii_lowercase(),
+            _ => name.to_string(),
+        };
+        let old_value = data.attributes.get(&name).cloned();
+        self.index_attribute_change(element, &name, false);
+        let data = self.element_mut(element)?;
+        let span = data.attribute_spans.get(&name).copied();
+        let result = update(&mut data.token_list(&name));
+        let changed = data.attributes.get(&name) != old_value.as_ref();
+        if let (false, Some(span)) = (changed, span) {
+            // The value is the one from the source after all.
+            data.attribute_spans.insert(name.clone(), span);
+        }
+        self.index_attribute_change(element, &name, true);
+
+        if changed {
+            let mut record = MutationRecord::new(MutationType::Attributes, element);
+            record.attribute_name = Some(name);
+            record.old_value = old_value;
+            self.notify(record);
+        }
+        Ok(result)
+    }
+
+    /// Set the text of a node. The data of a text node, comment or processing instruction is
+    /// replaced; an element's children are all replaced by a single text node.
+    /// https://dom.spec.whatwg.org/#dom-node-textcontent
+    pub fn set_text(&mut self, id: NodeId, text: &str) -> Result<(), DomError> {
+        let data = match &mut self.nodes[id.0].node_type {
+            NodeType::Text(data) | NodeType::Comment(data) => data,
+            NodeType::ProcessingInstruction(pi) => &mut pi.data,
+            NodeType::Element(_) | NodeType::DocumentFragment => {
+                return self.replace_children_with_text(id, text)
+            }
+            NodeType::Doctype(_) => return Err(DomError::InvalidNodeType),
+        };
+        let old_value = std::mem::replace(data, text.to_string());
+
+        let mut record = MutationRecord::new(MutationType::CharacterData, id);
+        record.old_value = Some(old_value);
+        self.notify(record);
+        Ok(())
+    }
+
+    fn replace_children_with_text(&mut self, element: NodeId, text: &str) -> Result<(), DomError> {
+        let removed: Vec<NodeId> = self.children(element).collect();
+        for &child in &removed {
+            self.detach(child);
+        }
+        let mut record = MutationRecord::new(MutationType::ChildList, element);
+        if !text.is_empty() {
+            let child = self.create_text(text);
+            self.attach(element, child, None);
+            record.added_nodes.push(child);
+        }
+        record.removed_nodes = removed;
+        if !record.added_nodes.is_empty() || !record.removed_nodes.is_empty() {
+            self.notify(record);
+        }
+        Ok(())
+    }
+
+    fn element_mut(&mut self, id: NodeId) -> Result<&mut ElementData, DomError> {
+        match &mut self.nodes[id.0].node_type {
+            NodeType::Element(element) => Ok(element),
+            _ => Err(DomError::InvalidNodeType),
+        }
+    }
+
+    /// Check that `child` may be inserted into `parent` before `reference`.
+    /// https://dom.spec.whatwg.org/#concept-node-ensure-pre-insertion-validity
+    fn check_insert(
+        &self,
+        parent: NodeId,
+        child: NodeId,
+        reference: Option<NodeId>,
+    ) -> Result<(), DomError> {
+        if !matches!(
+            self.node_type(parent),
+            NodeType::Element(_) | NodeType::DocumentFragment
+        ) || matches!(self.node_type(child), NodeType::Doctype(_))
+            || child == parent
+            || self.ancestors(parent).any(|ancestor| ancestor == child)
+        {
+            return Err(DomError::HierarchyRequest);
+        }
+        match reference {
+            Some(reference) if self.parent(reference) != Some(parent) => Err(DomError::NotFound),
+            _ => Ok(()),
+        }
+    }
+
+    /// Detach `child` from its parent, if it has one, and report it.
+    fn remove(&mut self, child: NodeId) {
+        let Some(parent) = self.parent(child) else {
+            return;
+        };
+        let mut record = MutationRecord::new(MutationType::ChildList, parent);
+        record.removed_nodes.push(child);
+        record.previous_sibling = self.previous_sibling(child);
+        record.next_sibling = self.next_sibling(child);
+        self.detach(child);
+        self.notify(record);
+    }
+
+    /// Detach the nodes that inserting `node` adds: the node itself, or the children of a
+    /// `DocumentFragment`.
+    fn take_for_insertion(&mut self, node: NodeId) -> Vec<NodeId> {
+        if !matches!(self.node_type(node), NodeType::DocumentFragment) {
+            self.remove(node);
+            return vec![node];
+        }
+        let children: Vec<NodeId> = self.children(node).collect();
+        for &child in &children {
+            self.detach(child);
+        }
+        if !children.is_empty() {
+            let mut record = MutationRecord::new(MutationType::ChildList, node);
+            record.removed_nodes = children.clone();
+            self.notify(record);
+        }
+        children
+    }
+
+    fn notify(&mut self, record: MutationRecord) {
+        for (_, observer) in &mut self.observers {
+            observer(&record);
+        }
+    }
+
+    // Events.
+
+    /// Call `callback` with every event of type `event_type` dispatched to `target` or its
+    /// descendants. Capturing listeners are called on the way down to the event's target,
+    /// others at the target and, if the event bubbles, on the way back up.
+    /// https://dom.spec.whatwg.org/#dom-eventtarget-addeventlistener
+    pub fn add_event_listener<F>(
+        &mut self,
+        target: NodeId,
+        event_type: &str,
+        capture: bool,
+        callback: F,
+    ) -> ListenerId
+    where
+        F: FnMut(&mut Event) + 'static,
+    {
+        let id = ListenerId(self.next_listener);
+        self.next_listener += 1;
+        self.listeners.push(EventListener {
+            id,
+            target,
+            event_type: event_type.to_string(),
+            capture,
+            callback: Box::new(callback),
+        });
+        id
+    }
+
+    /// Stop calling a callback registered with `add_event_listener`.
+    /// https://dom.spec.whatwg.org/#dom-eventtarget-removeeventlistener
+    pub fn remove_event_listener(&mut self, id: ListenerId) {
+        self.listeners.retain(|listener| listener.id != id);
+    }
+
+    /// Send `event` to `target`, through the capture, target and bubble phases. Returns false
+    /// if a listener canceled the event.
+    /// https://dom.spec.whatwg.org/#concept-event-dispatch
+    pub fn dispatch_event(&mut self, target: NodeId, event: &mut Event) -> bool {
+        event.target = Some(target);
+        // The path is fixed before any listener is called.
+        let mut path: Vec<NodeId> = self.ancestors(target).collect();
+        path.reverse();
+
+        event.phase = EventPhase::Capturing;
+        for &node in &path {
+            self.invoke_listeners(node, event, true);
+        }
+        event.phase = EventPhase::AtTarget;
+        self.invoke_listeners(target, event, true);
+        self.invoke_listeners(target, event, false);
+        if event.does_bubble() {
+            event.phase = EventPhase::Bubbling;
+            for &node in path.iter().rev() {
+                self.invoke_listeners(node, event, false);
+            }
+        }
+
+        event.phase = EventPhase::None;
+        event.current_target = None;
+        event.stop_propagation = false;
+        event.stop_immediate_propagation = false;
+        !event.default_prevented()
+    }
+
+    /// https://dom.spec.whatwg.org/#concept-event-listener-inner-invoke
+    fn invoke_listeners(&mut self, node: NodeId, event: &mut Event, capture: bool) {
+        if event.stop_propagation {
+            return;
+        }
+        event.current_target = Some(node);
+        for listener in &mut self.listeners {
+            if listener.target == node
+                && listener.capture == capture
+                && listener.event_type == event.event_type()
+            {
+                (listener.callback)(event);
+                if event.stop_immediate_propagation {
+                    return;
+                }
+            }
+        }
+    }
+
+    // Indexes.
+
+    /// The first element in tree order whose id is `id`.
+    /// https://dom.spec.whatwg.org/#dom-nonelementparentnode-getelementbyid
+    pub fn get_element_by_id(&self, id: &str) -> Option<NodeId> {
+        let ids = self.indexes.ids.get(id)?;
+        ids.iter()
+            .copied()
+            .min_by(|&a, &b| self.compare_tree_order(a, b))
+    }
+
+    /// The elements that have all of the space-separated `class_names`. The collection is
+    /// live: it reflects the tree at the time it is read.
+    /// https://dom.spec.whatwg.org/#dom-document-getelementsbyclassname
+    pub fn get_elements_by_class_name(&self, class_names: &str) -> HtmlCollection {
+        HtmlCollection {
+            filter: Filter::ClassNames(
+                class_names
+                    .split_ascii_whitespace()
+                    .map(str::to_string)
+                    .collect(),
+            ),
+        }
+    }
+
+    /// The elements with the tag name `name`, or all elements for `*`. HTML elements match
+    /// in any case. The collection is live: it reflects the tree at the time it is read.
+    /// https://dom.spec.whatwg.org/#dom-document-getelementsbytagname
+    pub fn get_elements_by_tag_name(&self, name: &str) -> HtmlCollection {
+        HtmlCollection {
+            filter: Filter::TagName(name.to_string()),
+        }
+    }
+
+    /// Whether `id` is the root or one of its descendants, rather than a detached node.
+    pub fn is_connected(&self, id: NodeId) -> bool {
+        id == self.root || self.ancestors(id).any(|ancestor| ancestor == self.root)
+    }
+
+    /// Order two nodes as they appear in the tree, ancestors before their descendants.
+    /// https://dom.spec.whatwg.org/#concept-tree-order
+    pub fn compare_tree_order(&self, a: NodeId, b: NodeId) -> Ordering {
+        if a == b {
+            return Ordering::Equal;
+        }
+        let path = |id: NodeId| {
+            let mut path: Vec<NodeId> = self.ancestors(id).collect();
+            path.reverse();
+            path.push(id);
+            path
+        };
+        let (a_path, b_path) = (path(a), path(b));
+        match a_path.iter().zip(&b_path).position(|(a, b)| a != b) {
+            // Different subtrees of a common parent: whichever sibling comes first.
+            Some(i) => {
+                if self.following_siblings(a_path[i]).any(|id| id == b_path[i]) {
+                    Ordering::Less
+                } else {
+                    Ordering::Greater
+                }
+            }
+            // One is an ancestor of the other.
+            None => a_path.len().cmp(&b_path.len()),
+        }
+    }
+
+    fn index_subtree(&mut self, id: NodeId, add: bool) {
+        let ids: Vec<NodeId> = std::iter::once(id).chain(self.descendants(id)).collect();
+        for id in ids {
+            if let NodeType::Element(element) = &self.nodes[id.0].node_type {
+                self.indexes.update(id, element, add);
+            }
+        }
+    }
+
+    /// Keep the indexes up to date around a change to an attribute of `element`.
+    fn index_attribute_change(&mut self, element: NodeId, name: &str, add: bool) {
+        if matches!(name, "id" | "class") && self.is_connected(element) {
+            if let NodeType::Element(data) = &self.nodes[element.0].node_type {
+                self.indexes.update(element, data, add);
+            }
+        }
+    }
+
+    /// Add `node` and its descendants to the arena, without a parent.
+    fn add_tree(&mut self, node: Node) -> NodeId {
+        let root = NodeId(self.nodes.len());
+        // Nodes wait on a stack rather than in recursive calls, so the tree can be any depth.
+        let mut stack = vec![(node, None)];
+        while let Some((mut node, place)) = stack.pop() {
+            let id = NodeId(self.nodes.len());
+            let mut node_type = std::mem::replace(&mut node.node_type, NodeType::DocumentFragment);
+            // Template contents become a fragment of their own in the arena.
+            if let NodeType::Element(element) = &mut node_type {
+                if let Some(contents) = element.template_contents.take() {
+                    stack.push((*contents, Some(Place::TemplateContents(id))));
+                }
+            }
+            self.nodes.push(NodeData {
+                node_type,
+                span: node.span,
+                parent: None,
+                first_child: None,
+                last_child: None,
+                previous_sibling: None,
+                next_sibling: None,
+                template_contents: None,
+            });
+            match place {
+                Some(Place::Child(parent)) => self.link(parent, id, None),
+                Some(Place::TemplateContents(template)) => {
+                    self.nodes[template.0].template_contents = Some(id)
+                }
+                None => {}
+            }
+            // Reversed, so the first child comes off the stack first.
+            let children = std::mem::take(&mut node.children);
+            stack.extend(
+                children
+                    .into_iter()
+                    .rev()
+                    .map(|child| (child, Some(Place::Child(id)))),
+            );
+        }
+        root
+    }
+
+    /// Link `child` into the tree and index it, if `parent` is in the tree.
+    fn attach(&mut self, parent: NodeId, child: NodeId, before: Option<NodeId>) {
+        self.link(parent, child, before);
+        if self.is_connected(parent) {
+            self.index_subtree(child, true);
+        }
+    }
+
+    /// Unlink `child` from the tree and drop it from the indexes.
+    fn detach(&mut self, child: NodeId) {
+        if self.is_connected(child) {
+            self.index_subtree(child, false);
+        }
+        self.unlink(child);
+    }
+
+    /// Attach `child`, which has no parent, to `parent` before `before`, or else last.
+    fn link(&mut self, parent: NodeId, child: NodeId, before: Option<NodeId>) {
+        let previous = match before {
+            Some(before) => self.nodes[before.0].previous_sibling,
+            None => self.nodes[parent.0].last_child,
+        };
+        match previous {
+            Some(previous) => self.nodes[previous.0].next_sibling = Some(child),
+            None => self.nodes[parent.0].first_child = Some(child),
+        }
+        match before {
+            Some(before) => self.nodes[before.0].previous_sibling = Some(child),
+            None => self.nodes[parent.0].last_child = Some(child),
+        }
+        let data = &mut self.nodes[child.0];
+        data.parent = Some(parent);
+        data.previous_sibling = previous;
+        data.next_sibling = before;
+    }
+
+    /// Detach `child` from its parent and siblings, without reporting it.
+    fn unlink(&mut self, child: NodeId) {
+        let data = &mut self.nodes[child.0];
+        let (Some(parent), previous, next) = (
+            data.parent.take(),
+            data.previous_sibling.take(),
+            data.next_sibling.take(),
+        ) else {
+            return;
+        };
+        match previous {
+            Some(previous) => self.nodes[previous.0].next_sibling = next,
+            None => self.nodes[parent.0].first_child = next,
+        }
+        match next {
+            Some(next) => self.nodes[next.0].previous_sibling = previous,
+            None => self.nodes[parent.0].last_child = previous,
+        }
+    }
+}
+
+/// A live list of elements, read against the document each time. See
+/// `Document::get_elements_by_class_name`.
+/// https://dom.spec.whatwg.org/#interface-htmlcollection
+#[derive(Debug, Clone, PartialEq)]
+pub struct HtmlCollection {
+    filter: Filter,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Filter {
+    ClassNames(Vec<String>),
+    TagName(String),
+}
+
+impl HtmlCollection {
+    /// The elements currently in the collection, in tree order.
+    pub fn elements(&self, document: &Document) -> Vec<NodeId> {
+        let mut elements: Vec<NodeId> =
+            match &self.filter {
+                // No classes match nothing.
+                Filter::ClassNames(classes) => match classes.split_first() {
+                    Some((first, rest)) => document
+                        .indexes
+                        .classes
+                        .get(first)
+                        .into_iter()
+                        .flatten()
+                        .copied()
+                        .filter(|&id| {
+                            let element = document.element(id).unwrap();
+                            rest.iter()
+                                .all(|class| class_names(element).any(|name| name == class))
+                        })
+                        .collect(),
+                    None => Vec::new(),
+                },
+                Filter::TagName(name) if name == "*" => std::iter::once(document.root)
+                    .chain(document.descendants(document.root))
+                    .filter(|&id| document.element(id).is_some())
+                    .collect(),
+                Filter::TagName(name) => {
+                    let lowercase = name.to_ascii_lowercase();
+                    let html = document.indexes.tags.get(&lowercase).into_iter().flatten();
+                    let foreign = document.indexes.tags.get(name).into_iter().flatten();
+                    html.filter(|&&id| document.element(id).unwrap().namespace == Namespace::Html)
+                        .chain(foreign.filter(|&&id| {
+                            document.element(id).unwrap().namespace != Namespace::Html
+                        }))
+                        .copied()
+                        .collect()
+                }
+            };
+        elements.sort_by(|&a, &b| document.compare_tree_order(a, b));
+        elements.dedup();
+        elements
+    }
+
+    /// https://dom.spec.whatwg.org/#dom-htmlcollection-length
+    pub fn len(&self, document: &Document) -> usize {
+        self.elements(document).len()
+    }
+
+    pub fn is_empty(&self, document: &Document) -> bool {
+        self.elements(document).is_empty()
+    }
+
+    /// https://dom.spec.whatwg.org/#dom-htmlcollection-item
+    pub fn item(&self, document: &Document, index: usize) -> Option<NodeId> {
+        self.elements(document).get(index).copied()
+    }
+}
+
+/// The classes of an element, split on ASCII whitespace.
+fn class_names(element: &ElementData) -> impl Iterator<Item = &str> {
+    element.tokens("class").into_iter()
+}
+
+impl From<Node> for Document {
+    fn from(root: Node) -> Self {
+        Document::new(root)
+    }
+}
+
+/// Iterates over a run of siblings. See `Document::children`.
+pub struct Siblings<'a> {
+    document: &'a Document,
+    next: Option<NodeId>,
+    forward: bool,
+}
+
+impl Iterator for Siblings<'_> {
+    type Item = NodeId;
+
+    fn next(&mut self) -> Option<NodeId> {
+        let id = self.next?;
+        self.next = if self.forward {
+            self.document.next_sibling(id)
+        } else {
+            self.document.previous_sibling(id)
+        };
+        Some(id)
+    }
+}
+
+/// Iterates up the tree. See `Document::ancestors`.
+pub struct Ancestors<'a> {
+    document: &'a Document,
+    next: Option<NodeId>,
+}
+
+impl Iterator for Ancestors<'_> {
+    type Item = NodeId;
+
+    fn next(&mut self) -> Option<NodeId> {
+        let id = self.next?;
+        self.next = self.document.parent(id);
+        Some(id)
+    }
+}
+
+/// Iterates over a subtree in document order without recursion. See `Document::descendants`.
+pub struct Descendants<'a> {
+    document: &'a Document,
+    root: NodeId,
+    next: Option<NodeId>,
+}
+
+impl Iterator for Descendants<'_> {
+    type Item = NodeId;
+
+    fn next(&mut self) -> Option<NodeId> {
+        let id = self.next?;
+        self.next = self.document.first_child(id).or_else(|| {
+            // Climb until a node has a next sibling, without leaving the subtree.
+            let mut node = id;
+            loop {
+                if node == self.root {
+                    return None;
+                }
+                if let Some(sibling) = self.document.next_sibling(node) {
+                    return Some(sibling);
+                }
+                node = self.document.parent(node)?;
+            }
+        });
+        Some(id)
+    }
+}
@@ -0,0 +1,460 @@
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use std::cell::RefCell;
+    use std::rc::Rc;
+
+    use crate::document::{Document, DomError, MutationRecord, MutationType, NodeId};
+    use crate::dom;
+    use crate::html;
+
+    /// `<div><p>One</p><!-- two --><p>Three</p></div>`
+    fn create_document() -> Document {
+        Document::new(dom::elem(
+            "div".to_string(),
+            HashMap::new(),
+            vec![
+                dom::elem(
+                    "p".to_string(),
+                    HashMap::new(),
+                    vec![dom::text("One".to_string())],
+                ),
+                dom::comment(" two ".to_string()),
+                dom::elem(
+                    "p".to_string(),
+                    HashMap::new(),
+                    vec![dom::text("Three".to_string())],
+                ),
+            ],
+        ))
+    }
+
+    fn describe(document: &Document, id: NodeId) -> String {
+        match document.node_type(id) {
+            dom::NodeType::Element(element) => element.tag_name.clone(),
+            dom::NodeType::Text(text) => text.clone(),
+            dom::NodeType::Comment(_) => "comment".to_string(),
+            _ => "other".to_string(),
+        }
+    }
+
+    #[test]
+    fn test_navigation() {
+        let document = create_document();
+        let root = document.root();
+        let first = document.first_child(root).unwrap();
+        let comment = document.next_sibling(first).unwrap();
+        let last = document.last_child(root).unwrap();
+
+        assert_eq!(document.parent(root), None);
+        assert_eq!(document.parent(first), Some(root));
+        assert_eq!(document.previous_sibling(first), None);
+        assert_eq!(document.next_sibling(comment), Some(last));
+        assert_eq!(document.previous_sibling(last), Some(comment));
+        assert_eq!(document.next_sibling(last), None);
+        assert_eq!(document.first_child(comment), None);
+        assert_eq!(describe(&document, comment), "comment");
+    }
+
+    #[test]
+    fn test_iterators() {
+        let document = create_document();
+        let root = document.root();
+        let names = |ids: Vec<NodeId>| -> Vec<String> {
+            ids.into_iter().map(|id| describe(&document, id)).collect()
+        };
+
+        assert_eq!(
+            names(document.children(root).collect()),
+            vec!["p", "comment", "p"]
+        );
+        assert_eq!(
+            names(document.descendants(root).collect()),
+            vec!["p", "One", "comment", "p", "Three"]
+        );
+
+        let three = document.descendants(root).last().unwrap();
+        assert_eq!(names(document.ancestors(three).collect()), vec!["p", "div"]);
+
+        let first = document.first_child(root).unwrap();
+        let last = document.last_child(root).unwrap();
+        assert_eq!(
+            names(document.following_siblings(first).collect()),
+            vec!["comment", "p"]
+        );
+        assert_eq!(
+            names(document.preceding_siblings(last).collect()),
+            vec!["comment", "p"]
+        );
+
+        // Descendants stay inside the subtree they start from.
+        assert_eq!(names(document.descendants(first).collect()), vec!["One"]);
+    }
+
+    #[test]
+    fn test_round_trip_through_node() {
+        let root = html::parse("<ul><li>One<li>Two</ul>".to_string()).root;
+        let document = Document::from(root.clone());
+
+        assert_eq!(document.to_node(document.root()), root);
+        assert_eq!(document.len(), 8);
+        assert_eq!(document.span(document.root()), root.span, "spans are kept");
+    }
+
+    /// Collect the records of every change made to `document`.
+    fn record(document: &mut Document) -> Rc<RefCell<Vec<MutationRecord>>> {
+        let records = Rc::new(RefCell::new(Vec::new()));
+        let sink = Rc::clone(&records);
+        document.observe(move |record| sink.borrow_mut().push(record.clone()));
+        records
+    }
+
+    #[test]
+    fn test_insert_and_remove_children() {
+        let mut document = create_document();
+        let records = record(&mut document);
+        let root = document.root();
+        let first = document.first_child(root).unwrap();
+        let comment = document.next_sibling(first).unwrap();
+
+        let span = document.create_element("span", HashMap::new());
+        document.insert_before(root, span, Some(comment)).unwrap();
+        document.remove_child(root, first).unwrap();
+        // Appending a node that is already in the tree moves it.
+        document.append_child(root, comment).unwrap();
+
+        let names: Vec<String> = document
+            .children(root)
+            .map(|id| describe(&document, id))
+            .collect();
+        assert_eq!(names, vec!["span", "p", "comment"]);
+        assert_eq!(document.parent(first), None);
+        assert_eq!(document.last_child(root), Some(comment));
+
+        let records = records.borrow();
+        assert_eq!(records.len(), 4);
+        assert_eq!(records[0].kind, MutationType::ChildList);
+        assert_eq!(records[0].added_nodes, vec![span]);
+        assert_eq!(records[0].previous_sibling, Some(first));
+        assert_eq!(records[0].next_sibling, Some(comment));
+        assert_eq!(records[1].removed_nodes, vec![first]);
+        assert_eq!(records[2].removed_nodes, vec![comment]);
+        assert_eq!(records[3].added_nodes, vec![comment]);
+    }
+
+    #[test]
+    fn test_move_within_parent() {
+        let mut document = create_document();
+        let root = document.root();
+        let [first, comment, last]: [NodeId; 3] = document
+            .children(root)
+            .collect::<Vec<_>>()
+            .try_into()
+            .unwrap();
+        let records = record(&mut document);
+        let child_list =
+            |added: Option<NodeId>, removed: Option<NodeId>, previous, next| MutationRecord {
+                kind: MutationType::ChildList,
+                target: root,
+                added_nodes: added.into_iter().collect(),
+                removed_nodes: removed.into_iter().collect(),
+                previous_sibling: previous,
+                next_sibling: next,
+                attribute_name: None,
+                old_value: None,
+            };
+
+        document.append_child(root, first).unwrap();
+        // The node is still the last child when the record's previous sibling is taken.
+        document.append_child(root, first).unwrap();
+        document.insert_before(root, comment, Some(first)).unwrap();
+
+        let names: Vec<String> = document
+            .children(root)
+            .map(|id| describe(&document, id))
+            .collect();
+        assert_eq!(names, vec!["p", "comment", "p"]);
+        assert_eq!(
+            *records.borrow(),
+            vec![
+                child_list(None, Some(first), None, Some(comment)),
+                child_list(Some(first), None, Some(last), None),
+                child_list(None, Some(first), Some(last), None),
+                child_list(Some(first), None, Some(first), None),
+                child_list(None, Some(comment), None, Some(last)),
+                child_list(Some(comment), None, Some(last), Some(first)),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_replace_child() {
+        let mut document = create_document();
+        let records = record(&mut document);
+        let root = document.root();
+        let first = document.first_child(root).unwrap();
+
+        let text = document.create_text("Zero");
+        document.replace_child(root, text, first).unwrap();
+
+        assert_eq!(document.first_child(root), Some(text));
+        assert_eq!(document.parent(first), None);
+        let records = records.borrow();
+        assert_eq!(records.len(), 1);
+        assert_eq!(records[0].added_nodes, vec![text]);
+        assert_eq!(records[0].removed_nodes, vec![first]);
+    }
+
+    #[test]
+    fn test_invalid_mutations() {
+        let mut document = create_document();
+        let root = document.root();
+        let first = document.first_child(root).unwrap();
+        let text = document.first_child(first).unwrap();
+        let last = document.last_child(root).unwrap();
+
+        assert_eq!(
+            document.append_child(first, root),
+            Err(DomError::HierarchyRequest)
+        );
+        assert_eq!(
+            document.append_child(text, last),
+            Err(DomError::HierarchyRequest)
+        );
+        assert_eq!(
+            document.insert_before(root, last, Some(text)),
+            Err(DomError::NotFound)
+        );
+        assert_eq!(document.remove_child(first, last), Err(DomError::NotFound));
+        assert_eq!(
+            document.set_attribute(text, "id", "x"),
+            Err(DomError::InvalidNodeType)
+        );
+    }
+
+    #[test]
+    fn test_attributes_and_text() {
+        let mut document = create_document();
+        let records = record(&mut document);
+        let root = document.root();
+        let first = document.first_child(root).unwrap();
+        let text = document.first_child(first).unwrap();
+
+        document.set_attribute(root, "ID", "a").unwrap();
+        document.set_attribute(root, "id", "b").unwrap();
+        document.remove_attribute(root, "id").unwrap();
+        document.remove_attribute(root, "id").unwrap();
+        document.set_text(text, "Uno").unwrap();
+        document.set_text(root, "Gone").unwrap();
+
+        assert!(document.element(root).unwrap().attributes.is_empty());
+        assert_eq!(describe(&document, text), "Uno");
+        assert_eq!(document.text_content(root), "Gone");
+        let children: Vec<String> = document
+            .children(root)
+            .map(|id| describe(&document, id))
+            .collect();
+        assert_eq!(children, vec!["Gone"]);
+
+        let records = records.borrow();
+        let summary: Vec<(MutationType, Option<&str>, Option<&str>)> = records
+            .iter()
+            .map(|r| (r.kind, r.attribute_name.as_deref(), r.old_value.as_deref()))
+            .collect();
+        assert_eq!(
+            summary,
+            vec![
+                (MutationType::Attributes, Some("id"), None),
+                (MutationType::Attributes, Some("id"), Some("a")),
+                (MutationType::Attributes, Some("id"), Some("b")),
+                (MutationType::CharacterData, None, Some("One")),
+                (MutationType::ChildList, None, None),
+            ]
+        );
+        assert_eq!(records[4].removed_nodes.len(), 3);
+    }
+
+    #[test]
+    fn test_unobserve() {
+        let mut document = create_document();
+        let count = Rc::new(RefCell::new(0));
+        let counter = Rc::clone(&count);
+        let observer = document.observe(move |_| *counter.borrow_mut() += 1);
+        let root = document.root();
+
+        document.set_attribute(root, "id", "a").unwrap();
+        document.unobserve(observer);
+        document.set_attribute(root, "id", "b").unwrap();
+
+        assert_eq!(*count.borrow(), 1);
+    }
+
+    #[test]
+    fn test_get_element_by_id() {
+        let mut document = Document::new(
+            html::parse("<p id=a>One</p><div><p id=b>Two</p></div><p id=a>Three</p>".to_string())
+                .root,
+        );
+        let body = document
+            .get_elements_by_tag_name("body")
+            .item(&document, 0)
+            .unwrap();
+        let first = document.get_element_by_id("a").unwrap();
+        let b = document.get_element_by_id("b").unwrap();
+
+        assert_eq!(document.parent(first), Some(body));
+        assert_eq!(document.get_element_by_id("c"), None);
+        assert_eq!(document.get_element_by_id(""), None);
+
+        // Ids follow attribute changes and removals.
+        document.set_attribute(b, "id", "a").unwrap();
+        assert_eq!(document.get_element_by_id("b"), None);
+        document.remove_child(body, first).unwrap();
+        assert_eq!(document.get_element_by_id("a"), Some(b));
+        document.insert_before(body, first, None).unwrap();
+        assert_eq!(document.get_element_by_id("a"), Some(b));
+    }
+
+    #[test]
+    fn test_live_collections() {
+        let mut document = Document::new(
+            html::parse(
+                "<p class='a b'>One</p><p class=a>Two</p><svg><linearGradient class=a /></svg>"
+                    .to_string(),
+            )
+            .root,
+        );
+        let body = document
+            .get_elements_by_tag_name("BODY")
+            .item(&document, 0)
+            .unwrap();
+        let by_class = document.get_elements_by_class_name(" a\tb ");
+        let a = document.get_elements_by_class_name("a");
+        let paragraphs = document.get_elements_by_tag_name("P");
+
+        assert_eq!(by_class.len(&document), 1);
+        assert_eq!(a.len(&document), 3);
+        assert_eq!(paragraphs.len(&document), 2);
+        assert_eq!(
+            document
+                .get_elements_by_tag_name("linearGradient")
+                .len(&document),
+            1
+        );
+        assert!(document
+            .get_elements_by_tag_name("lineargradient")
+            .is_empty(&document));
+        assert_eq!(document.get_elements_by_tag_name("*").len(&document), 7);
+        assert!(document.get_elements_by_class_name("").is_empty(&document));
+
+        // The collections see later changes.
+        let two = paragraphs.item(&document, 1).unwrap();
+        document.set_attribute(two, "class", "b a").unwrap();
+        let p = document.create_node(dom::elem(
+            "p".to_string(),
+            [("class".to_string(), "a b".to_string())]
+                .into_iter()
+                .collect(),
+            vec![],
+        ));
+        document
+            .insert_before(body, p, document.first_child(body))
+            .unwrap();
+
+        assert_eq!(by_class.elements(&document)[0], p);
+        assert_eq!(by_class.len(&document), 3);
+        assert_eq!(paragraphs.item(&document, 0), Some(p));
+
+        document.set_text(body, "").unwrap();
+        assert!(a.is_empty(&document));
+        assert_eq!(document.get_elements_by_tag_name("*").len(&document), 3);
+    }
+
+    #[test]
+    fn test_update_token_list() {
+        let mut document = Document::new(html::parse("<p class=a>".to_string()).root);
+        let records = record(&mut document);
+        let p = document
+            .get_elements_by_tag_name("p")
+            .item(&document, 0)
+            .unwrap();
+        let b = document.get_elements_by_class_name("b");
+
+        // An update that leaves the value as it was keeps its source span.
+        document
+            .update_token_list(p, "class", |classes| classes.add("a"))
+            .unwrap()
+            .unwrap();
+        assert!(document
+            .element(p)
+            .unwrap()
+            .attribute_spans
+            .contains_key("class"));
+
+        let added = document
+            .update_token_list(p, "CLASS", |classes| classes.toggle("b"))
+            .unwrap();
+        assert_eq!(added, Ok(true));
+        assert_eq!(document.element(p).unwrap().attributes["class"], "a b");
+        assert_eq!(b.item(&document, 0), Some(p));
+
+        // Nothing changes, so nothing is recorded.
+        document
+            .update_token_list(p, "class", |classes| classes.add("a"))
+            .unwrap()
+            .unwrap();
+        assert_eq!(records.borrow().len(), 1);
+        assert_eq!(records.borrow()[0].old_value.as_deref(), Some("a"));
+    }
+
+    #[test]
+    fn test_clone_template_contents() {
+        let root = html::parse("<template><li class=item>Row</li></template><ul>".to_string()).root;
+        let mut document = Document::new(root.clone());
+        let records = record(&mut document);
+        let template = document
+            .get_elements_by_tag_name("template")
+            .item(&document, 0)
+            .unwrap();
+        let ul = document
+            .get_elements_by_tag_name("ul")
+            .item(&document, 0)
+            .unwrap();
+
+        // The contents are outside the tree.
+        assert_eq!(document.first_child(template), None);
+        assert!(document
+            .get_elements_by_class_name("item")
+            .is_empty(&document));
+        let contents = document.template_contents(template).unwrap();
+        assert_eq!(document.text_content(contents), "Row");
+
+        for _ in 0..2 {
+            let fragment = document.clone_template_contents(template).unwrap();
+            document.append_child(ul, fragment).unwrap();
+            assert_eq!(document.first_child(fragment), None);
+        }
+        assert_eq!(document.text_content(ul), "RowRow");
+        assert_eq!(
+            document.get_elements_by_class_name("item").len(&document),
+            2
+        );
+        assert_eq!(document.text_content(contents), "Row");
+        assert_eq!(
+            document.to_node(document.root()).children[0],
+            root.children[0]
+        );
+        assert_eq!(
+            document.clone_template_contents(ul),
+            Err(DomError::InvalidNodeType)
+        );
+
+        // Each insertion empties the fragment and adds its children to the list.
+        let records = records.borrow();
+        assert_eq!(records.len(), 4);
+        assert_eq!(records[1].target, ul);
+        assert_eq!(records[1].added_nodes.len(), 1);
+        assert_eq!(records[0].removed_nodes, records[1].added_nodes);
+    }
+}
@@ -1,23 +1,170 @@
 //! Basic DOM data structures.
 // https://dom.spec.whatwg.org/
 
-use std::collections::{HashMap, HashSet};
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::fmt;
+
+use serde::{Deserialize, Serialize, Serializer};
+
+use crate::css::{self, InvalidSelector};
+use crate::cssom::Selector;
+use crate::style;
 
 /// A mapping of attribute names to their values.
 pub type AttrMap = HashMap<String, String>;
 
 /// Represents a node in the DOM (Document Object Model).
-#[derive(Debug, PartialEq)]
+///
+/// `Node` implements `Drop`, so a field can no longer be moved out of a node with a pattern
+/// or a field access. Take it out with `std::mem::take` or `std::mem::replace` instead:
+///
+/// ```ignore
+/// let children = std::mem::take(&mut node.children);
+/// let node_type = std::mem::replace(&mut node.node_type, NodeType::DocumentFragment);
+/// ```
+#[derive(Debug, Serialize, Deserialize)]
 pub struct Node {
     /// Children nodes of this node.
     pub children: Vec<Node>,
     /// Type of the node, either text or element.
     pub node_type: NodeType,
+    /// Where the node came from in the source, if it was parsed.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub span: Option<Span>,
+}
+
+/// Nodes are compared by content; where they came from does not matter. Pairs of nodes wait
+/// on a stack rather than in recursive calls, so trees of any depth can be compared. Template
+/// contents wait on the same stack.
+impl PartialEq for Node {
+    fn eq(&self, other: &Self) -> bool {
+        let mut pairs = vec![(self, other)];
+        while let Some((a, b)) = pairs.pop() {
+            match (&a.node_type, &b.node_type) {
+                (NodeType::Element(a), NodeType::Element(b)) => {
+                    if !a.eq_without_contents(b) {
+                        return false;
+                    }
+                    match (&a.template_contents, &b.template_contents) {
+                        (Some(a), Some(b)) => pairs.push((a, b)),
+                        (None, None) => {}
+                        _ => return false,
+                    }
+                }
+                (a, b) if a != b => return false,
+                _ => {}
+            }
+            if a.children.len() != b.children.len() {
+                return false;
+            }
+            pairs.extend(a.children.iter().zip(&b.children));
+        }
+        true
+    }
+}
+
+/// Copies are put together from a stack of nodes whose children are still being copied,
+/// rather than recursively, so trees of any depth can be cloned.
+impl Clone for Node {
+    fn clone(&self) -> Self {
+        // A node's template contents are copied before its children.
+        fn parts(node: &Node) -> impl Iterator<Item = &Node> {
+            let contents = match &node.node_type {
+                NodeType::Element(element) => element.template_contents.as_deref(),
+                _ => None,
+            };
+            contents.into_iter().chain(&node.children)
+        }
+
+        let mut stack = vec![(self, parts(self), Vec::new())];
+        while let Some((node, remaining, copies)) = stack.last_mut() {
+            if let Some(part) = remaining.next() {
+                stack.push((part, parts(part), Vec::new()));
+                continue;
+            }
+            let node: &Node = node;
+            let mut children = std::mem::take(copies);
+            let node_type = match &node.node_type {
+                NodeType::Element(element) => NodeType::Element(ElementData {
+                    tag_name: element.tag_name.clone(),
+                    namespace: element.namespace,
+                    attributes: element.attributes.clone(),
+                    attribute_spans: element.attribute_spans.clone(),
+                    template_contents: element
+                        .template_contents
+                        .as_ref()
+                        .map(|_| Box::new(children.remove(0))),
+                }),
+                node_type => node_type.clone(),
+            };
+            let copy = Node {
+                children,
+                node_type,
+                span: node.span,
+            };
+            stack.pop();
+            match stack.last_mut() {
+                Some((_, _, copies)) => copies.push(copy),
+                None => return copy,
+            }
+        }
+        unreachable!("the root is copied last")
+    }
+}
+
+/// Descendants and template contents are dropped from a list rather than recursively, so
+/// trees of any depth can be dropped.
+impl Drop for Node {
+    fn drop(&mut self) {
+        let mut nodes = std::mem::take(&mut self.children);
+        nodes.extend(self.take_template_contents());
+        while let Some(mut node) = nodes.pop() {
+            nodes.append(&mut node.children);
+            nodes.extend(node.take_template_contents());
+        }
+    }
+}
+
+/// A position in the source text.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+pub struct SourcePosition {
+    /// Byte offset from the start of the source.
+    pub offset: usize,
+    /// 1-based line number.
+    pub line: usize,
+    /// 1-based column number, counted in characters.
+    pub column: usize,
+}
+
+/// The part of the source text a node or attribute was parsed from.
+/// Elements span from their start tag to their end tag, or to their last descendant when
+/// the end tag is missing. Elements the parser inserted on its own, such as an implied
+/// `<tbody>`, start where the markup that caused them starts.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+pub struct Span {
+    pub start: SourcePosition,
+    /// The position just past the end.
+    pub end: SourcePosition,
+}
+
+impl fmt::Display for Span {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "{}:{}-{}:{} (bytes {}..{})",
+            self.start.line,
+            self.start.column,
+            self.end.line,
+            self.end.column,
+            self.start.offset,
+            self.end.offset
+        )
+    }
 }
 
 /// Enum representing the type of a Node.
 /// https://dom.spec.whatwg.org/#dom-node-nodetype
-#[derive(Debug, PartialEq)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub enum NodeType {
     /// https://dom.spec.whatwg.org/#interface-text
     Text(String),
@@ -30,45 +177,219 @@ pub enum NodeType {
 
     /// https://dom.spec.whatwg.org/#interface-comment
     Comment(String),
+
+    /// https://dom.spec.whatwg.org/#interface-documenttype
+    Doctype(DoctypeData),
+
+    /// A parentless container for nodes, such as the contents of a `<template>`.
+    /// https://dom.spec.whatwg.org/#interface-documentfragment
+    DocumentFragment,
 }
 
 /// Struct representing the data of an Element node.
-#[derive(Debug, PartialEq)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ElementData {
     pub tag_name: String,
+    /// The namespace the element is in. Elements inside `<svg>` and `<math>` are in the SVG
+    /// and MathML namespaces, and their names are case-sensitive.
+    #[serde(default)]
+    pub namespace: Namespace,
+    #[serde(serialize_with = "serialize_sorted")]
     pub attributes: AttrMap,
+    /// Where each attribute, name and value, came from in the source.
+    #[serde(
+        default,
+        serialize_with = "serialize_sorted",
+        skip_serializing_if = "HashMap::is_empty"
+    )]
+    pub attribute_spans: HashMap<String, Span>,
+    /// The contents of a `<template>`, as a `DocumentFragment` node. They are not children of
+    /// the template, so they are not styled or rendered.
+    /// https://html.spec.whatwg.org/multipage/scripting.html#template-contents
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub template_contents: Option<Box<Node>>,
+}
+
+impl PartialEq for ElementData {
+    fn eq(&self, other: &Self) -> bool {
+        self.eq_without_contents(other) && self.template_contents == other.template_contents
+    }
+}
+
+/// The namespaces elements and attributes can be in.
+/// https://infra.spec.whatwg.org/#namespaces
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Namespace {
+    #[default]
+    Html,
+    Svg,
+    MathMl,
+    XLink,
+    Xml,
+    Xmlns,
+}
+
+impl Namespace {
+    /// The namespace URL.
+    pub fn url(&self) -> &'static str {
+        match self {
+            Namespace::Html => "http://www.w3.org/1999/xhtml",
+            Namespace::Svg => "http://www.w3.org/2000/svg",
+            Namespace::MathMl => "http://www.w3.org/1998/Math/MathML",
+            Namespace::XLink => "http://www.w3.org/1999/xlink",
+            Namespace::Xml => "http://www.w3.org/XML/1998/namespace",
+            Namespace::Xmlns => "http://www.w3.org/2000/xmlns/",
+        }
+    }
+}
+
+impl Node {
+    /// The text of this node: the data of a text node or comment, or the text of every
+    /// descendant text node of an element, in order.
+    /// https://dom.spec.whatwg.org/#dom-node-textcontent
+    pub fn text_content(&self) -> String {
+        match &self.node_type {
+            NodeType::Text(data) | NodeType::Comment(data) => data.clone(),
+            NodeType::ProcessingInstruction(pi) => pi.data.clone(),
+            NodeType::Doctype(_) => String::new(),
+            NodeType::Element(_) | NodeType::DocumentFragment => {
+                let mut text = String::new();
+                let mut stack: Vec<&Node> = self.children.iter().rev().collect();
+                while let Some(node) = stack.pop() {
+                    if let NodeType::Text(data) = &node.node_type {
+                        text.push_str(data);
+                    }
+                    stack.extend(node.children.iter().rev());
+                }
+                text
+            }
+        }
+    }
+
+    fn take_template_contents(&mut self) -> Option<Node> {
+        match &mut self.node_type {
+            NodeType::Element(element) => {
+                element.template_contents.take().map(|contents| *contents)
+            }
+            _ => None,
+        }
+    }
+
+    /// The first element below this node, in document order, that matches the selector list.
+    /// https://dom.spec.whatwg.org/#dom-parentnode-queryselector
+    pub fn query_selector(&self, selectors: &str) -> Result<Option<&Node>, InvalidSelector> {
+        let selectors = css::parse_selector_list(selectors)?;
+        Ok(self.matching_descendants(selectors).next())
+    }
+
+    /// Every element below this node, in document order, that matches the selector list.
+    /// https://dom.spec.whatwg.org/#dom-parentnode-queryselectorall
+    pub fn query_selector_all(&self, selectors: &str) -> Result<Vec<&Node>, InvalidSelector> {
+        let selectors = css::parse_selector_list(selectors)?;
+        Ok(self.matching_descendants(selectors).collect())
+    }
+
+    fn matching_descendants(&self, selectors: Vec<Selector>) -> impl Iterator<Item = &Node> {
+        // Walk the tree depth first with a stack, children in reverse so the first comes out
+        // first.
+        let mut stack: Vec<&Node> = self.children.iter().rev().collect();
+        std::iter::from_fn(move || {
+            let node = stack.pop()?;
+            stack.extend(node.children.iter().rev());
+            Some(node)
+        })
+        .filter(move |node| match &node.node_type {
+            NodeType::Element(element) => selectors
+                .iter()
+                .any(|selector| style::matches(element, selector)),
+            _ => false,
+        })
+    }
 }
 
 /// Struct representing the data of a Processing Instruction node.
-#[derive(Debug, PartialEq)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct ProcessingInstructionData {
     pub target: String,
     pub data: String,
 }
 
+/// Struct representing the data of a Document Type node.
+/// A missing public or system identifier is stored as the empty string.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct DoctypeData {
+    pub name: String,
+    pub public_id: String,
+    pub system_id: String,
+}
+
+/// The mode a document is rendered in, as decided by its doctype.
+/// https://dom.spec.whatwg.org/#concept-document-mode
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub enum DocumentMode {
+    NoQuirks,
+    LimitedQuirks,
+    Quirks,
+}
+
+/// Serialize a map with its keys in order, so the output is the same every time.
+pub(crate) fn serialize_sorted<S, V>(
+    map: &HashMap<String, V>,
+    serializer: S,
+) -> Result<S::Ok, S::Error>
+where
+    S: Serializer,
+    V: Serialize,
+{
+    map.iter().collect::<BTreeMap<_, _>>().serialize(serializer)
+}
+
 /// Creates a text node with the given data.
 pub fn text(data: String) -> Node {
     Node {
         children: Vec::new(),
+        span: None,
         node_type: NodeType::Text(data),
     }
 }
 
-/// Creates an element node with the given tag name, attributes, and children nodes.
+/// Creates an HTML element node with the given tag name, attributes, and children nodes.
 pub fn elem(name: String, attrs: AttrMap, children: Vec<Node>) -> Node {
+    elem_ns(Namespace::Html, name, attrs, children)
+}
+
+/// Creates an element node in the given namespace, such as an SVG `<circle>`. An HTML
+/// `<template>` gets empty contents.
+pub fn elem_ns(namespace: Namespace, name: String, attrs: AttrMap, children: Vec<Node>) -> Node {
+    let template_contents = (namespace == Namespace::Html && name == "template")
+        .then(|| Box::new(document_fragment(Vec::new())));
     Node {
         children,
+        span: None,
         node_type: NodeType::Element(ElementData {
             tag_name: name,
+            namespace,
             attributes: attrs,
+            attribute_spans: HashMap::new(),
+            template_contents,
         }),
     }
 }
 
+/// Creates a document fragment node with the given children.
+pub fn document_fragment(children: Vec<Node>) -> Node {
+    Node {
+        children,
+        span: None,
+        node_type: NodeType::DocumentFragment,
+    }
+}
+
 /// Creates a processing instruction node with the given target and data
 pub fn processing_instruction(target: String, data: String) -> Node {
     Node {
         children: Vec::new(),
+        span: None,
         node_type: NodeType::ProcessingInstruction(ProcessingInstructionData { target, data }),
     }
 }
@@ -76,50 +397,319 @@ pub fn processing_instruction(target: String, data: String) -> Node {
 pub fn comment(data: String) -> Node {
     Node {
         children: Vec::new(),
+        span: None,
         node_type: NodeType::Comment(data),
     }
 }
 
+/// Creates a document type node with the given name, public id and system id.
+pub fn doctype(name: String, public_id: String, system_id: String) -> Node {
+    Node {
+        children: Vec::new(),
+        span: None,
+        node_type: NodeType::Doctype(DoctypeData {
+            name,
+            public_id,
+            system_id,
+        }),
+    }
+}
+
 impl ElementData {
     pub fn id(&self) -> Option<&String> {
         self.attributes.get("id")
     }
 
+    /// Whether the elements are equal, apart from their template contents.
+    fn eq_without_contents(&self, other: &Self) -> bool {
+        self.tag_name == other.tag_name
+            && self.namespace == other.namespace
+            && self.attributes == other.attributes
+    }
+
+    /// The namespace of an attribute. Only attributes of foreign elements such as
+    /// `xlink:href` have one; the name keeps its prefix.
+    /// https://html.spec.whatwg.org/multipage/parsing.html#adjust-foreign-attributes
+    pub fn attribute_namespace(&self, name: &str) -> Option<Namespace> {
+        if self.namespace == Namespace::Html {
+            return None;
+        }
+        match name {
+            "xlink:actuate" | "xlink:arcrole" | "xlink:href" | "xlink:role" | "xlink:show"
+            | "xlink:title" | "xlink:type" => Some(Namespace::XLink),
+            "xml:lang" | "xml:space" => Some(Namespace::Xml),
+            "xmlns" | "xmlns:xlink" => Some(Namespace::Xmlns),
+            _ => None,
+        }
+    }
+
     pub fn classes(&self) -> HashSet<&str> {
-        match self.attributes.get("class") {
-            Some(classlist) => classlist.split(' ').collect(),
-            None => HashSet::new(),
+        self.tokens("class").into_iter().collect()
+    }
+
+    /// The tokens of an attribute such as `class` or `rel`, in order and without duplicates.
+    pub fn tokens(&self, name: &str) -> Vec<&str> {
+        self.attributes
+            .get(name)
+            .map(|value| split_tokens(value))
+            .unwrap_or_default()
+    }
+
+    /// The classes of the element as a list that writes changes back to `class`.
+    /// https://dom.spec.whatwg.org/#dom-element-classlist
+    pub fn class_list(&mut self) -> ClassList<'_> {
+        self.token_list("class")
+    }
+
+    /// The link types of the element as a list that writes changes back to `rel`.
+    /// https://html.spec.whatwg.org/multipage/links.html#dom-a-rellist
+    pub fn rel_list(&mut self) -> RelList<'_> {
+        self.token_list("rel")
+    }
+
+    /// The tokens of any attribute as a list that writes changes back to it.
+    pub fn token_list(&mut self, name: &str) -> TokenList<'_> {
+        let tokens = self.tokens(name).into_iter().map(str::to_string).collect();
+        TokenList {
+            element: self,
+            attribute: name.to_string(),
+            tokens,
         }
     }
 }
 
+/// Split an attribute value on ASCII whitespace, keeping the first of any repeated tokens.
+/// https://dom.spec.whatwg.org/#concept-ordered-set-parser
+pub fn split_tokens(value: &str) -> Vec<&str> {
+    let mut tokens: Vec<&str> = Vec::new();
+    for token in value.split_ascii_whitespace() {
+        if !tokens.contains(&token) {
+            tokens.push(token);
+        }
+    }
+    tokens
+}
+
+/// The tokens of an attribute, such as the classes in `class`. Every change is written back
+/// to the attribute, normalized to single spaces:
+///
+/// ```ignore
+/// let mut classes: ClassList = element.class_list();
+/// classes.toggle("open")?;
+/// classes.replace("old", "new")?;
+/// assert_eq!(element.attributes["class"], classes.to_string());
+/// ```
+/// https://dom.spec.whatwg.org/#interface-domtokenlist
+#[derive(Debug)]
+pub struct TokenList<'a> {
+    element: &'a mut ElementData,
+    attribute: String,
+    tokens: Vec<String>,
+}
+
+/// The classes of an element, from `ElementData::class_list`.
+/// https://dom.spec.whatwg.org/#dom-element-classlist
+pub type ClassList<'a> = TokenList<'a>;
+
+/// The link types of an element, from `ElementData::rel_list`.
+/// https://html.spec.whatwg.org/multipage/links.html#dom-a-rellist
+pub type RelList<'a> = TokenList<'a>;
+
+impl TokenList<'_> {
+    pub fn len(&self) -> usize {
+        self.tokens.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.tokens.is_empty()
+    }
+
+    /// https://dom.spec.whatwg.org/#dom-domtokenlist-item
+    pub fn item(&self, index: usize) -> Option<&str> {
+        self.tokens.get(index).map(String::as_str)
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &str> {
+        self.tokens.iter().map(String::as_str)
+    }
+
+    /// https://dom.spec.whatwg.org/#dom-domtokenlist-contains
+    pub fn contains(&self, token: &str) -> bool {
+        self.tokens.iter().any(|t| t == token)
+    }
+
+    /// Add `token` to the end of the list, unless it is there already.
+    /// https://dom.spec.whatwg.org/#dom-domtokenlist-add
+    pub fn add(&mut self, token: &str) -> Result<(), InvalidToken> {
+        validate_token(token)?;
+        if !self.contains(token) {
+            self.tokens.push(token.to_string());
+        }
+        self.update();
+        Ok(())
+    }
+
+    /// https://dom.spec.whatwg.org/#dom-domtokenlist-remove
+    pub fn remove(&mut self, token: &str) -> Result<(), InvalidToken> {
+        validate_token(token)?;
+        self.tokens.retain(|t| t != token);
+        self.update();
+        Ok(())
+    }
+
+    /// Remove `token` if it is in the list, or add it if not. Returns whether it is in the
+    /// list afterwards.
+    /// https://dom.spec.whatwg.org/#dom-domtokenlist-toggle
+    pub fn toggle(&mut self, token: &str) -> Result<bool, InvalidToken> {
+        validate_token(token)?;
+        let present = !self.contains(token);
+        if present {
+            self.tokens.push(token.to_string());
+        } else {
+            self.tokens.retain(|t| t != token);
+        }
+        self.update();
+        Ok(present)
+    }
+
+    /// Put `new` where `old` is in the list. Returns false, and changes nothing, if `old` is
+    /// not in the list.
+    /// https://dom.spec.whatwg.org/#dom-domtokenlist-replace
+    pub fn replace(&mut self, old: &str, new: &str) -> Result<bool, InvalidToken> {
+        validate_token(old)?;
+        validate_token(new)?;
+        let Some(index) = self.tokens.iter().position(|t| t == old) else {
+            return Ok(false);
+        };
+        if self.contains(new) {
+            self.tokens.remove(index);
+            // `new` stays in the first of the two places.
+            let first = self.tokens.iter().position(|t| t == new).unwrap();
+            if first > index {
+                self.tokens.remove(first);
+                self.tokens.insert(index, new.to_string());
+            }
+        } else {
+            self.tokens[index] = new.to_string();
+        }
+        self.update();
+        Ok(true)
+    }
+
+    /// Write the tokens back to the attribute. A missing attribute is not added for an empty
+    /// list.
+    /// https://dom.spec.whatwg.org/#concept-dtl-update
+    fn update(&mut self) {
+        if self.tokens.is_empty() && !self.element.attributes.contains_key(&self.attribute) {
+            return;
+        }
+        // The value no longer comes from the source.
+        self.element.attribute_spans.remove(&self.attribute);
+        self.element
+            .attributes
+            .insert(self.attribute.clone(), self.to_string());
+    }
+}
+
+impl fmt::Display for TokenList<'_> {
+    /// https://dom.spec.whatwg.org/#concept-ordered-set-serializer
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.tokens.join(" "))
+    }
+}
+
+fn validate_token(token: &str) -> Result<(), InvalidToken> {
+    if token.is_empty() || token.contains(|c: char| c.is_ascii_whitespace()) {
+        return Err(InvalidToken(token.to_string()));
+    }
+    Ok(())
+}
+
+/// A token that is empty or contains whitespace, which no token list can hold.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct InvalidToken(pub String);
+
+impl fmt::Display for InvalidToken {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "'{}' is not a valid token", self.0)
+    }
+}
+
+impl std::error::Error for InvalidToken {}
+
 // Pretty-print a tree of DOM nodes
 pub fn pretty_print(node: &Node, indent: usize) {
+    print_tree(node, indent, false);
+}
+
+/// Pretty-print a tree of DOM nodes, with the source span of each node and attribute.
+pub fn pretty_print_with_spans(node: &Node, indent: usize) {
+    print_tree(node, indent, true);
+}
+
+fn print_tree(node: &Node, indent: usize, show_spans: bool) {
+    let span = |span: Option<&Span>| match span {
+        Some(span) if show_spans => format!(" @ {}", span),
+        _ => String::new(),
+    };
+    let node_span = span(node.span.as_ref());
+
     match &node.node_type {
         NodeType::Text(data) => {
-            println!("{}Text: {}", " ".repeat(indent), data);
+            println!("{}Text: {}{}", " ".repeat(indent), data, node_span);
         }
 
         NodeType::Element(element_data) => {
             println!(
-                "{}Element: <{}>",
+                "{}Element: <{}>{}",
                 "  ".repeat(indent),
-                element_data.tag_name
+                element_data.tag_name,
+                node_span
             );
             for (attr, value) in &element_data.attributes {
-                println!("{}  {}=\"{}\"", "  ".repeat(indent), attr, value);
+                println!(
+                    "{}  {}=\"{}\"{}",
+                    "  ".repeat(indent),
+                    attr,
+                    value,
+                    span(element_data.attribute_spans.get(attr))
+                );
             }
+            if let Some(contents) = &element_data.template_contents {
+                print_tree(contents, indent + 1, show_spans);
+            }
+        }
+
+        NodeType::DocumentFragment => {
+            println!("{}DocumentFragment{}", "  ".repeat(indent), node_span);
         }
 
         NodeType::Comment(data) => {
-            println!("{}Comment: <!-- {} -->", "  ".repeat(indent), data);
+            println!(
+                "{}Comment: <!-- {} -->{}",
+                "  ".repeat(indent),
+                data,
+                node_span
+            );
+        }
+
+        NodeType::Doctype(doctype) => {
+            println!(
+                "{}Doctype: <!DOCTYPE {} \"{}\" \"{}\">{}",
+                "  ".repeat(indent),
+                doctype.name,
+                doctype.public_id,
+                doctype.system_id,
+                node_span
+            );
         }
 
         NodeType::ProcessingInstruction(processing_instruction) => {
             println!(
-                "{}Data: {}",
+                "{}Data: {}{}",
                 " ".repeat(indent),
                 processing_instruction.data,
+                node_span
             );
             println!(
                 "{}Target: {}",
@@ -130,6 +720,6 @@ pub fn pretty_print(node: &Node, indent: usize) {
     }
 
     for child in &node.children {
-        pretty_print(child, indent + 1)
+        print_tree(child, indent + 1, show_spans)
     }
 }
@@ -1,8 +1,9 @@
 #[cfg(test)]
 mod tests {
-    use std::collections::HashSet;
+    use std::collections::{HashMap, HashSet};
 
     use crate::dom;
+    use crate::html;
 
     fn create_attrs() -> dom::AttrMap {
         [("class".to_string(), "container".to_string())]
@@ -43,7 +44,7 @@ mod tests {
         let attrs = create_attrs();
         let element_node = dom::elem("div".to_string(), attrs, Vec::new());
 
-        if let dom::NodeType::Element(data) = element_node.node_type {
+        if let dom::NodeType::Element(data) = &element_node.node_type {
             assert_eq!(data.attributes.get("class"), Some(&"container".to_string()));
         } else {
             panic!("Expected an Element node.");
@@ -55,14 +56,20 @@ mod tests {
         let attributes_with_id = create_attributes_with_id();
         let element_data_with_id = dom::ElementData {
             tag_name: "div".to_string(),
+            namespace: dom::Namespace::Html,
             attributes: attributes_with_id,
+            attribute_spans: HashMap::new(),
+            template_contents: None,
         };
 
         assert_eq!(element_data_with_id.id(), Some(&"my-id".to_string()));
 
         let element_data_without_id = dom::ElementData {
             tag_name: "div".to_string(),
+            namespace: dom::Namespace::Html,
             attributes: dom::AttrMap::new(),
+            attribute_spans: HashMap::new(),
+            template_contents: None,
         };
 
         assert_eq!(element_data_without_id.id(), None);
@@ -73,7 +80,10 @@ mod tests {
         let attributes_with_class: dom::AttrMap = create_attributes_with_class();
         let element_data_with_class = dom::ElementData {
             tag_name: "div".to_string(),
+            namespace: dom::Namespace::Html,
             attributes: attributes_with_class,
+            attribute_spans: HashMap::new(),
+            template_contents: None,
         };
 
         let expected_classes: HashSet<&str> = ["class1", "class2"].iter().cloned().collect();
@@ -81,12 +91,66 @@ mod tests {
 
         let element_data_without_class = dom::ElementData {
             tag_name: "div".to_string(),
+            namespace: dom::Namespace::Html,
             attributes: dom::AttrMap::new(),
+            attribute_spans: HashMap::new(),
+            template_contents: None,
         };
 
         assert!(element_data_without_class.classes().is_empty());
     }
 
+    #[test]
+    fn test_classes_split_on_ascii_whitespace() {
+        let node = html::parse("<p class=' a\t b\n\na  c\x0c'>".to_string()).root;
+        let p = node.query_selector("p").unwrap().unwrap();
+        let dom::NodeType::Element(element) = &p.node_type else {
+            panic!("not an element");
+        };
+
+        assert_eq!(element.tokens("class"), vec!["a", "b", "c"]);
+        assert_eq!(element.classes(), ["a", "b", "c"].into_iter().collect());
+        assert_eq!(dom::split_tokens(" "), Vec::<&str>::new());
+        assert!(node.query_selector(".b.c").unwrap().is_some());
+    }
+
+    #[test]
+    fn test_token_list() {
+        let mut element = dom::ElementData {
+            tag_name: "a".to_string(),
+            namespace: dom::Namespace::Html,
+            attributes: [("class".to_string(), "a  b a c".to_string())]
+                .into_iter()
+                .collect(),
+            attribute_spans: HashMap::new(),
+            template_contents: None,
+        };
+
+        let mut classes: dom::ClassList = element.class_list();
+        assert_eq!(classes.len(), 3);
+        assert_eq!(classes.item(1), Some("b"));
+        assert!(classes.contains("c"));
+        classes.add("d").unwrap();
+        classes.remove("a").unwrap();
+        assert_eq!(classes.toggle("b"), Ok(false));
+        assert_eq!(classes.toggle("e"), Ok(true));
+        assert_eq!(classes.replace("e", "c"), Ok(true));
+        assert_eq!(classes.replace("x", "y"), Ok(false));
+        assert_eq!(classes.to_string(), "c d");
+        assert_eq!(
+            classes.add("").unwrap_err(),
+            dom::InvalidToken(String::new())
+        );
+        assert!(classes.remove("a b").is_err());
+        assert_eq!(element.attributes["class"], "c d");
+
+        // An empty list does not add a missing attribute, but writes any other change.
+        element.rel_list().remove("nofollow").unwrap();
+        assert!(!element.attributes.contains_key("rel"));
+        element.rel_list().add("noopener").unwrap();
+        assert_eq!(element.attributes["rel"], "noopener");
+    }
+
     #[test]
     fn test_pretty_print() {
         let tree = dom::elem(
@@ -119,4 +183,106 @@ mod tests {
 
         dom::pretty_print(&tree, 0);
     }
+
+    #[test]
+    fn test_query_selector() {
+        let root = html::parse(
+            "<div id=main><p class=note>One</p><section><p>Two</p><p class='note big'>Three</p></section></div>"
+                .to_string(),
+        )
+        .root;
+        let text = |node: &dom::Node| match &node.children[0].node_type {
+            dom::NodeType::Text(text) => text.clone(),
+            _ => String::new(),
+        };
+
+        let first = root.query_selector("p").unwrap().unwrap();
+        assert_eq!(text(first), "One");
+        let notes: Vec<String> = root
+            .query_selector_all(".note")
+            .unwrap()
+            .into_iter()
+            .map(text)
+            .collect();
+        assert_eq!(notes, vec!["One", "Three"]);
+
+        // A selector list matches any of its selectors, and results stay in document order.
+        let all: Vec<String> = root
+            .query_selector_all("p.big, p.note, #nothing")
+            .unwrap()
+            .into_iter()
+            .map(text)
+            .collect();
+        assert_eq!(all, vec!["One", "Three"]);
+        assert_eq!(root.query_selector_all("P").unwrap().len(), 3);
+        assert!(root.query_selector("#main").unwrap().is_some());
+        assert!(root.query_selector("table").unwrap().is_none());
+    }
+
+    #[test]
+    fn test_query_selector_rejects_invalid_selectors() {
+        let root = html::parse("<p>Hi</p>".to_string()).root;
+
+        for selector in ["", "p,", "p {", "#", "p > a"] {
+            assert!(root.query_selector(selector).is_err(), "{:?}", selector);
+        }
+    }
+
+    #[test]
+    fn test_text_content() {
+        let root = html::parse(
+            "<head><title>T</title></head><p>One <b>two</b><!-- no --></p><script>x</script>"
+                .to_string(),
+        )
+        .root;
+
+        assert_eq!(root.text_content(), "TOne twox");
+        assert_eq!(dom::comment("c".to_string()).text_content(), "c");
+    }
+
+    #[test]
+    fn test_json_round_trip() {
+        let root = html::parse("<p id=a class=b>Hi<!-- c --></p>".to_string()).root;
+
+        let json = serde_json::to_string(&root).unwrap();
+        let parsed: dom::Node = serde_json::from_str(&json).unwrap();
+
+        assert_eq!(parsed, root);
+        assert_eq!(parsed.span, root.span);
+        // Attributes are written in order, so the output is stable.
+        assert!(json.contains(r#""attributes":{"class":"b","id":"a"}"#));
+        // Spans and namespaces can be left out.
+        let built: dom::Node = serde_json::from_str(
+            r#"{"children":[],"node_type":{"Element":{"tag_name":"p","attributes":{}}}}"#,
+        )
+        .unwrap();
+        assert_eq!(built, dom::elem("p".to_string(), HashMap::new(), vec![]));
+    }
+
+    /// `<template>`s each holding the next in their contents, around a text node.
+    fn nested_templates(depth: usize, leaf: &str) -> dom::Node {
+        let mut node = dom::text(leaf.to_string());
+        for _ in 0..depth {
+            let mut template = dom::elem("template".to_string(), HashMap::new(), vec![]);
+            if let dom::NodeType::Element(element) = &mut template.node_type {
+                if let Some(contents) = &mut element.template_contents {
+                    contents.children.push(node);
+                }
+            }
+            node = template;
+        }
+        node
+    }
+
+    #[test]
+    fn test_deep_template_contents() {
+        let depth = 30_000;
+        let a = nested_templates(depth, "leaf");
+
+        // Not `assert_eq!`, whose message would print the whole tree.
+        assert!(a == nested_templates(depth, "leaf"));
+        assert!(a != nested_templates(depth, "other"));
+        assert!(a != nested_templates(depth - 1, "leaf"));
+        assert!(a.clone() == a);
+    }
 }
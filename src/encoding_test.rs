@@ -0,0 +1,111 @@
+#[cfg(test)]
+mod tests {
+    use crate::encoding::{self, Encoding};
+    use crate::html;
+
+    #[test]
+    fn test_byte_order_marks() {
+        assert_eq!(
+            encoding::decode(b"\xEF\xBB\xBFcaf\xC3\xA9"),
+            ("caf\u{e9}".to_string(), Encoding::Utf8)
+        );
+        assert_eq!(
+            encoding::decode(b"\xFF\xFEh\x00i\x00"),
+            ("hi".to_string(), Encoding::Utf16Le)
+        );
+        assert_eq!(
+            encoding::decode(b"\xFE\xFF\x00h\xD8\x3D\xDE\x00"),
+            ("h\u{1F600}".to_string(), Encoding::Utf16Be)
+        );
+        // A byte order mark wins over a `<meta>` declaration.
+        assert_eq!(
+            encoding::sniff(b"\xEF\xBB\xBF<meta charset=latin1>"),
+            Encoding::Utf8
+        );
+    }
+
+    #[test]
+    fn test_meta_charset() {
+        assert_eq!(
+            encoding::sniff(b"<!DOCTYPE html><meta charset=\"ISO-8859-2\">"),
+            Encoding::Iso8859_2
+        );
+        assert_eq!(
+            encoding::sniff(b"<html><head><META CHARSET='latin1'/>"),
+            Encoding::Windows1252
+        );
+        // A document cannot declare itself UTF-16 in ASCII.
+        assert_eq!(
+            encoding::sniff(b"<meta charset=utf-16>\xE9"),
+            Encoding::Utf8
+        );
+        // Declarations in comments and attribute values do not count.
+        assert_eq!(
+            encoding::sniff(b"<!-- <meta charset=latin2> --><p title='<meta charset=latin3>'>"),
+            Encoding::Utf8
+        );
+    }
+
+    #[test]
+    fn test_meta_http_equiv() {
+        assert_eq!(
+            encoding::sniff(
+                b"<meta http-equiv=\"Content-Type\" content=\"text/html; charset=iso-8859-15\">"
+            ),
+            Encoding::Iso8859_15
+        );
+        assert_eq!(
+            encoding::sniff(b"<meta content='text/html; CHARSET = \"koi8-r\"; x' http-equiv=content-type><meta charset=l5>"),
+            Encoding::Utf8
+        );
+        // Without `http-equiv`, the `content` attribute is ignored.
+        assert_eq!(
+            encoding::sniff(b"<meta content=\"text/html; charset=iso-8859-5\">\xE9"),
+            Encoding::Windows1252
+        );
+    }
+
+    #[test]
+    fn test_fallback() {
+        assert_eq!(
+            encoding::sniff("<p>caf\u{e9}</p>".as_bytes()),
+            Encoding::Utf8
+        );
+        assert_eq!(encoding::sniff(b"<p>caf\xE9</p>"), Encoding::Windows1252);
+    }
+
+    #[test]
+    fn test_single_byte_decoders() {
+        assert_eq!(
+            Encoding::Windows1252.decode(b"\x80 \x93hi\x94 \x81"),
+            "\u{20AC} \u{201C}hi\u{201D} \u{81}"
+        );
+        assert_eq!(Encoding::Iso8859_2.decode(b"\xB1\xE6"), "\u{105}\u{107}");
+        assert_eq!(Encoding::Iso8859_5.decode(b"\xB0"), "\u{410}");
+        assert_eq!(Encoding::Iso8859_15.decode(b"\xA4"), "\u{20AC}");
+        // Bytes with no character in the encoding become U+FFFD.
+        assert_eq!(Encoding::Iso8859_3.decode(b"\xA5"), "\u{FFFD}");
+    }
+
+    #[test]
+    fn test_labels() {
+        assert_eq!(Encoding::for_label(" UTF8 "), Some(Encoding::Utf8));
+        assert_eq!(Encoding::for_label("ascii"), Some(Encoding::Windows1252));
+        assert_eq!(Encoding::for_label("utf-16"), Some(Encoding::Utf16Le));
+        assert_eq!(Encoding::for_label("latin9"), None);
+        assert_eq!(Encoding::Iso8859_8.to_string(), "ISO-8859-8");
+    }
+
+    #[test]
+    fn test_parse_bytes() {
+        let result = html::parse_bytes(b"<meta charset=windows-1252><p>Caf\xE9 \x80");
+
+        assert_eq!(result.encoding, Encoding::Windows1252);
+        let body = &result.root.children[1];
+        assert_eq!(
+            body.children[0].children[0].node_type,
+            crate::dom::NodeType::Text("Caf\u{e9} \u{20AC}".to_string())
+        );
+        assert_eq!(html::parse(String::new()).encoding, Encoding::Utf8);
+    }
+}
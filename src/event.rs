@@ -0,0 +1,119 @@
+//! Events dispatched through a `Document`, with listeners that are plain Rust closures.
+// https://dom.spec.whatwg.org/#events
+
+use crate::document::NodeId;
+
+/// Which part of its path an event is being dispatched through.
+/// https://dom.spec.whatwg.org/#dom-event-eventphase
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum EventPhase {
+    /// The event is not being dispatched.
+    #[default]
+    None,
+    /// Going down from the root to the target's parent.
+    Capturing,
+    /// At the target itself.
+    AtTarget,
+    /// Going back up from the target's parent to the root.
+    Bubbling,
+}
+
+/// An event such as a click, or a custom event with any type.
+/// https://dom.spec.whatwg.org/#interface-event
+#[derive(Debug, Clone, PartialEq)]
+pub struct Event {
+    event_type: String,
+    bubbles: bool,
+    cancelable: bool,
+    pub(crate) target: Option<NodeId>,
+    pub(crate) current_target: Option<NodeId>,
+    pub(crate) phase: EventPhase,
+    pub(crate) stop_propagation: bool,
+    pub(crate) stop_immediate_propagation: bool,
+    canceled: bool,
+}
+
+impl Event {
+    /// An event of the given type that neither bubbles nor can be canceled.
+    pub fn new(event_type: &str) -> Self {
+        Event {
+            event_type: event_type.to_string(),
+            bubbles: false,
+            cancelable: false,
+            target: None,
+            current_target: None,
+            phase: EventPhase::None,
+            stop_propagation: false,
+            stop_immediate_propagation: false,
+            canceled: false,
+        }
+    }
+
+    /// Let the event go back up through the target's ancestors.
+    pub fn bubbles(mut self) -> Self {
+        self.bubbles = true;
+        self
+    }
+
+    /// Let listeners cancel the event with `prevent_default`.
+    pub fn cancelable(mut self) -> Self {
+        self.cancelable = true;
+        self
+    }
+
+    /// https://dom.spec.whatwg.org/#dom-event-type
+    pub fn event_type(&self) -> &str {
+        &self.event_type
+    }
+
+    /// The node the event was dispatched to.
+    /// https://dom.spec.whatwg.org/#dom-event-target
+    pub fn target(&self) -> Option<NodeId> {
+        self.target
+    }
+
+    /// The node whose listener is being called.
+    /// https://dom.spec.whatwg.org/#dom-event-currenttarget
+    pub fn current_target(&self) -> Option<NodeId> {
+        self.current_target
+    }
+
+    pub fn phase(&self) -> EventPhase {
+        self.phase
+    }
+
+    pub fn does_bubble(&self) -> bool {
+        self.bubbles
+    }
+
+    pub fn is_cancelable(&self) -> bool {
+        self.cancelable
+    }
+
+    /// Don't call listeners on any further nodes. The rest of the listeners on the current
+    /// node are still called.
+    /// https://dom.spec.whatwg.org/#dom-event-stoppropagation
+    pub fn stop_propagation(&mut self) {
+        self.stop_propagation = true;
+    }
+
+    /// Don't call any further listeners, even on the current node.
+    /// https://dom.spec.whatwg.org/#dom-event-stopimmediatepropagation
+    pub fn stop_immediate_propagation(&mut self) {
+        self.stop_propagation = true;
+        self.stop_immediate_propagation = true;
+    }
+
+    /// Cancel the event, if it is cancelable.
+    /// https://dom.spec.whatwg.org/#dom-event-preventdefault
+    pub fn prevent_default(&mut self) {
+        if self.cancelable {
+            self.canceled = true;
+        }
+    }
+
+    /// https://dom.spec.whatwg.org/#dom-event-defaultprevented
+    pub fn default_prevented(&self) -> bool {
+        self.canceled
+    }
+}
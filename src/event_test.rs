@@ -0,0 +1,107 @@
+#[cfg(test)]
+mod tests {
+    use std::cell::RefCell;
+    use std::rc::Rc;
+
+    use crate::document::{Document, NodeId};
+    use crate::event::{Event, EventPhase};
+    use crate::html;
+
+    /// A document for `<div><p><b>`, with the ids of those three elements.
+    fn create_document() -> (Document, [NodeId; 3]) {
+        let document = Document::new(html::parse("<div><p><b>Hi</b></p></div>".to_string()).root);
+        let b = document.get_elements_by_tag_name("b").item(&document, 0);
+        let p = document.parent(b.unwrap());
+        let div = document.parent(p.unwrap());
+        (document, [div.unwrap(), p.unwrap(), b.unwrap()])
+    }
+
+    type Log = Rc<RefCell<Vec<String>>>;
+
+    /// Listen on `node`, logging the listener's name and the event phase.
+    fn listen(document: &mut Document, log: &Log, node: NodeId, capture: bool, name: &str) {
+        let log = Rc::clone(log);
+        let name = name.to_string();
+        document.add_event_listener(node, "click", capture, move |event| {
+            log.borrow_mut()
+                .push(format!("{} {:?}", name, event.phase()));
+        });
+    }
+
+    #[test]
+    fn test_dispatch_phases() {
+        let (mut document, [div, p, b]) = create_document();
+        let log = Log::default();
+        listen(&mut document, &log, div, false, "div");
+        listen(&mut document, &log, div, true, "div capture");
+        listen(&mut document, &log, b, false, "b");
+        listen(&mut document, &log, b, true, "b capture");
+        listen(&mut document, &log, p, true, "p capture");
+        let targets = Rc::new(RefCell::new(Vec::new()));
+        let sink = Rc::clone(&targets);
+        document.add_event_listener(p, "click", false, move |event| {
+            sink.borrow_mut()
+                .push((event.target(), event.current_target()));
+        });
+        document.add_event_listener(p, "input", false, |_| panic!("wrong type"));
+
+        let mut event = Event::new("click").bubbles();
+        assert!(document.dispatch_event(b, &mut event));
+        assert_eq!(
+            *log.borrow(),
+            vec![
+                "div capture Capturing",
+                "p capture Capturing",
+                "b capture AtTarget",
+                "b AtTarget",
+                "div Bubbling",
+            ]
+        );
+        assert_eq!(*targets.borrow(), vec![(Some(b), Some(p))]);
+        assert_eq!(event.phase(), EventPhase::None);
+        assert_eq!(event.current_target(), None);
+
+        // Events that don't bubble only reach listeners on the way down and at the target.
+        log.borrow_mut().clear();
+        document.dispatch_event(p, &mut Event::new("click"));
+        assert_eq!(
+            *log.borrow(),
+            vec!["div capture Capturing", "p capture AtTarget"]
+        );
+    }
+
+    #[test]
+    fn test_stop_propagation() {
+        let (mut document, [div, p, b]) = create_document();
+        let log = Log::default();
+        document.add_event_listener(p, "click", false, |event| event.stop_propagation());
+        listen(&mut document, &log, p, false, "p");
+        listen(&mut document, &log, div, false, "div");
+        let immediate = document.add_event_listener(b, "click", false, |event| {
+            event.stop_immediate_propagation()
+        });
+        listen(&mut document, &log, b, false, "b");
+
+        document.dispatch_event(b, &mut Event::new("click").bubbles());
+        assert!(log.borrow().is_empty());
+
+        document.remove_event_listener(immediate);
+        document.dispatch_event(b, &mut Event::new("click").bubbles());
+        assert_eq!(*log.borrow(), vec!["b AtTarget", "p Bubbling"]);
+    }
+
+    #[test]
+    fn test_prevent_default() {
+        let (mut document, [div, _, b]) = create_document();
+        document.add_event_listener(div, "click", false, |event| event.prevent_default());
+
+        let mut event = Event::new("click").bubbles().cancelable();
+        assert!(!document.dispatch_event(b, &mut event));
+        assert!(event.default_prevented());
+
+        // Only cancelable events can be canceled.
+        let mut event = Event::new("click").bubbles();
+        assert!(document.dispatch_event(b, &mut event));
+        assert!(!event.default_prevented());
+    }
+}
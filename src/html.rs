@@ -1,61 +1,626 @@
-//! A simple parser for a tiny subset of HTML.
+//! An HTML parser.
 //!
-//! Can parse basic opening and closing tags, and text nodes.
+//! The tokenizer lives here; `tree_builder` turns its tokens into a DOM tree. The tokenizer
+//! can also be used on its own, to scan markup without building a DOM:
+//!
+//! ```ignore
+//! for token in html::Tokenizer::from_reader(File::open("index.html")?) {
+//!     if let html::Token::StartTag(tag) = token {
+//!         println!("{}", tag.name);
+//!     }
+//! }
+//! ```
 //!
 /// https://html.spec.whatwg.org/multipage/introduction.html#a-quick-introduction-to-html
 ///
+use std::cell::Cell;
+use std::collections::hash_map::Entry;
 use std::collections::HashMap;
+use std::fmt;
+use std::io::{self, Read};
 
 use crate::dom;
+use crate::encoding::{self, Encoding};
+use crate::entities;
+use crate::tree_builder::TreeBuilder;
 
-/// A simple HTML parser that converts HTML strings into a DOM tree.
-/// https://html.spec.whatwg.org/multipage/parsing.html#parsing
-struct Parser {
+/// Splits HTML source into tokens. Iterating over a tokenizer yields every token up to the
+/// end of the input.
+/// https://html.spec.whatwg.org/multipage/parsing.html#tokenization
+pub struct Tokenizer {
+    /// Byte offset in the source of the next character to tokenize.
     pos: usize,
+    /// The input read and not yet dropped, which starts at byte offset `base` of the source.
+    /// Input read from a reader is dropped once it has been tokenized.
     input: String,
+    base: usize,
+    /// Where `base` is, for counting columns on a line whose start has been dropped.
+    base_position: dom::SourcePosition,
+    /// Where more input comes from, until it runs out.
+    reader: Option<Box<dyn Read>>,
+    /// Bytes read that do not make up a whole UTF-8 character yet.
+    pending: Vec<u8>,
+    /// The error that stopped reading, if any.
+    io_error: Option<io::Error>,
+    pub(crate) errors: Vec<ParseError>,
+    /// Byte offset where the most recent token started.
+    pub(crate) token_start: usize,
+    /// Whether `<![CDATA[` starts a CDATA section, as it does in SVG and MathML. Set by the
+    /// tree builder.
+    cdata_allowed: bool,
+    /// Set after the start tag of a raw text element, such as `<style>`, to the element's name
+    /// and whether character references are decoded in it.
+    raw_text: Option<(String, bool)>,
+    /// Set by the tree builder after a `<plaintext>` start tag. The rest of the input is text.
+    plaintext: bool,
+    /// Byte offset of the start of each line, for turning offsets into line and column. Lines
+    /// before the one holding `base` are dropped with the input, and counted in `first_line`.
+    line_starts: Vec<usize>,
+    first_line: usize,
+    /// The last position looked up, to avoid recounting long lines from the start.
+    last_position: Cell<dom::SourcePosition>,
+}
+
+/// A token produced by the tokenizer.
+/// https://html.spec.whatwg.org/multipage/parsing.html#tokenization
+#[derive(Debug, Clone, PartialEq)]
+pub enum Token {
+    Doctype(DoctypeToken),
+    StartTag(Tag),
+    EndTag(Tag),
+    /// A run of text, with character references already decoded.
+    Text(String),
+    Comment(String),
+    ProcessingInstruction {
+        target: String,
+        data: String,
+    },
+    /// The end of the input. The tokenizer's iterator stops instead of returning it.
+    Eof,
+}
+
+/// A start or end tag.
+#[derive(Debug, Clone)]
+pub struct Tag {
+    pub name: String,
+    pub attributes: dom::AttrMap,
+    /// Where each attribute came from in the source.
+    pub attribute_spans: HashMap<String, dom::Span>,
+    /// Whether the tag ended with `/>`.
+    pub self_closing: bool,
+}
+
+/// A doctype as it appears in the source, before it becomes a `dom::DoctypeData`.
+/// Unlike the DOM node, it keeps track of missing identifiers.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct DoctypeToken {
+    pub name: Option<String>,
+    pub public_id: Option<String>,
+    pub system_id: Option<String>,
+    pub force_quirks: bool,
+}
+
+/// Like DOM nodes, tags are compared without their spans.
+impl PartialEq for Tag {
+    fn eq(&self, other: &Self) -> bool {
+        self.name == other.name
+            && self.attributes == other.attributes
+            && self.self_closing == other.self_closing
+    }
+}
+
+impl Tag {
+    /// Creates a tag with the given name and no attributes.
+    pub fn new(name: &str) -> Tag {
+        Tag {
+            name: name.to_string(),
+            attributes: HashMap::new(),
+            attribute_spans: HashMap::new(),
+            self_closing: false,
+        }
+    }
 }
 
-/// Parse the entire HTML document and return the root node of the DOM tree.
-pub fn parse(source: String) -> dom::Node {
-    let mut nodes = Parser::new(source).parse_nodes();
+/// The outcome of parsing a document: the DOM tree plus every error found along the way.
+#[derive(Debug)]
+pub struct ParseResult {
+    /// Root node of the DOM tree.
+    pub root: dom::Node,
+    /// The document's `<!DOCTYPE>` node, if it had one.
+    pub doctype: Option<dom::Node>,
+    /// Comments and processing instructions that sit outside the root element.
+    pub document_nodes: Vec<dom::Node>,
+    /// The mode decided by the doctype, for style and layout to branch on.
+    pub mode: dom::DocumentMode,
+    /// Parse errors, in the order they were encountered.
+    pub errors: Vec<ParseError>,
+    /// The encoding the document was decoded from.
+    pub encoding: Encoding,
+}
+
+/// A recoverable error found while parsing. The parser never stops on an error.
+/// https://html.spec.whatwg.org/multipage/parsing.html#parse-errors
+#[derive(Debug, Clone, PartialEq)]
+pub struct ParseError {
+    pub kind: ParseErrorKind,
+    /// Byte offset in the source where the error was detected.
+    pub offset: usize,
+    /// 1-based line number.
+    pub line: usize,
+    /// 1-based column number, counted in characters.
+    pub column: usize,
+}
 
-    // If the document contains a root element, just return it. Otherwise, create one.
-    if nodes.len() == 1 {
-        nodes.swap_remove(0)
-    } else {
-        dom::elem("html".to_string(), HashMap::new(), nodes)
+/// The kind of a parse error. Names follow the spec's error codes where one exists.
+#[derive(Debug, Clone, PartialEq)]
+pub enum ParseErrorKind {
+    /// The input ended inside a tag.
+    EofInTag,
+    /// The input ended inside a comment.
+    EofInComment,
+    /// A comment was closed right after it opened, as in `<!-->` or `<!--->`.
+    AbruptClosingOfEmptyComment,
+    /// A comment was closed with `--!>` instead of `-->`.
+    IncorrectlyClosedComment,
+    /// A `<` was followed by a character that cannot start a tag name.
+    InvalidFirstCharacterOfTagName,
+    /// An end tag had no name, as in `</>`.
+    MissingEndTagName,
+    /// The input ended right after a `<` or `</`.
+    EofBeforeTagName,
+    /// An end tag had attributes.
+    EndTagWithAttributes,
+    /// An end tag ended with `/>`.
+    EndTagWithTrailingSolidus,
+    /// An attribute started with `=`.
+    UnexpectedEqualsSignBeforeAttributeName,
+    /// An attribute name contained a character that is not allowed.
+    UnexpectedCharacterInAttributeName,
+    /// An attribute had an `=` but no value.
+    MissingAttributeValue,
+    /// An unquoted attribute value contained a quote, `<`, `=` or backtick.
+    UnexpectedCharacterInUnquotedAttributeValue,
+    /// A `/` inside a tag was not followed by `>`.
+    UnexpectedSolidusInTag,
+    /// An attribute appeared more than once on the same tag.
+    DuplicateAttribute(String),
+    /// The input ended inside a doctype.
+    EofInDoctype,
+    /// A doctype had no name, as in `<!DOCTYPE>`.
+    MissingDoctypeName,
+    /// A doctype name was followed by something other than `PUBLIC` or `SYSTEM`.
+    InvalidCharacterSequenceAfterDoctypeName,
+    /// A doctype public identifier was not quoted.
+    MissingQuoteBeforeDoctypePublicIdentifier,
+    /// A doctype system identifier was not quoted.
+    MissingQuoteBeforeDoctypeSystemIdentifier,
+    /// A `>` appeared inside a doctype public identifier.
+    AbruptDoctypePublicIdentifier,
+    /// A `>` appeared inside a doctype system identifier.
+    AbruptDoctypeSystemIdentifier,
+    /// Something other than `>` followed the doctype identifiers.
+    UnexpectedCharacterAfterDoctypeSystemIdentifier,
+    /// A doctype appeared after the start of the document.
+    UnexpectedDoctype,
+    /// A `<!` was not followed by `--`, `DOCTYPE` or `[CDATA[`.
+    IncorrectlyOpenedComment,
+    /// A `<?` was not followed by a processing instruction target.
+    UnexpectedQuestionMarkInsteadOfTagName,
+    /// The input ended inside a processing instruction.
+    EofInProcessingInstruction,
+    /// The input ended inside a CDATA section.
+    EofInCdata,
+    /// A CDATA section appeared outside foreign content.
+    CdataInHtmlContent,
+    /// A character reference was not terminated by `;`.
+    MissingSemicolonAfterCharacterReference,
+    /// A `&name;` did not match any named character reference.
+    UnknownNamedCharacterReference,
+    /// A `&#` or `&#x` was not followed by any digits.
+    AbsenceOfDigitsInNumericCharacterReference,
+    /// A numeric character reference referred to U+0000.
+    NullCharacterReference,
+    /// A numeric character reference was beyond U+10FFFF.
+    CharacterReferenceOutsideUnicodeRange,
+    /// A numeric character reference referred to a surrogate.
+    SurrogateCharacterReference,
+    /// A numeric character reference referred to a noncharacter.
+    NoncharacterCharacterReference,
+    /// A numeric character reference referred to a control character.
+    ControlCharacterReference,
+    /// A tag that is not a void element ended with `/>`.
+    NonVoidHtmlElementStartTagWithTrailingSolidus,
+    /// An end tag closed elements other than the current one, `expected`.
+    MismatchedEndTag { expected: String, found: String },
+    /// An end tag appeared where it is not allowed, so it was ignored.
+    UnexpectedEndTag(String),
+    /// A start tag appeared where it is not allowed.
+    UnexpectedStartTag(String),
+    /// Text appeared where only markup is allowed, such as directly inside a table.
+    UnexpectedText,
+    /// An element was closed implicitly, or was still open when the document ended.
+    MissingEndTag(String),
+}
+
+impl fmt::Display for ParseErrorKind {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ParseErrorKind::EofInTag => write!(f, "eof-in-tag"),
+            ParseErrorKind::EofInComment => write!(f, "eof-in-comment"),
+            ParseErrorKind::AbruptClosingOfEmptyComment => {
+                write!(f, "abrupt-closing-of-empty-comment")
+            }
+            ParseErrorKind::IncorrectlyClosedComment => write!(f, "incorrectly-closed-comment"),
+            ParseErrorKind::InvalidFirstCharacterOfTagName => {
+                write!(f, "invalid-first-character-of-tag-name")
+            }
+            ParseErrorKind::MissingEndTagName => write!(f, "missing-end-tag-name"),
+            ParseErrorKind::EofBeforeTagName => write!(f, "eof-before-tag-name"),
+            ParseErrorKind::EndTagWithAttributes => write!(f, "end-tag-with-attributes"),
+            ParseErrorKind::EndTagWithTrailingSolidus => {
+                write!(f, "end-tag-with-trailing-solidus")
+            }
+            ParseErrorKind::UnexpectedEqualsSignBeforeAttributeName => {
+                write!(f, "unexpected-equals-sign-before-attribute-name")
+            }
+            ParseErrorKind::UnexpectedCharacterInAttributeName => {
+                write!(f, "unexpected-character-in-attribute-name")
+            }
+            ParseErrorKind::MissingAttributeValue => write!(f, "missing-attribute-value"),
+            ParseErrorKind::UnexpectedCharacterInUnquotedAttributeValue => {
+                write!(f, "unexpected-character-in-unquoted-attribute-value")
+            }
+            ParseErrorKind::UnexpectedSolidusInTag => write!(f, "unexpected-solidus-in-tag"),
+            ParseErrorKind::DuplicateAttribute(name) => {
+                write!(f, "duplicate-attribute '{}'", name)
+            }
+            ParseErrorKind::EofInDoctype => write!(f, "eof-in-doctype"),
+            ParseErrorKind::MissingDoctypeName => write!(f, "missing-doctype-name"),
+            ParseErrorKind::InvalidCharacterSequenceAfterDoctypeName => {
+                write!(f, "invalid-character-sequence-after-doctype-name")
+            }
+            ParseErrorKind::MissingQuoteBeforeDoctypePublicIdentifier => {
+                write!(f, "missing-quote-before-doctype-public-identifier")
+            }
+            ParseErrorKind::MissingQuoteBeforeDoctypeSystemIdentifier => {
+                write!(f, "missing-quote-before-doctype-system-identifier")
+            }
+            ParseErrorKind::AbruptDoctypePublicIdentifier => {
+                write!(f, "abrupt-doctype-public-identifier")
+            }
+            ParseErrorKind::AbruptDoctypeSystemIdentifier => {
+                write!(f, "abrupt-doctype-system-identifier")
+            }
+            ParseErrorKind::UnexpectedCharacterAfterDoctypeSystemIdentifier => {
+                write!(f, "unexpected-character-after-doctype-system-identifier")
+            }
+            ParseErrorKind::UnexpectedDoctype => write!(f, "unexpected-doctype"),
+            ParseErrorKind::IncorrectlyOpenedComment => write!(f, "incorrectly-opened-comment"),
+            ParseErrorKind::UnexpectedQuestionMarkInsteadOfTagName => {
+                write!(f, "unexpected-question-mark-instead-of-tag-name")
+            }
+            ParseErrorKind::EofInProcessingInstruction => {
+                write!(f, "eof-in-processing-instruction")
+            }
+            ParseErrorKind::EofInCdata => write!(f, "eof-in-cdata"),
+            ParseErrorKind::CdataInHtmlContent => write!(f, "cdata-in-html-content"),
+            ParseErrorKind::MissingSemicolonAfterCharacterReference => {
+                write!(f, "missing-semicolon-after-character-reference")
+            }
+            ParseErrorKind::UnknownNamedCharacterReference => {
+                write!(f, "unknown-named-character-reference")
+            }
+            ParseErrorKind::AbsenceOfDigitsInNumericCharacterReference => {
+                write!(f, "absence-of-digits-in-numeric-character-reference")
+            }
+            ParseErrorKind::NullCharacterReference => write!(f, "null-character-reference"),
+            ParseErrorKind::CharacterReferenceOutsideUnicodeRange => {
+                write!(f, "character-reference-outside-unicode-range")
+            }
+            ParseErrorKind::SurrogateCharacterReference => {
+                write!(f, "surrogate-character-reference")
+            }
+            ParseErrorKind::NoncharacterCharacterReference => {
+                write!(f, "noncharacter-character-reference")
+            }
+            ParseErrorKind::ControlCharacterReference => write!(f, "control-character-reference"),
+            ParseErrorKind::NonVoidHtmlElementStartTagWithTrailingSolidus => {
+                write!(f, "non-void-html-element-start-tag-with-trailing-solidus")
+            }
+            ParseErrorKind::MismatchedEndTag { expected, found } => {
+                write!(f, "mismatched-end-tag '{}', expected '{}'", found, expected)
+            }
+            ParseErrorKind::UnexpectedEndTag(name) => write!(f, "unexpected-end-tag '{}'", name),
+            ParseErrorKind::UnexpectedStartTag(name) => {
+                write!(f, "unexpected-start-tag '{}'", name)
+            }
+            ParseErrorKind::UnexpectedText => write!(f, "unexpected-text"),
+            ParseErrorKind::MissingEndTag(name) => write!(f, "missing-end-tag '{}'", name),
+        }
     }
 }
 
-impl Parser {
-    // Create a new parser struct
-    fn new(input: String) -> Self {
-        Parser { pos: 0, input }
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}:{}: {}", self.line, self.column, self.kind)
     }
+}
+
+/// Parse the entire HTML document and return the root node of the DOM tree,
+/// together with any parse errors.
+pub fn parse(source: String) -> ParseResult {
+    parse_tokens(Tokenizer::new(source))
+}
+
+/// Parse an HTML document from its raw bytes, detecting and decoding its character encoding
+/// first. Spans and error offsets refer to the decoded UTF-8 text.
+pub fn parse_bytes(bytes: &[u8]) -> ParseResult {
+    let (source, encoding) = encoding::decode(bytes);
+    let mut result = parse(source);
+    result.encoding = encoding;
+    result
+}
 
-    /// Get the next character in the input string.
-    fn next_char(&self) -> char {
-        self.input[self.pos..].chars().next().unwrap()
+/// Parse an HTML document as it is read from `reader`, which must produce UTF-8.
+pub fn parse_reader<R: Read + 'static>(reader: R) -> ParseResult {
+    parse_tokens(Tokenizer::from_reader(reader))
+}
+
+/// Build the DOM tree from the tokens of `tokenizer`.
+pub fn parse_tokens(tokenizer: Tokenizer) -> ParseResult {
+    TreeBuilder::new(tokenizer).build()
+}
+
+impl Iterator for Tokenizer {
+    type Item = Token;
+
+    fn next(&mut self) -> Option<Token> {
+        match self.next_token() {
+            Token::Eof => None,
+            token => Some(token),
+        }
+    }
+}
+
+/// How much input is read from a reader at a time.
+const CHUNK_SIZE: usize = 8192;
+
+impl Tokenizer {
+    /// Create a tokenizer for a whole document.
+    pub fn new(input: String) -> Self {
+        Tokenizer {
+            pos: 0,
+            base: 0,
+            base_position: dom::SourcePosition {
+                offset: 0,
+                line: 1,
+                column: 1,
+            },
+            reader: None,
+            pending: Vec::new(),
+            io_error: None,
+            errors: Vec::new(),
+            token_start: 0,
+            cdata_allowed: false,
+            raw_text: None,
+            plaintext: false,
+            line_starts: std::iter::once(0)
+                .chain(input.match_indices('\n').map(|(i, _)| i + 1))
+                .collect(),
+            first_line: 0,
+            last_position: Cell::new(dom::SourcePosition {
+                offset: 0,
+                line: 1,
+                column: 1,
+            }),
+            input,
+        }
+    }
+
+    /// Create a tokenizer that reads its input in chunks from `reader`, which must produce
+    /// UTF-8. Invalid bytes become U+FFFD REPLACEMENT CHARACTER. Only the input that has not
+    /// been tokenized yet is kept, so memory use does not grow with the size of the input.
+    pub fn from_reader<R: Read + 'static>(reader: R) -> Self {
+        let mut tokenizer = Tokenizer::new(String::new());
+        tokenizer.reader = Some(Box::new(reader));
+        tokenizer
+    }
+
+    /// Allow CDATA sections, which only exist in foreign content such as `<svg>`. Elsewhere
+    /// `<![CDATA[` starts a bogus comment.
+    /// https://html.spec.whatwg.org/multipage/parsing.html#markup-declaration-open-state
+    pub(crate) fn set_cdata_allowed(&mut self, cdata_allowed: bool) {
+        self.cdata_allowed = cdata_allowed;
+    }
+
+    /// Parse the contents of the element just started as markup after all. Used for elements
+    /// like `<style>` in SVG, which are not raw text elements.
+    pub(crate) fn cancel_raw_text(&mut self) {
+        self.raw_text = None;
+    }
+
+    /// Read the rest of the input as text, for the contents of a `<plaintext>` element, which
+    /// has no end tag.
+    /// https://html.spec.whatwg.org/multipage/parsing.html#plaintext-state
+    pub(crate) fn set_plaintext(&mut self) {
+        self.plaintext = true;
+    }
+
+    /// The parse errors found so far.
+    pub fn errors(&self) -> &[ParseError] {
+        &self.errors
+    }
+
+    /// The error that stopped reading the input early, if there was one. The tokens up to
+    /// that point are still produced.
+    pub fn io_error(&self) -> Option<&io::Error> {
+        self.io_error.as_ref()
+    }
+
+    /// The part of the source the most recent token came from.
+    pub fn token_span(&self) -> dom::Span {
+        dom::Span {
+            start: self.source_position(self.token_start),
+            end: self.source_position(self.pos),
+        }
+    }
+
+    /// Produce the next token. Once the input is used up, this always returns `Token::Eof`.
+    pub fn next_token(&mut self) -> Token {
+        if self.reader.is_some() && self.pos - self.base >= CHUNK_SIZE {
+            self.drop_tokenized_input();
+        }
+        loop {
+            let (pos, errors, raw_text) = (self.pos, self.errors.len(), self.raw_text.clone());
+            let token = self.read_token();
+            // A token that runs up to the end of the input read so far may continue in the
+            // next chunk, so read more and try again. Plain text can be split anywhere.
+            if !self.eof() || self.reader.is_none() || self.plaintext && token != Token::Eof {
+                return token;
+            }
+            self.pos = pos;
+            self.errors.truncate(errors);
+            self.raw_text = raw_text;
+            // Read at least as much again as the token has so far, so that a long token is
+            // only tokenized again a few times.
+            let token_len = self.rest().len();
+            self.read_chunk();
+            while self.reader.is_some() && self.rest().len() < 2 * token_len {
+                self.read_chunk();
+            }
+        }
+    }
+
+    /// Drop the input before `pos`, which has been tokenized already.
+    fn drop_tokenized_input(&mut self) {
+        self.base_position = self.source_position(self.pos);
+        self.input.drain(..self.pos - self.base);
+        self.base = self.pos;
+        // Keep the start of the line holding `base`.
+        let lines = self
+            .line_starts
+            .partition_point(|&start| start <= self.base)
+            - 1;
+        self.line_starts.drain(..lines);
+        self.first_line += lines;
+    }
+
+    /// Add the next chunk from the reader to the input. Drops the reader once it runs out.
+    fn read_chunk(&mut self) {
+        let mut chunk = [0; CHUNK_SIZE];
+        let Some(reader) = &mut self.reader else {
+            return;
+        };
+        let read = loop {
+            match reader.read(&mut chunk) {
+                Err(e) if e.kind() == io::ErrorKind::Interrupted => {}
+                Err(e) => {
+                    self.io_error = Some(e);
+                    break 0;
+                }
+                Ok(read) => break read,
+            }
+        };
+
+        if read == 0 {
+            self.reader = None;
+            let rest = String::from_utf8_lossy(&self.pending).into_owned();
+            self.pending.clear();
+            self.push_input(&rest);
+            return;
+        }
+
+        // Keep a character that is split between chunks until the rest of it arrives.
+        self.pending.extend_from_slice(&chunk[..read]);
+        let valid = match std::str::from_utf8(&self.pending) {
+            Ok(_) => self.pending.len(),
+            Err(e) if e.error_len().is_none() => e.valid_up_to(),
+            Err(_) => self.pending.len(),
+        };
+        let text = String::from_utf8_lossy(&self.pending[..valid]).into_owned();
+        self.pending.drain(..valid);
+        self.push_input(&text);
+    }
+
+    fn push_input(&mut self, text: &str) {
+        let base = self.base + self.input.len();
+        self.line_starts
+            .extend(text.match_indices('\n').map(|(i, _)| base + i + 1));
+        self.input.push_str(text);
+    }
+
+    /// Tokenize the input read so far.
+    fn read_token(&mut self) -> Token {
+        self.token_start = self.pos;
+        if let Some((tag_name, escapable)) = self.raw_text.take() {
+            let text = self.parse_raw_text(&tag_name, escapable);
+            if !text.is_empty() {
+                return Token::Text(text);
+            }
+            self.token_start = self.pos;
+        }
+
+        if self.eof() {
+            Token::Eof
+        } else if self.plaintext {
+            let text = self.rest().to_string();
+            self.pos += text.len();
+            Token::Text(text)
+        } else if self.starts_with("<!--") {
+            self.parse_comment()
+        } else if self.starts_with_ignore_case("<!doctype") {
+            Token::Doctype(self.parse_doctype())
+        } else if self.starts_with("<![CDATA[") && self.cdata_allowed {
+            self.parse_cdata()
+        } else if self.starts_with("<!") {
+            if self.starts_with("<![CDATA[") {
+                self.error(ParseErrorKind::CdataInHtmlContent);
+            }
+            self.parse_bogus_comment()
+        } else if self.starts_with("<?") {
+            self.parse_processing_instruction()
+        } else if self.starts_with("</") {
+            self.parse_end_tag()
+        } else if self.starts_with("<") {
+            self.parse_start_tag()
+        } else {
+            Token::Text(self.parse_text())
+        }
+    }
+
+    /// The input from the current position on.
+    fn rest(&self) -> &str {
+        &self.input[self.pos - self.base..]
+    }
+
+    /// Get the next character in the input string, or `None` at the end of the input.
+    fn next_char(&self) -> Option<char> {
+        self.rest().chars().next()
     }
 
     /// Check if the input string starts with a given substring.
     fn starts_with(&self, s: &str) -> bool {
-        self.input[self.pos..].starts_with(s)
+        self.rest().starts_with(s)
+    }
+
+    /// Check if the input string starts with a given ASCII substring, ignoring case.
+    fn starts_with_ignore_case(&self, s: &str) -> bool {
+        self.rest()
+            .get(..s.len())
+            .is_some_and(|prefix| prefix.eq_ignore_ascii_case(s))
     }
 
     /// Check if the parser has reached the end of the input.
     fn eof(&self) -> bool {
-        self.pos >= self.input.len()
+        self.rest().is_empty()
     }
 
     /// Consume the current character and advance the position to the next character.
-    fn consume_char(&mut self) -> char {
-        let mut iter = self.input[self.pos..].char_indices();
-        let (_, cur_char) = iter.next().unwrap();
-        let (next_pos, _) = iter.next().unwrap_or((1, ' '));
-        self.pos += next_pos;
+    fn consume_char(&mut self) -> Option<char> {
+        let cur_char = self.next_char()?;
+        self.pos += cur_char.len_utf8();
 
-        cur_char
+        Some(cur_char)
     }
 
     /// Consume characters while the given predicate function returns true.
@@ -64,8 +629,9 @@ impl Parser {
         F: Fn(char) -> bool,
     {
         let mut result = String::new();
-        while !self.eof() && test(self.next_char()) {
-            result.push(self.consume_char());
+        while let Some(c) = self.next_char().filter(|&c| test(c)) {
+            self.pos += c.len_utf8();
+            result.push(c);
         }
 
         result
@@ -73,135 +639,628 @@ impl Parser {
 
     /// Consume whitespace characters.
     fn consume_whitespace(&mut self) {
-        self.consume_while(char::is_whitespace);
+        self.consume_while(|c| c.is_ascii_whitespace());
+    }
+
+    /// Record a parse error at the current position.
+    fn error(&mut self, kind: ParseErrorKind) {
+        self.error_at(kind, self.pos);
+    }
+
+    /// Record a parse error at the given byte offset.
+    pub(crate) fn error_at(&mut self, kind: ParseErrorKind, offset: usize) {
+        let position = self.source_position(offset);
+        self.errors.push(ParseError {
+            kind,
+            offset,
+            line: position.line,
+            column: position.column,
+        });
+    }
+
+    /// The current byte offset in the input.
+    pub(crate) fn offset(&self) -> usize {
+        self.pos
+    }
+
+    /// Find the line and column of a byte offset.
+    /// The offset must not be before the start of the current token.
+    pub(crate) fn source_position(&self, offset: usize) -> dom::SourcePosition {
+        let line_index = self.line_starts.partition_point(|&start| start <= offset);
+        let line = self.first_line + line_index;
+        let line_start = self.line_starts[line_index - 1];
+        // Count columns from the nearest known position on the same line.
+        let last = self.last_position.get();
+        let from = if last.line == line && self.base <= last.offset && last.offset <= offset {
+            last
+        } else if line_start < self.base {
+            self.base_position
+        } else {
+            dom::SourcePosition {
+                offset: line_start,
+                line,
+                column: 1,
+            }
+        };
+        let column = from.column
+            + self.input[from.offset - self.base..offset - self.base]
+                .chars()
+                .count();
+        let position = dom::SourcePosition {
+            offset,
+            line,
+            column,
+        };
+        self.last_position.set(position);
+        position
+    }
+
+    /// The span from `start` to the current offset.
+    fn span_from(&self, start: usize) -> dom::Span {
+        dom::Span {
+            start: self.source_position(start),
+            end: self.source_position(self.pos),
+        }
     }
 
     /// Parse the tag name of an HTML element.
+    /// https://html.spec.whatwg.org/multipage/parsing.html#tag-name-state
     fn parse_tag_name(&mut self) -> String {
-        self.consume_while(|c| match c {
-            'a'..='z' | 'A'..='Z' | '0'..='9' => true,
-            _ => false,
-        })
+        let name = self.consume_while(|c| !c.is_ascii_whitespace() && c != '/' && c != '>');
+        name.to_ascii_lowercase()
     }
 
     /// Parse the text content of an HTML node.
-    fn parse_text(&mut self) -> dom::Node {
-        dom::text(self.consume_while(|c| c != '<'))
+    fn parse_text(&mut self) -> String {
+        // A `<` that does not start markup is kept as text, so always take the first character.
+        let start = self.pos;
+        let mut text: String = self.consume_char().into_iter().collect();
+        text.push_str(&self.consume_while(|c| c != '<'));
+        self.decode_character_references(&text, start, false)
     }
 
-    /// Parse the value of an HTML attribute.
-    // Parse a quoted value.
+    /// Parse the name of an HTML attribute.
+    /// https://html.spec.whatwg.org/multipage/parsing.html#attribute-name-state
+    fn parse_attr_name(&mut self) -> String {
+        let start = self.pos;
+        let name =
+            self.consume_while(|c| !c.is_ascii_whitespace() && !matches!(c, '/' | '>' | '='));
+        if name.contains(['"', '\'', '<']) {
+            self.error_at(ParseErrorKind::UnexpectedCharacterInAttributeName, start);
+        }
+        name.to_ascii_lowercase()
+    }
+
+    /// Parse the value of an HTML attribute, either quoted or unquoted.
     fn parse_attr_value(&mut self) -> String {
-        let open_quote = self.consume_char();
-        assert!(open_quote == '"' || open_quote == '\'');
-        let value = self.consume_while(|c| c != open_quote);
-        assert!(self.consume_char() == open_quote);
-        value
+        match self.next_char() {
+            Some(open_quote @ ('"' | '\'')) => {
+                self.consume_char();
+                let start = self.pos;
+                let value = self.consume_while(|c| c != open_quote);
+                if self.consume_char().is_none() {
+                    self.error(ParseErrorKind::EofInTag);
+                }
+                self.decode_character_references(&value, start, true)
+            }
+            Some('>') | None => {
+                self.error(ParseErrorKind::MissingAttributeValue);
+                String::new()
+            }
+            _ => {
+                let start = self.pos;
+                let value = self.consume_while(|c| !c.is_ascii_whitespace() && c != '>');
+                if value.contains(['"', '\'', '<', '=', '`']) {
+                    self.error_at(
+                        ParseErrorKind::UnexpectedCharacterInUnquotedAttributeValue,
+                        start,
+                    );
+                }
+                self.decode_character_references(&value, start, true)
+            }
+        }
+    }
+
+    /// Replace the character references in `raw`, which starts at byte offset `start` of the
+    /// input, with the characters they stand for.
+    /// https://html.spec.whatwg.org/multipage/parsing.html#character-reference-state
+    fn decode_character_references(
+        &mut self,
+        raw: &str,
+        start: usize,
+        in_attribute: bool,
+    ) -> String {
+        let mut result = String::with_capacity(raw.len());
+        let mut rest = raw;
+        while let Some(amp) = rest.find('&') {
+            result.push_str(&rest[..amp]);
+            let offset = start + (raw.len() - rest.len()) + amp;
+            let after = &rest[amp + 1..];
+            let (decoded, consumed) = if after.starts_with('#') {
+                self.decode_numeric_reference(after, offset)
+            } else {
+                self.decode_named_reference(after, offset, in_attribute)
+            };
+            match decoded {
+                Some(decoded) => result.push_str(&decoded),
+                // Not a character reference, so the `&` is kept as it is.
+                None => result.push('&'),
+            }
+            rest = &after[consumed..];
+        }
+        result.push_str(rest);
+        result
+    }
+
+    /// Decode a named character reference such as `&amp;`. `after` is the text following the
+    /// `&`. Returns the replacement, if any, and how many bytes of `after` it used.
+    /// https://html.spec.whatwg.org/multipage/parsing.html#named-character-reference-state
+    fn decode_named_reference(
+        &mut self,
+        after: &str,
+        offset: usize,
+        in_attribute: bool,
+    ) -> (Option<String>, usize) {
+        let name_len = after
+            .bytes()
+            .take(entities::LONGEST_NAME)
+            .take_while(u8::is_ascii_alphanumeric)
+            .count();
+        let terminated = after[name_len..].starts_with(';');
+        let candidate_len = if terminated { name_len + 1 } else { name_len };
+
+        // Use the longest name in the table that is a prefix of the input.
+        for len in (1..=candidate_len).rev() {
+            let name = &after[..len];
+            let value = match entities::lookup(name) {
+                Some(value) => value,
+                None => continue,
+            };
+            if !name.ends_with(';') {
+                // For historical reasons, `&copy=` or `&notit` in an attribute value is left
+                // alone, so that URLs like `?a=1&copy=2` keep working.
+                let next = after[len..].chars().next();
+                if in_attribute && next.is_some_and(|c| c == '=' || c.is_ascii_alphanumeric()) {
+                    return (None, 0);
+                }
+                self.error_at(
+                    ParseErrorKind::MissingSemicolonAfterCharacterReference,
+                    offset + 1 + len,
+                );
+            }
+            return (Some(value.to_string()), len);
+        }
+
+        if name_len > 0 && terminated {
+            self.error_at(ParseErrorKind::UnknownNamedCharacterReference, offset);
+        }
+        (None, 0)
+    }
+
+    /// Decode a numeric character reference such as `&#169;` or `&#x1F600;`. `after` is the
+    /// text following the `&`, starting with `#`.
+    /// https://html.spec.whatwg.org/multipage/parsing.html#numeric-character-reference-state
+    fn decode_numeric_reference(&mut self, after: &str, offset: usize) -> (Option<String>, usize) {
+        let (radix, prefix_len) = if after[1..].starts_with(['x', 'X']) {
+            (16, 2)
+        } else {
+            (10, 1)
+        };
+        let digits: &str = &after[prefix_len..];
+        let digits = &digits[..digits
+            .find(|c: char| !c.is_digit(radix))
+            .unwrap_or(digits.len())];
+        if digits.is_empty() {
+            self.error_at(
+                ParseErrorKind::AbsenceOfDigitsInNumericCharacterReference,
+                offset,
+            );
+            return (None, 0);
+        }
+
+        let mut consumed = prefix_len + digits.len();
+        if after[consumed..].starts_with(';') {
+            consumed += 1;
+        } else {
+            self.error_at(
+                ParseErrorKind::MissingSemicolonAfterCharacterReference,
+                offset + 1 + consumed,
+            );
+        }
+
+        // Saturate instead of overflowing; anything this large is out of range anyway.
+        let code = digits.chars().fold(0u32, |code, c| {
+            code.saturating_mul(radix)
+                .saturating_add(c.to_digit(radix).unwrap_or(0))
+        });
+        let error = match code {
+            0 => Some(ParseErrorKind::NullCharacterReference),
+            0xD800..=0xDFFF => Some(ParseErrorKind::SurrogateCharacterReference),
+            0x110000.. => Some(ParseErrorKind::CharacterReferenceOutsideUnicodeRange),
+            0xFDD0..=0xFDEF => Some(ParseErrorKind::NoncharacterCharacterReference),
+            _ if code & 0xFFFE == 0xFFFE => Some(ParseErrorKind::NoncharacterCharacterReference),
+            0x0D | 0x00..=0x08 | 0x0B | 0x0E..=0x1F | 0x7F..=0x9F => {
+                Some(ParseErrorKind::ControlCharacterReference)
+            }
+            _ => None,
+        };
+        if let Some(error) = error {
+            self.error_at(error, offset);
+        }
+
+        let c = match code {
+            0 => 0xFFFD,
+            0x80..=0x9F => C1_REPLACEMENTS[(code - 0x80) as usize].unwrap_or(code),
+            _ => code,
+        };
+        let c = char::from_u32(c).unwrap_or(char::REPLACEMENT_CHARACTER);
+        (Some(c.to_string()), consumed)
     }
 
     /// Parse an HTML attribute.
-    // Parse a single name="value" pair.
+    // Parse a `name`, `name=value`, `name='value'` or `name="value"` attribute.
     fn parse_attr(&mut self) -> (String, String) {
-        let name = self.parse_tag_name();
-        assert!(self.consume_char() == '=');
+        let mut name = String::new();
+        if self.next_char() == Some('=') {
+            // The `=` becomes the first character of the attribute name.
+            self.error(ParseErrorKind::UnexpectedEqualsSignBeforeAttributeName);
+            self.consume_char();
+            name.push('=');
+        }
+        name.push_str(&self.parse_attr_name());
+
+        self.consume_whitespace();
+        if self.next_char() != Some('=') {
+            // A boolean attribute, such as `disabled`, has the empty string as its value.
+            return (name, String::new());
+        }
+        self.consume_char();
+        self.consume_whitespace();
         let value = self.parse_attr_value();
         (name, value)
     }
 
-    /// Parse a map of HTML attributes.
-    fn parse_attributes(&mut self) -> dom::AttrMap {
-        let mut attributes = HashMap::new();
+    /// Parse the attributes of a tag, up to and including the closing `>`.
+    fn parse_attributes(&mut self, tag: &mut Tag) {
         loop {
             self.consume_whitespace();
-            if self.next_char() == '>' {
-                break;
+            match self.next_char() {
+                None => {
+                    self.error(ParseErrorKind::EofInTag);
+                    break;
+                }
+                Some('>') => {
+                    self.consume_char();
+                    break;
+                }
+                Some('/') => {
+                    self.consume_char();
+                    if self.next_char() == Some('>') {
+                        self.consume_char();
+                        tag.self_closing = true;
+                        break;
+                    }
+                    self.error(ParseErrorKind::UnexpectedSolidusInTag);
+                }
+                Some(_) => {
+                    let start = self.pos;
+                    let (name, value) = self.parse_attr();
+                    // When an attribute is repeated, the first occurrence wins.
+                    match tag.attributes.entry(name) {
+                        Entry::Occupied(entry) => {
+                            let name = entry.key().clone();
+                            self.error_at(ParseErrorKind::DuplicateAttribute(name), start);
+                        }
+                        Entry::Vacant(entry) => {
+                            let span = self.span_from(start);
+                            tag.attribute_spans.insert(entry.key().clone(), span);
+                            entry.insert(value);
+                        }
+                    }
+                }
             }
-            let (name, value) = self.parse_attr();
-            attributes.insert(name, value);
         }
-        attributes
     }
 
-    /// Parse an HTML node.
-    fn parse_node(&mut self) -> dom::Node {
+    /// Parse a start tag such as `<div class="x">`.
+    /// https://html.spec.whatwg.org/multipage/parsing.html#tag-open-state
+    fn parse_start_tag(&mut self) -> Token {
+        let start = self.pos;
+        self.consume_char();
         match self.next_char() {
-            '<' => {
-                if self.starts_with("<!--") {
-                    self.parse_comment()
-                } else {
-                    self.parse_element()
-                }
+            Some(c) if c.is_ascii_alphabetic() => {}
+            next => {
+                // Not a tag after all, so the `<` is just text.
+                self.error(match next {
+                    None => ParseErrorKind::EofBeforeTagName,
+                    Some(_) => ParseErrorKind::InvalidFirstCharacterOfTagName,
+                });
+                self.pos = start;
+                return Token::Text(self.parse_text());
             }
-            _ => self.parse_text(),
         }
+
+        let mut tag = Tag::new(&self.parse_tag_name());
+        self.parse_attributes(&mut tag);
+        if is_raw_text_element(&tag.name) {
+            self.raw_text = Some((tag.name.clone(), false));
+        } else if is_escapable_raw_text_element(&tag.name) {
+            self.raw_text = Some((tag.name.clone(), true));
+        }
+        Token::StartTag(tag)
     }
 
-    /// Parse a vector of HTML nodes.
-    fn parse_nodes(&mut self) -> Vec<dom::Node> {
-        let mut nodes = Vec::new();
-        loop {
-            self.consume_whitespace();
-            if self.eof() || self.starts_with("</") {
-                break;
+    /// Parse an end tag such as `</div>`.
+    /// https://html.spec.whatwg.org/multipage/parsing.html#end-tag-open-state
+    fn parse_end_tag(&mut self) -> Token {
+        self.pos += "</".len();
+        match self.next_char() {
+            Some(c) if c.is_ascii_alphabetic() => {}
+            Some('>') => {
+                self.error(ParseErrorKind::MissingEndTagName);
+                self.consume_char();
+                return self.read_token();
             }
-            nodes.push(self.parse_node());
+            None => {
+                self.error(ParseErrorKind::EofBeforeTagName);
+                return Token::Text("</".to_string());
+            }
+            Some(_) => {
+                self.error(ParseErrorKind::InvalidFirstCharacterOfTagName);
+                let data = self.consume_while(|c| c != '>');
+                self.consume_char();
+                return Token::Comment(data);
+            }
+        }
+
+        let mut tag = Tag::new(&self.parse_tag_name());
+        self.parse_attributes(&mut tag);
+        if !tag.attributes.is_empty() {
+            self.error(ParseErrorKind::EndTagWithAttributes);
+        }
+        if tag.self_closing {
+            self.error(ParseErrorKind::EndTagWithTrailingSolidus);
         }
-        nodes
+        Token::EndTag(tag)
     }
 
-    /// Parse a single HTML element, including its open tag, contents, and closing tag.
-    fn parse_element(&mut self) -> dom::Node {
-        // Opening tag.
-        assert!(self.consume_char() == '<');
-        let tag_name = self.parse_tag_name();
-        let attrs = self.parse_attributes();
-        assert!(self.consume_char() == '>');
+    /// Parse the contents of a raw text element such as `<style>`, where markup is not
+    /// recognised, up to its end tag. In escapable raw text elements such as `<title>`,
+    /// character references are still decoded.
+    /// https://html.spec.whatwg.org/multipage/parsing.html#rawtext-state
+    /// https://html.spec.whatwg.org/multipage/parsing.html#rcdata-state
+    fn parse_raw_text(&mut self, tag_name: &str, escapable: bool) -> String {
+        let start = self.pos;
+        let rest = &self.input[start - self.base..];
+        let end = rest
+            .match_indices("</")
+            .map(|(i, _)| i)
+            .find(|&i| {
+                let after = &rest[i + "</".len()..];
+                after
+                    .get(..tag_name.len())
+                    .is_some_and(|name| name.eq_ignore_ascii_case(tag_name))
+                    && after[tag_name.len()..]
+                        .starts_with(|c: char| c.is_ascii_whitespace() || c == '/' || c == '>')
+            })
+            .unwrap_or(rest.len());
 
-        // Contents.
-        let children = self.parse_nodes();
+        let raw = rest[..end].to_string();
+        self.pos += end;
+        if escapable {
+            self.decode_character_references(&raw, start, false)
+        } else {
+            raw
+        }
+    }
+
+    /// Parse a `<!DOCTYPE name PUBLIC "public id" "system id">`.
+    /// https://html.spec.whatwg.org/multipage/parsing.html#doctype-state
+    fn parse_doctype(&mut self) -> DoctypeToken {
+        self.pos += "<!doctype".len();
+        let mut token = DoctypeToken::default();
 
-        // Closing tag.
-        if self.starts_with("</") {
-            assert!(self.consume_char() == '<');
-            assert!(self.consume_char() == '/');
-            let closing_tag_name = self.parse_tag_name();
-            assert!(self.consume_char() == '>');
+        self.consume_whitespace();
+        match self.next_char() {
+            None => {
+                self.error(ParseErrorKind::EofInDoctype);
+                token.force_quirks = true;
+                return token;
+            }
+            Some('>') => {
+                self.consume_char();
+                self.error(ParseErrorKind::MissingDoctypeName);
+                token.force_quirks = true;
+                return token;
+            }
+            Some(_) => {}
+        }
+        token.name = Some(
+            self.consume_while(|c| !c.is_ascii_whitespace() && c != '>')
+                .to_ascii_lowercase(),
+        );
 
-            // Check if the closing tag matches the opening tag.
-            if tag_name != closing_tag_name {
-                eprintln!(
-                    "Warning: Closing tag '{}' does not match opening tag '{}', at position {}",
-                    closing_tag_name, tag_name, self.pos
+        self.consume_whitespace();
+        let mut bogus = false;
+        if self.starts_with_ignore_case("public") {
+            self.pos += "public".len();
+            token.public_id = self.parse_doctype_identifier(
+                &mut token.force_quirks,
+                ParseErrorKind::MissingQuoteBeforeDoctypePublicIdentifier,
+                ParseErrorKind::AbruptDoctypePublicIdentifier,
+            );
+            self.consume_whitespace();
+            if token.public_id.is_some() && matches!(self.next_char(), Some('"' | '\'')) {
+                token.system_id = self.parse_doctype_identifier(
+                    &mut token.force_quirks,
+                    ParseErrorKind::MissingQuoteBeforeDoctypeSystemIdentifier,
+                    ParseErrorKind::AbruptDoctypeSystemIdentifier,
                 );
             }
+        } else if self.starts_with_ignore_case("system") {
+            self.pos += "system".len();
+            token.system_id = self.parse_doctype_identifier(
+                &mut token.force_quirks,
+                ParseErrorKind::MissingQuoteBeforeDoctypeSystemIdentifier,
+                ParseErrorKind::AbruptDoctypeSystemIdentifier,
+            );
+        } else if !self.eof() && self.next_char() != Some('>') {
+            self.error(ParseErrorKind::InvalidCharacterSequenceAfterDoctypeName);
+            token.force_quirks = true;
+            bogus = true;
+        }
+
+        // Skip anything else up to the closing `>`.
+        self.consume_whitespace();
+        let start = self.pos;
+        let rest = self.consume_while(|c| c != '>');
+        if !rest.is_empty() && !bogus && !token.force_quirks {
+            self.error_at(
+                ParseErrorKind::UnexpectedCharacterAfterDoctypeSystemIdentifier,
+                start,
+            );
+        }
+        if self.consume_char().is_none() {
+            self.error(ParseErrorKind::EofInDoctype);
+            token.force_quirks = true;
+        }
+        token
+    }
+
+    /// Parse a quoted doctype public or system identifier.
+    fn parse_doctype_identifier(
+        &mut self,
+        force_quirks: &mut bool,
+        missing_quote: ParseErrorKind,
+        abrupt: ParseErrorKind,
+    ) -> Option<String> {
+        self.consume_whitespace();
+        let quote = match self.next_char() {
+            Some(quote @ ('"' | '\'')) => quote,
+            _ => {
+                self.error(missing_quote);
+                *force_quirks = true;
+                return None;
+            }
+        };
+        self.consume_char();
+
+        let id = self.consume_while(|c| c != quote && c != '>');
+        match self.next_char() {
+            Some(c) if c == quote => {
+                self.consume_char();
+            }
+            Some(_) => {
+                // A `>` ends the whole doctype, so leave it for the caller.
+                self.error(abrupt);
+                *force_quirks = true;
+            }
+            None => {}
+        }
+        Some(id)
+    }
+
+    /// Parse markup such as `<!foo>`, which is not a comment, doctype or CDATA section, as a
+    /// comment up to the next `>`.
+    /// https://html.spec.whatwg.org/multipage/parsing.html#bogus-comment-state
+    fn parse_bogus_comment(&mut self) -> Token {
+        self.error(ParseErrorKind::IncorrectlyOpenedComment);
+        self.pos += "<!".len();
+        let data = self.consume_while(|c| c != '>');
+        self.consume_char();
+        Token::Comment(data)
+    }
+
+    /// Parse a CDATA section such as `<![CDATA[x < y]]>`, whose contents are text.
+    /// https://html.spec.whatwg.org/multipage/parsing.html#cdata-section-state
+    fn parse_cdata(&mut self) -> Token {
+        self.pos += "<![CDATA[".len();
+        let rest = self.rest();
+        let end = rest.find("]]>").unwrap_or(rest.len());
+        let text = rest[..end].to_string();
+        self.pos += end;
+        if self.eof() {
+            self.error(ParseErrorKind::EofInCdata);
         } else {
-            eprintln!(
-                "Warning: Missing closing tag for '{}',  at position {}",
-                tag_name, self.pos
+            self.pos += "]]>".len();
+        }
+        Token::Text(text)
+    }
+
+    /// Parse a processing instruction such as `<?xml version="1.0"?>`.
+    /// https://www.w3.org/TR/xml/#sec-pi
+    fn parse_processing_instruction(&mut self) -> Token {
+        let start = self.pos;
+        self.pos += "<?".len();
+        let target = self.consume_while(|c| !c.is_ascii_whitespace() && c != '?' && c != '>');
+        if target.is_empty() {
+            // Without a target this is not a processing instruction, so treat it the way the
+            // HTML spec does, as a comment.
+            self.error_at(
+                ParseErrorKind::UnexpectedQuestionMarkInsteadOfTagName,
+                start,
             );
+            self.pos = start + "<".len();
+            let data = self.consume_while(|c| c != '>');
+            self.consume_char();
+            return Token::Comment(data);
         }
 
-        dom::elem(tag_name, attrs, children)
+        self.consume_whitespace();
+        let rest = self.rest();
+        let end = rest.find("?>");
+        let data = rest[..end.unwrap_or(rest.len())].to_string();
+        self.pos += data.len();
+        match end {
+            Some(_) => self.pos += "?>".len(),
+            None => self.error(ParseErrorKind::EofInProcessingInstruction),
+        }
+        Token::ProcessingInstruction { target, data }
     }
 
-    fn parse_comment(&mut self) -> dom::Node {
+    /// Parse a comment such as `<!-- text -->`.
+    /// https://html.spec.whatwg.org/multipage/parsing.html#comment-start-state
+    fn parse_comment(&mut self) -> Token {
         // Opening comment.
-        assert!(self.consume_char() == '<');
-        assert!(self.consume_char() == '!');
-        assert!(self.consume_char() == '-');
-        assert!(self.consume_char() == '-');
+        self.pos += "<!--".len();
 
-        let mut comment = String::new();
-        let mut consecutive_dashes = 0;
+        // Comment start and comment start dash states: `<!-->` and `<!--->` are empty.
+        for end in [">", "->"] {
+            if self.starts_with(end) {
+                self.error(ParseErrorKind::AbruptClosingOfEmptyComment);
+                self.pos += end.len();
+                return Token::Comment(String::new());
+            }
+        }
 
-        // Data.
+        let mut comment = String::new();
+        let mut consecutive_dashes: usize = 0;
 
+        // Data. The last two dashes before `>` close the comment; any others are part of it.
         loop {
-            let current_char = self.consume_char();
+            let current_char = match self.consume_char() {
+                Some(c) => c,
+                None => {
+                    self.error(ParseErrorKind::EofInComment);
+                    comment.push_str(&"-".repeat(consecutive_dashes.saturating_sub(2)));
+                    break;
+                }
+            };
 
             match current_char {
                 '-' => consecutive_dashes += 1,
-                '>' if consecutive_dashes >= 2 => break, // Closing tag.
+                // Comment end state.
+                '>' if consecutive_dashes >= 2 => {
+                    comment.push_str(&"-".repeat(consecutive_dashes - 2));
+                    break;
+                }
+                // Comment end bang state.
+                '!' if consecutive_dashes >= 2 && self.starts_with(">") => {
+                    self.error(ParseErrorKind::IncorrectlyClosedComment);
+                    self.pos += 1;
+                    comment.push_str(&"-".repeat(consecutive_dashes - 2));
+                    break;
+                }
                 _ => {
                     // Append consecutive dashes if any.
                     comment.push_str(&"-".repeat(consecutive_dashes));
@@ -212,6 +1271,81 @@ impl Parser {
             }
         }
 
-        dom::comment(comment)
+        Token::Comment(comment)
     }
 }
+
+/// Void elements can't have any contents, so they have no end tag.
+/// https://html.spec.whatwg.org/multipage/syntax.html#void-elements
+pub fn is_void_element(tag_name: &str) -> bool {
+    matches!(
+        tag_name,
+        "area"
+            | "base"
+            | "br"
+            | "col"
+            | "embed"
+            | "hr"
+            | "img"
+            | "input"
+            | "link"
+            | "meta"
+            | "source"
+            | "track"
+            | "wbr"
+    )
+}
+
+/// Raw text elements contain text that is not parsed as markup.
+/// https://html.spec.whatwg.org/multipage/syntax.html#raw-text-elements
+pub fn is_raw_text_element(tag_name: &str) -> bool {
+    matches!(
+        tag_name,
+        "script" | "style" | "xmp" | "iframe" | "noembed" | "noframes"
+    )
+}
+
+/// Escapable raw text elements contain text that is not parsed as markup, but may contain
+/// character references.
+/// https://html.spec.whatwg.org/multipage/syntax.html#escapable-raw-text-elements
+pub fn is_escapable_raw_text_element(tag_name: &str) -> bool {
+    matches!(tag_name, "textarea" | "title")
+}
+
+/// Numeric character references to C1 controls are taken to mean the windows-1252 character
+/// with that code, as old pages expect. `None` marks codes that are left as they are.
+/// https://html.spec.whatwg.org/multipage/parsing.html#numeric-character-reference-end-state
+const C1_REPLACEMENTS: [Option<u32>; 32] = [
+    Some(0x20AC),
+    None,
+    Some(0x201A),
+    Some(0x0192),
+    Some(0x201E),
+    Some(0x2026),
+    Some(0x2020),
+    Some(0x2021),
+    Some(0x02C6),
+    Some(0x2030),
+    Some(0x0160),
+    Some(0x2039),
+    Some(0x0152),
+    None,
+    Some(0x017D),
+    None,
+    None,
+    Some(0x2018),
+    Some(0x2019),
+    Some(0x201C),
+    Some(0x201D),
+    Some(0x2022),
+    Some(0x2013),
+    Some(0x2014),
+    Some(0x02DC),
+    Some(0x2122),
+    Some(0x0161),
+    Some(0x203A),
+    Some(0x0153),
+    None,
+    Some(0x017E),
+    Some(0x0178),
+];
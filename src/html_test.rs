@@ -4,6 +4,7 @@ mod tests {
 
     use crate::dom;
     use crate::html;
+    use crate::serializer;
 
     fn create_attrs() -> dom::AttrMap {
         [("class".to_string(), "container".to_string())]
@@ -12,19 +13,49 @@ mod tests {
             .collect()
     }
 
+    /// The document tree for a page whose body has the given children.
+    fn document(children: Vec<dom::Node>) -> dom::Node {
+        dom::elem(
+            "html".to_string(),
+            HashMap::new(),
+            vec![
+                dom::elem("head".to_string(), HashMap::new(), vec![]),
+                dom::elem("body".to_string(), HashMap::new(), children),
+            ],
+        )
+    }
+
+    fn attrs(pairs: &[(&str, &str)]) -> dom::AttrMap {
+        pairs
+            .iter()
+            .map(|(name, value)| (name.to_string(), value.to_string()))
+            .collect()
+    }
+
+    fn error_kinds(result: &html::ParseResult) -> Vec<html::ParseErrorKind> {
+        result
+            .errors
+            .iter()
+            .map(|error| error.kind.clone())
+            .collect()
+    }
+
     #[test]
     fn test_parse_text_node() {
         let html = "Hello, World!".to_string();
-        let parsed_node = html::parse(html);
-        assert_eq!(parsed_node, dom::text("Hello, World!".to_string()));
+        let parsed_node = html::parse(html).root;
+        assert_eq!(
+            parsed_node,
+            document(vec![dom::text("Hello, World!".to_string())])
+        );
     }
 
     #[test]
     fn test_parse_element_node() {
         let html = "<div class=\"container\"><p>Hello</p></div>".to_string();
-        let parsed_node = html::parse(html);
+        let parsed_node = html::parse(html).root;
 
-        let expected_node = dom::elem(
+        let expected_node = document(vec![dom::elem(
             "div".to_string(),
             create_attrs(),
             vec![dom::elem(
@@ -32,7 +63,7 @@ mod tests {
                 HashMap::new(),
                 vec![dom::text("Hello".to_string())],
             )],
-        );
+        )]);
 
         assert_eq!(parsed_node, expected_node);
     }
@@ -40,60 +71,1273 @@ mod tests {
     #[test]
     fn test_parse_comment() {
         let html = "<body><h1>Hello, World!</h1> <!--This is a comment.--> <p>This is a paragraph.</p></body>".to_string();
-        let parsed_node = html::parse(html);
+        let parsed_node = html::parse(html).root;
+
+        let expected_node = document(vec![
+            dom::elem(
+                "h1".to_string(),
+                HashMap::new(),
+                vec![dom::text("Hello, World!".to_string())],
+            ),
+            dom::text(" ".to_string()),
+            dom::comment("This is a comment.".to_string()),
+            dom::text(" ".to_string()),
+            dom::elem(
+                "p".to_string(),
+                HashMap::new(),
+                vec![dom::text("This is a paragraph.".to_string())],
+            ),
+        ]);
 
-        let expected_node = dom::elem(
-            "body".to_string(),
+        assert_eq!(parsed_node, expected_node);
+    }
+
+    #[test]
+    fn test_parse_invalid_node() {
+        let html = "<div><p>Some text</p><p>Unclosed paragraph<p>should work</p></div>".to_string();
+        let parsed_node = html::parse(html).root;
+
+        // A `<p>` start tag closes the open paragraph.
+        let expected_node = document(vec![dom::elem(
+            "div".to_string(),
             HashMap::new(),
             vec![
                 dom::elem(
-                    "h1".to_string(),
+                    "p".to_string(),
+                    HashMap::new(),
+                    vec![dom::text("Some text".to_string())],
+                ),
+                dom::elem(
+                    "p".to_string(),
                     HashMap::new(),
-                    vec![dom::text("Hello, World!".to_string())],
+                    vec![dom::text("Unclosed paragraph".to_string())],
                 ),
-                dom::comment("This is a comment.".to_string()),
                 dom::elem(
                     "p".to_string(),
                     HashMap::new(),
-                    vec![dom::text("This is a paragraph.".to_string())],
+                    vec![dom::text("should work".to_string())],
                 ),
             ],
-        );
+        )]);
 
         assert_eq!(parsed_node, expected_node);
     }
 
     #[test]
-    fn test_parse_invalid_node() {
-        let html = "<div><p>Some text</p><p>Unclosed paragraph<p>should work</p></div>".to_string();
-        let parsed_node = html::parse(html);
+    fn test_parse_unterminated_comment() {
+        let result = html::parse("<div><!-- never closed".to_string());
 
-        let expected_node = dom::elem(
+        let expected_node = document(vec![dom::elem(
             "div".to_string(),
             HashMap::new(),
+            vec![dom::comment(" never closed".to_string())],
+        )]);
+        assert_eq!(result.root, expected_node);
+
+        let kinds: Vec<_> = result.errors.iter().map(|e| e.kind.clone()).collect();
+        assert_eq!(
+            kinds,
+            vec![
+                html::ParseErrorKind::EofInComment,
+                html::ParseErrorKind::MissingEndTag("div".to_string()),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_comment_end_states() {
+        let tokens = |html: &str| {
+            let mut tokenizer = html::Tokenizer::new(html.to_string());
+            let tokens: Vec<html::Token> = tokenizer.by_ref().collect();
+            (tokens, tokenizer.errors().len())
+        };
+        let comment = |data: &str| html::Token::Comment(data.to_string());
+        let text = html::Token::Text("x".to_string());
+
+        assert_eq!(tokens("<!-->x"), (vec![comment(""), text.clone()], 1));
+        assert_eq!(tokens("<!--->x"), (vec![comment(""), text.clone()], 1));
+        assert_eq!(
+            tokens("<!--a---->x"),
+            (vec![comment("a--"), text.clone()], 0)
+        );
+        assert_eq!(
+            tokens("<!---a--->x"),
+            (vec![comment("-a-"), text.clone()], 0)
+        );
+        assert_eq!(tokens("<!--a--!>x"), (vec![comment("a"), text], 1));
+        assert_eq!(
+            html::parse("<!-->".to_string()).errors[0].kind,
+            html::ParseErrorKind::AbruptClosingOfEmptyComment
+        );
+    }
+
+    #[test]
+    fn test_parse_stray_equals_sign() {
+        let result = html::parse("<div =x class=\"container\"></div>".to_string());
+
+        let mut attrs = create_attrs();
+        attrs.insert("=x".to_string(), String::new());
+        assert_eq!(
+            result.root,
+            document(vec![dom::elem("div".to_string(), attrs, vec![])])
+        );
+        assert_eq!(
+            result.errors[0].kind,
+            html::ParseErrorKind::UnexpectedEqualsSignBeforeAttributeName
+        );
+    }
+
+    #[test]
+    fn test_parse_error_location() {
+        let result = html::parse("<div>\n  <p>Hi</b>\n</div>".to_string());
+
+        assert_eq!(result.errors.len(), 1);
+        let error = &result.errors[0];
+        assert_eq!(
+            error.kind,
+            html::ParseErrorKind::UnexpectedEndTag("b".to_string())
+        );
+        assert_eq!((error.offset, error.line, error.column), (13, 2, 8));
+        assert_eq!(error.to_string(), "2:8: unexpected-end-tag 'b'");
+        let mismatched = html::ParseErrorKind::MismatchedEndTag {
+            expected: "p".to_string(),
+            found: "div".to_string(),
+        };
+        assert_eq!(
+            mismatched.to_string(),
+            "mismatched-end-tag 'div', expected 'p'"
+        );
+    }
+
+    #[test]
+    fn test_parse_stray_end_tag() {
+        let result = html::parse("</span><p>Hello</p>".to_string());
+
+        assert_eq!(
+            result.root,
+            document(vec![dom::elem(
+                "p".to_string(),
+                HashMap::new(),
+                vec![dom::text("Hello".to_string())],
+            )])
+        );
+        assert_eq!(
+            result.errors[0].kind,
+            html::ParseErrorKind::UnexpectedEndTag("span".to_string())
+        );
+    }
+
+    #[test]
+    fn test_parse_boolean_and_unquoted_attributes() {
+        let result = html::parse(
+            "<div colspan=2 data-x nowrap xml:lang = 'pt' my_attr=\"a b\"></div>".to_string(),
+        );
+
+        let expected_attrs: dom::AttrMap = [
+            ("colspan", "2"),
+            ("data-x", ""),
+            ("nowrap", ""),
+            ("xml:lang", "pt"),
+            ("my_attr", "a b"),
+        ]
+        .iter()
+        .map(|(name, value)| (name.to_string(), value.to_string()))
+        .collect();
+
+        assert_eq!(
+            result.root,
+            document(vec![dom::elem("div".to_string(), expected_attrs, vec![])])
+        );
+        assert!(result.errors.is_empty());
+    }
+
+    #[test]
+    fn test_parse_duplicate_attribute() {
+        let result = html::parse("<div class=\"container\" class=\"other\"></div>".to_string());
+
+        assert_eq!(
+            result.root,
+            document(vec![dom::elem("div".to_string(), create_attrs(), vec![])])
+        );
+        assert_eq!(result.errors.len(), 1);
+        assert_eq!(
+            result.errors[0].kind,
+            html::ParseErrorKind::DuplicateAttribute("class".to_string())
+        );
+        assert_eq!(result.errors[0].offset, 23);
+    }
+
+    #[test]
+    fn test_parse_void_elements() {
+        let html = "<p>Line<br>Next<img src=\"a.png\"/><meta charset=\"utf-8\"></p>".to_string();
+        let result = html::parse(html);
+
+        let img_attrs = [("src".to_string(), "a.png".to_string())]
+            .iter()
+            .cloned()
+            .collect();
+        let meta_attrs = [("charset".to_string(), "utf-8".to_string())]
+            .iter()
+            .cloned()
+            .collect();
+        let expected_node = document(vec![dom::elem(
+            "p".to_string(),
+            HashMap::new(),
             vec![
+                dom::text("Line".to_string()),
+                dom::elem("br".to_string(), HashMap::new(), vec![]),
+                dom::text("Next".to_string()),
+                dom::elem("img".to_string(), img_attrs, vec![]),
+                dom::elem("meta".to_string(), meta_attrs, vec![]),
+            ],
+        )]);
+
+        assert_eq!(result.root, expected_node);
+        assert!(result.errors.is_empty());
+    }
+
+    #[test]
+    fn test_parse_self_closing_non_void_element() {
+        let result = html::parse("<div/><p>Hello</p></div>".to_string());
+
+        let expected_node = document(vec![dom::elem(
+            "div".to_string(),
+            HashMap::new(),
+            vec![dom::elem(
+                "p".to_string(),
+                HashMap::new(),
+                vec![dom::text("Hello".to_string())],
+            )],
+        )]);
+
+        assert_eq!(result.root, expected_node);
+        assert_eq!(
+            result.errors[0].kind,
+            html::ParseErrorKind::NonVoidHtmlElementStartTagWithTrailingSolidus
+        );
+    }
+
+    #[test]
+    fn test_parse_void_element_end_tag() {
+        let result = html::parse("<p><img></img></p>".to_string());
+
+        assert_eq!(
+            result.root,
+            document(vec![dom::elem(
+                "p".to_string(),
+                HashMap::new(),
+                vec![dom::elem("img".to_string(), HashMap::new(), vec![])],
+            )])
+        );
+        assert_eq!(
+            result.errors[0].kind,
+            html::ParseErrorKind::UnexpectedEndTag("img".to_string())
+        );
+    }
+
+    #[test]
+    fn test_parse_character_references() {
+        let html =
+            "<p>&lt;b&gt; &amp; &copy &#169; &#x1F600; &#128; &notit; &bogus;</p>".to_string();
+        let result = html::parse(html);
+
+        assert_eq!(
+            result.root,
+            document(vec![dom::elem(
+                "p".to_string(),
+                HashMap::new(),
+                vec![dom::text(
+                    "<b> & \u{A9} \u{A9} \u{1F600} \u{20AC} \u{AC}it; &bogus;".to_string()
+                )],
+            )])
+        );
+
+        let kinds: Vec<_> = result.errors.iter().map(|e| e.kind.clone()).collect();
+        assert_eq!(
+            kinds,
+            vec![
+                html::ParseErrorKind::MissingSemicolonAfterCharacterReference,
+                html::ParseErrorKind::ControlCharacterReference,
+                html::ParseErrorKind::MissingSemicolonAfterCharacterReference,
+                html::ParseErrorKind::UnknownNamedCharacterReference,
+            ]
+        );
+    }
+
+    #[test]
+    fn test_parse_character_references_in_attributes() {
+        let html =
+            "<a title=\"Tom &amp; Jerry\" href=\"?a=1&copy=2&lang=pt\" alt=&quot;x&quot;></a>"
+                .to_string();
+        let result = html::parse(html);
+
+        let expected_attrs: dom::AttrMap = [
+            ("title", "Tom & Jerry"),
+            ("href", "?a=1&copy=2&lang=pt"),
+            ("alt", "\"x\""),
+        ]
+        .iter()
+        .map(|(name, value)| (name.to_string(), value.to_string()))
+        .collect();
+
+        assert_eq!(
+            result.root,
+            document(vec![dom::elem("a".to_string(), expected_attrs, vec![])])
+        );
+        assert!(result.errors.is_empty());
+    }
+
+    #[test]
+    fn test_null_character_reference() {
+        let result = html::parse("<p title='a&#0;b'>a&#x0;b</p>".to_string());
+
+        assert_eq!(
+            result.root,
+            document(vec![dom::elem(
+                "p".to_string(),
+                attrs(&[("title", "a\u{FFFD}b")]),
+                vec![dom::text("a\u{FFFD}b".to_string())],
+            )])
+        );
+        assert_eq!(
+            error_kinds(&result),
+            vec![html::ParseErrorKind::NullCharacterReference; 2]
+        );
+    }
+
+    #[test]
+    fn test_parse_doctype() {
+        let result = html::parse("<!DOCTYPE html>\n<p>Hello</p>".to_string());
+
+        assert_eq!(
+            result.doctype,
+            Some(dom::doctype(
+                "html".to_string(),
+                String::new(),
+                String::new()
+            ))
+        );
+        assert_eq!(result.mode, dom::DocumentMode::NoQuirks);
+        assert_eq!(
+            result.root,
+            document(vec![dom::elem(
+                "p".to_string(),
+                HashMap::new(),
+                vec![dom::text("Hello".to_string())],
+            )])
+        );
+        assert!(result.errors.is_empty());
+    }
+
+    #[test]
+    fn test_document_mode() {
+        let mode = |html: &str| html::parse(html.to_string()).mode;
+
+        assert_eq!(mode("<p></p>"), dom::DocumentMode::Quirks);
+        assert_eq!(mode("<!doctype HTML><p></p>"), dom::DocumentMode::NoQuirks);
+        assert_eq!(
+            mode("<!DOCTYPE html PUBLIC \"-//W3C//DTD HTML 4.01//EN\" \"http://www.w3.org/TR/html4/strict.dtd\">"),
+            dom::DocumentMode::NoQuirks
+        );
+        assert_eq!(
+            mode("<!DOCTYPE HTML PUBLIC \"-//W3C//DTD HTML 4.01 Transitional//EN\">"),
+            dom::DocumentMode::Quirks
+        );
+        assert_eq!(
+            mode("<!DOCTYPE HTML PUBLIC \"-//W3C//DTD HTML 4.01 Transitional//EN\" \"http://www.w3.org/TR/html4/loose.dtd\">"),
+            dom::DocumentMode::LimitedQuirks
+        );
+        assert_eq!(
+            mode("<!DOCTYPE html PUBLIC \"-//W3C//DTD XHTML 1.0 Transitional//EN\" \"http://www.w3.org/TR/xhtml1/DTD/xhtml1-transitional.dtd\">"),
+            dom::DocumentMode::LimitedQuirks
+        );
+        assert_eq!(mode("<!DOCTYPE svg>"), dom::DocumentMode::Quirks);
+        assert_eq!(mode("<!DOCTYPE html SYSTEM>"), dom::DocumentMode::Quirks);
+    }
+
+    #[test]
+    fn test_parse_misplaced_doctype() {
+        let result = html::parse("<p>Hi<!DOCTYPE html></p>".to_string());
+
+        assert_eq!(result.doctype, None);
+        assert_eq!(result.mode, dom::DocumentMode::Quirks);
+        assert_eq!(
+            result.errors[0].kind,
+            html::ParseErrorKind::UnexpectedDoctype
+        );
+    }
+
+    #[test]
+    fn test_parse_processing_instruction() {
+        let html = "<?xml version=\"1.0\" encoding=\"UTF-8\"?><!DOCTYPE html><p>Hi</p>".to_string();
+        let result = html::parse(html);
+
+        assert_eq!(
+            result.document_nodes,
+            vec![dom::processing_instruction(
+                "xml".to_string(),
+                "version=\"1.0\" encoding=\"UTF-8\"".to_string()
+            )]
+        );
+        assert_eq!(
+            result.root,
+            document(vec![dom::elem(
+                "p".to_string(),
+                HashMap::new(),
+                vec![dom::text("Hi".to_string())],
+            )])
+        );
+        assert_eq!(result.mode, dom::DocumentMode::NoQuirks);
+        assert!(result.errors.is_empty());
+    }
+
+    #[test]
+    fn test_parse_processing_instruction_without_target() {
+        let result = html::parse("<div><? data ?><?php echo 1;</div>".to_string());
+
+        assert_eq!(
+            result.root,
+            document(vec![dom::elem(
+                "div".to_string(),
+                HashMap::new(),
+                vec![
+                    dom::comment("? data ?".to_string()),
+                    dom::processing_instruction("php".to_string(), "echo 1;</div>".to_string()),
+                ],
+            )])
+        );
+        let kinds: Vec<_> = result.errors.iter().map(|e| e.kind.clone()).collect();
+        assert_eq!(
+            kinds,
+            vec![
+                html::ParseErrorKind::UnexpectedQuestionMarkInsteadOfTagName,
+                html::ParseErrorKind::EofInProcessingInstruction,
+                html::ParseErrorKind::MissingEndTag("div".to_string()),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_parse_raw_text_elements() {
+        let html = "<div><style>ul > li { color: red; }</style><script>if (a < b && c > d) {}</script></div>"
+            .to_string();
+        let result = html::parse(html);
+
+        assert_eq!(
+            result.root,
+            document(vec![dom::elem(
+                "div".to_string(),
+                HashMap::new(),
+                vec![
+                    dom::elem(
+                        "style".to_string(),
+                        HashMap::new(),
+                        vec![dom::text("ul > li { color: red; }".to_string())],
+                    ),
+                    dom::elem(
+                        "script".to_string(),
+                        HashMap::new(),
+                        vec![dom::text("if (a < b && c > d) {}".to_string())],
+                    ),
+                ],
+            )])
+        );
+        assert!(result.errors.is_empty());
+    }
+
+    #[test]
+    fn test_parse_escapable_raw_text_elements() {
+        let html = "<div><title>Fish &amp; <b>Chips</b></title><textarea>\n</textareax> &lt;</TEXTAREA></div>"
+            .to_string();
+        let result = html::parse(html);
+
+        assert_eq!(
+            result.root,
+            document(vec![dom::elem(
+                "div".to_string(),
+                HashMap::new(),
+                vec![
+                    dom::elem(
+                        "title".to_string(),
+                        HashMap::new(),
+                        vec![dom::text("Fish & <b>Chips</b>".to_string())],
+                    ),
+                    dom::elem(
+                        "textarea".to_string(),
+                        HashMap::new(),
+                        vec![dom::text("</textareax> <".to_string())],
+                    ),
+                ],
+            )])
+        );
+    }
+
+    #[test]
+    fn test_implied_end_tags() {
+        let html = "<ul><li>One<li>Two</ul><select><option>A<option>B</select>".to_string();
+        let result = html::parse(html);
+
+        let item = |text: &str| {
+            dom::elem(
+                "li".to_string(),
+                HashMap::new(),
+                vec![dom::text(text.to_string())],
+            )
+        };
+        let option = |text: &str| {
+            dom::elem(
+                "option".to_string(),
+                HashMap::new(),
+                vec![dom::text(text.to_string())],
+            )
+        };
+        assert_eq!(
+            result.root,
+            document(vec![
                 dom::elem(
-                    "p".to_string(),
+                    "ul".to_string(),
                     HashMap::new(),
-                    vec![dom::text("Some text".to_string())],
+                    vec![item("One"), item("Two")]
+                ),
+                dom::elem(
+                    "select".to_string(),
+                    HashMap::new(),
+                    vec![option("A"), option("B")]
+                ),
+            ])
+        );
+        assert!(result.errors.is_empty());
+    }
+
+    #[test]
+    fn test_implied_table_elements() {
+        let result = html::parse("<table><td>1<td>2<tr><td>3</table>".to_string());
+
+        let cell = |text: &str| {
+            dom::elem(
+                "td".to_string(),
+                HashMap::new(),
+                vec![dom::text(text.to_string())],
+            )
+        };
+        assert_eq!(
+            result.root,
+            document(vec![dom::elem(
+                "table".to_string(),
+                HashMap::new(),
+                vec![dom::elem(
+                    "tbody".to_string(),
+                    HashMap::new(),
+                    vec![
+                        dom::elem("tr".to_string(), HashMap::new(), vec![cell("1"), cell("2")]),
+                        dom::elem("tr".to_string(), HashMap::new(), vec![cell("3")]),
+                    ],
+                )],
+            )])
+        );
+    }
+
+    #[test]
+    fn test_implicit_head_and_body() {
+        let result = html::parse("<title>Hi</title><p>Text".to_string());
+
+        assert_eq!(
+            result.root,
+            dom::elem(
+                "html".to_string(),
+                HashMap::new(),
+                vec![
+                    dom::elem(
+                        "head".to_string(),
+                        HashMap::new(),
+                        vec![dom::elem(
+                            "title".to_string(),
+                            HashMap::new(),
+                            vec![dom::text("Hi".to_string())],
+                        )],
+                    ),
+                    dom::elem(
+                        "body".to_string(),
+                        HashMap::new(),
+                        vec![dom::elem(
+                            "p".to_string(),
+                            HashMap::new(),
+                            vec![dom::text("Text".to_string())],
+                        )],
+                    ),
+                ],
+            )
+        );
+    }
+
+    #[test]
+    fn test_misnested_formatting_elements() {
+        let result = html::parse("<b>1<p>2</b>3</p>".to_string());
+
+        // The adoption agency algorithm splits the `b` so each part stays well nested.
+        assert_eq!(
+            result.root,
+            document(vec![
+                dom::elem(
+                    "b".to_string(),
+                    HashMap::new(),
+                    vec![dom::text("1".to_string())],
                 ),
                 dom::elem(
                     "p".to_string(),
                     HashMap::new(),
                     vec![
-                        dom::text("Unclosed paragraph".to_string()),
-                        // in a real implementation they should not be a siblings,
                         dom::elem(
-                            "p".to_string(),
+                            "b".to_string(),
                             HashMap::new(),
-                            vec![dom::text("should work".to_string())],
+                            vec![dom::text("2".to_string())],
                         ),
+                        dom::text("3".to_string()),
                     ],
                 ),
-                // should stay here the node <p>should work</p>
-            ],
+            ])
+        );
+        assert_eq!(
+            result.errors[0].kind,
+            html::ParseErrorKind::MismatchedEndTag {
+                expected: "p".to_string(),
+                found: "b".to_string(),
+            }
         );
+    }
 
-        assert_eq!(parsed_node, expected_node);
+    #[test]
+    fn test_reopened_formatting_elements() {
+        let result = html::parse("<p><i>a<p>b".to_string());
+
+        let italic = |text: &str| {
+            dom::elem(
+                "i".to_string(),
+                HashMap::new(),
+                vec![dom::text(text.to_string())],
+            )
+        };
+        assert_eq!(
+            result.root,
+            document(vec![
+                dom::elem("p".to_string(), HashMap::new(), vec![italic("a")]),
+                dom::elem("p".to_string(), HashMap::new(), vec![italic("b")]),
+            ])
+        );
+    }
+
+    #[test]
+    fn test_foster_parenting() {
+        let result = html::parse("<table>oops<tr><td>cell</td></tr></table>".to_string());
+
+        let table = dom::elem(
+            "table".to_string(),
+            HashMap::new(),
+            vec![dom::elem(
+                "tbody".to_string(),
+                HashMap::new(),
+                vec![dom::elem(
+                    "tr".to_string(),
+                    HashMap::new(),
+                    vec![dom::elem(
+                        "td".to_string(),
+                        HashMap::new(),
+                        vec![dom::text("cell".to_string())],
+                    )],
+                )],
+            )],
+        );
+        assert_eq!(
+            result.root,
+            document(vec![dom::text("oops".to_string()), table])
+        );
+        assert_eq!(result.errors[0].kind, html::ParseErrorKind::UnexpectedText);
+    }
+
+    #[test]
+    fn test_source_spans() {
+        let result = html::parse("<p class=\"x\">Hi</p>\n<ul><li>One</ul>".to_string());
+
+        let offsets = |node: &dom::Node| {
+            let span = node.span.unwrap();
+            (span.start.offset, span.end.offset)
+        };
+        let html = &result.root;
+        let head = &html.children[0];
+        let body = &html.children[1];
+        let p = &body.children[0];
+        let ul = &body.children[2];
+        let li = &ul.children[0];
+
+        // Implied elements start where the markup that caused them starts.
+        assert_eq!(offsets(head), (0, 0));
+        assert_eq!(offsets(body), (0, 36));
+        assert_eq!(offsets(html), (0, 36));
+        assert_eq!(offsets(p), (0, 19));
+        assert_eq!(offsets(&p.children[0]), (13, 15));
+        assert_eq!(offsets(&body.children[1]), (19, 20));
+        assert_eq!(offsets(ul), (20, 36));
+        // Without an end tag, an element ends with its last child.
+        assert_eq!(offsets(li), (24, 31));
+
+        let ul_span = ul.span.unwrap();
+        assert_eq!((ul_span.start.line, ul_span.start.column), (2, 1));
+        assert_eq!((ul_span.end.line, ul_span.end.column), (2, 17));
+
+        match &p.node_type {
+            dom::NodeType::Element(element) => {
+                let span = element.attribute_spans["class"];
+                assert_eq!((span.start.offset, span.end.offset), (3, 12));
+                assert_eq!((span.start.column, span.end.column), (4, 13));
+            }
+            _ => panic!("expected an element"),
+        }
+    }
+
+    /// A reader that hands out its input a few bytes at a time.
+    struct Trickle {
+        input: Vec<u8>,
+        pos: usize,
+    }
+
+    impl std::io::Read for Trickle {
+        fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+            let n = buf.len().min(3).min(self.input.len() - self.pos);
+            buf[..n].copy_from_slice(&self.input[self.pos..self.pos + n]);
+            self.pos += n;
+            Ok(n)
+        }
+    }
+
+    #[test]
+    fn test_tokenizer_iterator() {
+        let tokens: Vec<_> =
+            html::Tokenizer::new("<!DOCTYPE html><p id=a>Hi<!--c--></p><?pi x?>".to_string())
+                .collect();
+
+        let mut p = html::Tag::new("p");
+        p.attributes.insert("id".to_string(), "a".to_string());
+        assert_eq!(
+            tokens,
+            vec![
+                html::Token::Doctype(html::DoctypeToken {
+                    name: Some("html".to_string()),
+                    ..Default::default()
+                }),
+                html::Token::StartTag(p),
+                html::Token::Text("Hi".to_string()),
+                html::Token::Comment("c".to_string()),
+                html::Token::EndTag(html::Tag::new("p")),
+                html::Token::ProcessingInstruction {
+                    target: "pi".to_string(),
+                    data: "x".to_string(),
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn test_tokenizer_reads_chunks() {
+        let html = "<p title=\"a > b\">Caf\u{e9} &amp; cr\u{e8}me<style>p { x: '</sty' }</style><!-- a -- b -->&copy</p>";
+        let trickle = Trickle {
+            input: html.as_bytes().to_vec(),
+            pos: 0,
+        };
+
+        let whole: Vec<_> = html::Tokenizer::new(html.to_string()).collect();
+        let streamed: Vec<_> = html::Tokenizer::from_reader(trickle).collect();
+        assert_eq!(streamed, whole);
+
+        let trickle = Trickle {
+            input: html.as_bytes().to_vec(),
+            pos: 0,
+        };
+        let result = html::parse_reader(trickle);
+        let expected = html::parse(html.to_string());
+        assert_eq!(result.root, expected.root);
+        assert_eq!(result.errors, expected.errors);
+    }
+
+    #[test]
+    fn test_tokenizer_drops_tokenized_input() {
+        // Long enough that input is dropped many times, with a comment that spans chunks and
+        // errors and lines after it.
+        let mut html = String::new();
+        for i in 0..2000 {
+            html.push_str(&format!("<p class=c{}>Caf\u{e9} {}</b>\n", i, i));
+        }
+        html.push_str(&format!("<!--{}-->\n<p>é</x> <div>", "-é".repeat(20_000)));
+
+        let expected = html::parse(html.clone());
+        for reader in [
+            Box::new(std::io::Cursor::new(html.clone().into_bytes())) as Box<dyn std::io::Read>,
+            Box::new(Trickle {
+                input: html.clone().into_bytes(),
+                pos: 0,
+            }),
+        ] {
+            let result = html::parse_reader(reader);
+            // Debug output includes spans, which equality leaves out.
+            assert_eq!(format!("{:?}", result.root), format!("{:?}", expected.root));
+            assert_eq!(result.errors, expected.errors);
+        }
+        assert_eq!(expected.errors.last().unwrap().line, 2002);
+    }
+
+    #[test]
+    fn test_names_are_lowercased() {
+        let result =
+            html::parse("<DIV CLASS=\"x\" Class=\"y\" data-Id=1><P>Hi</p></Div>".to_string());
+
+        let attrs: dom::AttrMap = [("class", "x"), ("data-id", "1")]
+            .iter()
+            .map(|(name, value)| (name.to_string(), value.to_string()))
+            .collect();
+        assert_eq!(
+            result.root,
+            document(vec![dom::elem(
+                "div".to_string(),
+                attrs,
+                vec![dom::elem(
+                    "p".to_string(),
+                    HashMap::new(),
+                    vec![dom::text("Hi".to_string())],
+                )],
+            )])
+        );
+        // Names that differ only in case are the same attribute.
+        let kinds: Vec<_> = result.errors.iter().map(|e| e.kind.clone()).collect();
+        assert_eq!(
+            kinds,
+            vec![html::ParseErrorKind::DuplicateAttribute(
+                "class".to_string()
+            )]
+        );
+    }
+
+    #[test]
+    fn test_foreign_content_keeps_case() {
+        let result = html::parse(
+            "<SVG><linearGradient gradientUnits=x></linearGradient><foreignObject><B>Hi</B></foreignObject></SVG>"
+                .to_string(),
+        );
+
+        let svg = dom::Namespace::Svg;
+        assert_eq!(
+            result.root,
+            document(vec![dom::elem_ns(
+                svg,
+                "svg".to_string(),
+                HashMap::new(),
+                vec![
+                    dom::elem_ns(
+                        svg,
+                        "linearGradient".to_string(),
+                        attrs(&[("gradientUnits", "x")]),
+                        vec![],
+                    ),
+                    dom::elem_ns(
+                        svg,
+                        "foreignObject".to_string(),
+                        HashMap::new(),
+                        vec![dom::elem(
+                            "b".to_string(),
+                            HashMap::new(),
+                            vec![dom::text("Hi".to_string())],
+                        )],
+                    ),
+                ],
+            )])
+        );
+        assert!(result.errors.is_empty());
+    }
+
+    #[test]
+    fn test_foreign_attributes() {
+        let result = html::parse(
+            "<svg viewBox='0 0 1 1'><use xlink:href=#a /></svg><math definitionURL=x></math>"
+                .to_string(),
+        );
+
+        let body = &result.root.children[1];
+        fn element(node: &dom::Node) -> &dom::ElementData {
+            match &node.node_type {
+                dom::NodeType::Element(element) => element,
+                _ => panic!("not an element"),
+            }
+        }
+        let svg = element(&body.children[0]);
+        assert_eq!(svg.namespace, dom::Namespace::Svg);
+        assert_eq!(svg.attributes.get("viewBox"), Some(&"0 0 1 1".to_string()));
+        let used = element(&body.children[0].children[0]);
+        assert_eq!(used.attributes.get("xlink:href"), Some(&"#a".to_string()));
+        assert_eq!(
+            used.attribute_namespace("xlink:href"),
+            Some(dom::Namespace::XLink)
+        );
+        let math = element(&body.children[1]);
+        assert_eq!(math.namespace, dom::Namespace::MathMl);
+        assert!(math.attributes.contains_key("definitionURL"));
+        assert!(result.errors.is_empty());
+    }
+
+    #[test]
+    fn test_foreign_self_closing_and_markup() {
+        // Self-closing tags are fine in SVG, and `<style>` holds markup, not raw text.
+        let result = html::parse("<svg><circle/><style><g/></style></svg>".to_string());
+
+        let svg = dom::Namespace::Svg;
+        assert_eq!(
+            result.root,
+            document(vec![dom::elem_ns(
+                svg,
+                "svg".to_string(),
+                HashMap::new(),
+                vec![
+                    dom::elem_ns(svg, "circle".to_string(), HashMap::new(), vec![]),
+                    dom::elem_ns(
+                        svg,
+                        "style".to_string(),
+                        HashMap::new(),
+                        vec![dom::elem_ns(svg, "g".to_string(), HashMap::new(), vec![])],
+                    ),
+                ],
+            )])
+        );
+        assert!(result.errors.is_empty());
+    }
+
+    #[test]
+    fn test_break_out_to_integration_points() {
+        let body = |html: &str| {
+            let root = html::parse(html.to_string()).root;
+            serializer::inner_html(&root.children[1])
+        };
+
+        assert_eq!(body("<svg><desc></p>"), "<svg><desc><p></p></desc></svg>");
+        assert_eq!(body("<math><mi></br>"), "<math><mi><br></mi></math>");
+        assert_eq!(body("<svg><title></br>"), "<svg><title><br></title></svg>");
+        assert_eq!(
+            body("<svg><foreignObject></p>x"),
+            "<svg><foreignObject><p></p>x</foreignObject></svg>"
+        );
+        assert_eq!(
+            body("<svg><desc><svg><g><p>x"),
+            "<svg><desc><svg><g></g></svg><p>x</p></desc></svg>"
+        );
+    }
+
+    #[test]
+    fn test_cdata_sections() {
+        let result = html::parse("<svg><![CDATA[a < b]]></svg><![CDATA[c]]>".to_string());
+
+        assert_eq!(
+            result.root,
+            document(vec![
+                dom::elem_ns(
+                    dom::Namespace::Svg,
+                    "svg".to_string(),
+                    HashMap::new(),
+                    vec![dom::text("a < b".to_string())],
+                ),
+                // Outside foreign content, CDATA is a bogus comment.
+                dom::comment("[CDATA[c]]".to_string()),
+            ])
+        );
+        assert_eq!(
+            error_kinds(&result),
+            vec![
+                html::ParseErrorKind::CdataInHtmlContent,
+                html::ParseErrorKind::IncorrectlyOpenedComment,
+            ]
+        );
+    }
+
+    #[test]
+    fn test_foreign_elements_with_html_names() {
+        let body = |html: &str| {
+            let root = html::parse(html.to_string()).root;
+            serializer::inner_html(&root.children[1])
+        };
+
+        // A MathML `frameset` is not special, so it is not the adoption agency's furthest block.
+        assert_eq!(
+            body("<i>x<math><frameset>text</i>"),
+            "<i>x<math><frameset>text</frameset></math></i>"
+        );
+        // An SVG `td` does not end the scope of the `div`.
+        assert_eq!(
+            body("<div><svg><td></div>x"),
+            "<div><svg><td></td></svg></div>x"
+        );
+    }
+
+    #[test]
+    fn test_html_breaks_out_of_foreign_content() {
+        let result = html::parse("<svg><g><p>Hi</svg>".to_string());
+
+        let svg = dom::Namespace::Svg;
+        assert_eq!(
+            result.root,
+            document(vec![
+                dom::elem_ns(
+                    svg,
+                    "svg".to_string(),
+                    HashMap::new(),
+                    vec![dom::elem_ns(svg, "g".to_string(), HashMap::new(), vec![])],
+                ),
+                dom::elem(
+                    "p".to_string(),
+                    HashMap::new(),
+                    vec![dom::text("Hi".to_string())],
+                ),
+            ])
+        );
+        assert_eq!(
+            error_kinds(&result)[0],
+            html::ParseErrorKind::UnexpectedStartTag("p".to_string())
+        );
+    }
+
+    #[test]
+    fn test_mathml_text_integration_point() {
+        let result = html::parse("<math><mi><b>x</b></mi></math>".to_string());
+
+        let math = dom::Namespace::MathMl;
+        assert_eq!(
+            result.root,
+            document(vec![dom::elem_ns(
+                math,
+                "math".to_string(),
+                HashMap::new(),
+                vec![dom::elem_ns(
+                    math,
+                    "mi".to_string(),
+                    HashMap::new(),
+                    vec![dom::elem(
+                        "b".to_string(),
+                        HashMap::new(),
+                        vec![dom::text("x".to_string())],
+                    )],
+                )],
+            )])
+        );
+        assert!(result.errors.is_empty());
+    }
+
+    /// A `<template>` whose contents are `contents`.
+    fn template(contents: Vec<dom::Node>) -> dom::Node {
+        let mut template = dom::elem("template".to_string(), HashMap::new(), vec![]);
+        if let dom::NodeType::Element(element) = &mut template.node_type {
+            element.template_contents = Some(Box::new(dom::document_fragment(contents)));
+        }
+        template
+    }
+
+    #[test]
+    fn test_nesting_depth_limit() {
+        let html = format!("{}leaf", "<div>".repeat(10_000));
+        let root = html::parse(html).root;
+
+        // Past 512 open elements, each `div` is a sibling of the one before it.
+        let mut node = &root;
+        let mut depth = 0;
+        while let Some(child) = node.children.last() {
+            node = child;
+            depth += 1;
+        }
+        assert_eq!(depth, 512);
+        assert_eq!(node.text_content(), "leaf");
+        assert_eq!(root.query_selector_all("div").unwrap().len(), 10_000);
+    }
+
+    #[test]
+    fn test_template_contents() {
+        let result = html::parse(
+            "<template><p>Hi <b>there</b></template><template><tr><td>1</template>".to_string(),
+        );
+
+        // The contents are not children of the template.
+        assert_eq!(
+            result.root,
+            dom::elem(
+                "html".to_string(),
+                HashMap::new(),
+                vec![
+                    dom::elem(
+                        "head".to_string(),
+                        HashMap::new(),
+                        vec![
+                            template(vec![dom::elem(
+                                "p".to_string(),
+                                HashMap::new(),
+                                vec![
+                                    dom::text("Hi ".to_string()),
+                                    dom::elem(
+                                        "b".to_string(),
+                                        HashMap::new(),
+                                        vec![dom::text("there".to_string())],
+                                    ),
+                                ],
+                            )]),
+                            // Table parts need no table inside a template.
+                            template(vec![dom::elem(
+                                "tr".to_string(),
+                                HashMap::new(),
+                                vec![dom::elem(
+                                    "td".to_string(),
+                                    HashMap::new(),
+                                    vec![dom::text("1".to_string())],
+                                )],
+                            )]),
+                        ],
+                    ),
+                    dom::elem("body".to_string(), HashMap::new(), vec![]),
+                ],
+            )
+        );
+        assert!(result.errors.is_empty(), "{:?}", result.errors);
+    }
+
+    #[test]
+    fn test_nested_and_unclosed_templates() {
+        let result = html::parse(
+            "<body><table><template><template>x</template></template></table>\
+             <div><template><b>y"
+                .to_string(),
+        );
+
+        assert_eq!(
+            result.root,
+            document(vec![
+                dom::elem(
+                    "table".to_string(),
+                    HashMap::new(),
+                    vec![template(vec![template(vec![dom::text("x".to_string())])])],
+                ),
+                dom::elem(
+                    "div".to_string(),
+                    HashMap::new(),
+                    vec![template(vec![dom::elem(
+                        "b".to_string(),
+                        HashMap::new(),
+                        vec![dom::text("y".to_string())],
+                    )])],
+                ),
+            ])
+        );
+        assert!(error_kinds(&result)
+            .contains(&html::ParseErrorKind::MissingEndTag("template".to_string())));
+    }
+
+    #[test]
+    fn test_frameset() {
+        let elem = |name: &str, attributes: &[(&str, &str)], children| {
+            dom::elem(name.to_string(), attrs(attributes), children)
+        };
+        let result = html::parse(
+            "<frameset cols=50%,50%> <frame src=a><frameset><frame src=b></frameset>x\
+             </frameset><noframes>No frames</noframes></html><!-- end -->"
+                .to_string(),
+        );
+
+        assert_eq!(
+            result.root,
+            elem(
+                "html",
+                &[],
+                vec![
+                    elem("head", &[], vec![]),
+                    elem(
+                        "frameset",
+                        &[("cols", "50%,50%")],
+                        vec![
+                            dom::text(" ".to_string()),
+                            elem("frame", &[("src", "a")], vec![]),
+                            elem(
+                                "frameset",
+                                &[],
+                                vec![elem("frame", &[("src", "b")], vec![])]
+                            ),
+                        ],
+                    ),
+                    elem("noframes", &[], vec![dom::text("No frames".to_string())]),
+                ],
+            )
+        );
+        assert_eq!(
+            result.document_nodes,
+            vec![dom::comment(" end ".to_string())]
+        );
+        assert_eq!(
+            error_kinds(&result),
+            vec![html::ParseErrorKind::UnexpectedText]
+        );
+
+        // A frameset replaces a body with nothing in it that would show, and only that.
+        let root = html::parse("<div><frameset><frame>".to_string()).root;
+        assert_eq!(
+            root,
+            elem(
+                "html",
+                &[],
+                vec![
+                    elem("head", &[], vec![]),
+                    elem("frameset", &[], vec![elem("frame", &[], vec![])]),
+                ],
+            )
+        );
+        let root = html::parse("<div>Hi<frameset><frame>".to_string()).root;
+        assert_eq!(
+            root,
+            document(vec![elem("div", &[], vec![dom::text("Hi".to_string())])])
+        );
+    }
+
+    #[test]
+    fn test_plaintext() {
+        let html = "<p>a<plaintext>b</plaintext><i>&amp;</i>";
+        let result = html::parse(html.to_string());
+
+        assert_eq!(
+            result.root,
+            document(vec![
+                dom::elem(
+                    "p".to_string(),
+                    HashMap::new(),
+                    vec![dom::text("a".to_string())],
+                ),
+                dom::elem(
+                    "plaintext".to_string(),
+                    HashMap::new(),
+                    vec![dom::text("b</plaintext><i>&amp;</i>".to_string())],
+                ),
+            ])
+        );
+        let trickle = Trickle {
+            input: html.as_bytes().to_vec(),
+            pos: 0,
+        };
+        assert_eq!(html::parse_reader(trickle).root, result.root);
+
+        // Text read in chunks is still one run of text, with one error for being in a table.
+        let html = format!("<table><plaintext>{}", "<td>x".repeat(100));
+        let expected = html::parse(html.clone());
+        let trickle = Trickle {
+            input: html.into_bytes(),
+            pos: 0,
+        };
+        let result = html::parse_reader(trickle);
+        assert_eq!(format!("{:?}", result.root), format!("{:?}", expected.root));
+        assert_eq!(result.errors, expected.errors);
+        assert_eq!(
+            error_kinds(&expected),
+            vec![
+                html::ParseErrorKind::UnexpectedStartTag("plaintext".to_string()),
+                html::ParseErrorKind::UnexpectedText,
+                html::ParseErrorKind::MissingEndTag("plaintext".to_string()),
+                html::ParseErrorKind::MissingEndTag("table".to_string()),
+            ]
+        );
     }
 }
@@ -1,6 +1,7 @@
 use crate::cssom::Unit::Px;
 use crate::cssom::Value::{Keyword, Length};
 use crate::style::{Display, StyledNode};
+use serde::{Deserialize, Serialize, Serializer};
 use std::default::Default;
 
 pub use self::BoxType::{AnonymousBlock, BlockNode, InlineNode};
@@ -8,7 +9,7 @@ pub use self::BoxType::{AnonymousBlock, BlockNode, InlineNode};
 // CSS box model. All sizes are in px.
 
 /// Represents a rectangle with x, y coordinates and width, height dimensions.
-#[derive(Clone, Copy, Default, Debug)]
+#[derive(Clone, Copy, Default, Debug, Serialize, Deserialize)]
 pub struct Rect {
     pub x: f32,
     pub y: f32,
@@ -17,7 +18,7 @@ pub struct Rect {
 }
 
 /// Represents the dimensions of a layout box including content, padding, border, and margin.
-#[derive(Clone, Copy, Default, Debug)]
+#[derive(Clone, Copy, Default, Debug, Serialize, Deserialize)]
 pub struct Dimensions {
     /// Position and dimensions of the content area relative to the document origin.
     pub content: Rect,
@@ -28,7 +29,7 @@ pub struct Dimensions {
 }
 
 /// Represents the sizes of the edges (left, right, top, bottom) of a layout box.
-#[derive(Clone, Copy, Default, Debug)]
+#[derive(Clone, Copy, Default, Debug, Serialize, Deserialize)]
 pub struct EdgeSizes {
     pub left: f32,
     pub right: f32,
@@ -44,8 +45,29 @@ pub enum BoxType<'a> {
     AnonymousBlock,
 }
 
+/// Box types are serialized with the DOM node they were made for, but not its descendants.
+impl Serialize for BoxType<'_> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        match self {
+            BlockNode(node) => serializer.serialize_newtype_variant(
+                "BoxType",
+                0,
+                "BlockNode",
+                &node.node.node_type,
+            ),
+            InlineNode(node) => serializer.serialize_newtype_variant(
+                "BoxType",
+                1,
+                "InlineNode",
+                &node.node.node_type,
+            ),
+            AnonymousBlock => serializer.serialize_unit_variant("BoxType", 2, "AnonymousBlock"),
+        }
+    }
+}
+
 /// Represents a node in the layout tree.
-#[derive(Debug)]
+#[derive(Debug, Serialize)]
 pub struct LayoutBox<'a> {
     pub dimensions: Dimensions,
     pub box_type: BoxType<'a>,
@@ -282,7 +304,7 @@ impl<'a> LayoutBox<'a> {
         for child in &mut self.children {
             child.layout(*d);
             // Increment the height so each child is laid out below the previous one.
-            d.content.height = d.content.height + child.dimensions.margin_box().height;
+            d.content.height += child.dimensions.margin_box().height;
         }
     }
 
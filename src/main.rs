@@ -6,22 +6,34 @@ use image::DynamicImage::ImageRgba8;
 
 use std::default::Default;
 use std::env;
-use std::fs::File;
+use std::fs::{self, File};
 use std::io::{self, BufWriter, Read};
 
 pub mod css;
 mod css_test;
 pub mod cssom;
 mod cssom_test;
+pub mod document;
+mod document_test;
 pub mod dom;
 mod dom_test;
+pub mod encoding;
+mod encoding_test;
+pub mod entities;
+pub mod event;
+mod event_test;
 pub mod html;
 mod html_test;
 pub mod layout;
 pub mod painting;
 pub mod pdf;
+pub mod serializer;
+mod serializer_test;
 pub mod style;
 pub mod style_test;
+pub mod traversal;
+mod traversal_test;
+pub mod tree_builder;
 
 fn main() {
     // Parse command-line options:
@@ -49,10 +61,19 @@ fn main() {
         }
     };
 
+    // Choose a stage to dump as JSON, if any:
+    let dump_json = matches.opt_str("j");
+    if let Some(stage) = &dump_json {
+        if !matches!(&stage[..], "dom" | "stylesheet" | "style" | "layout") {
+            eprintln!("Unknown pipeline stage: {}", stage);
+            std::process::exit(1);
+        }
+    }
+
     // Read input files:
-    let html = read_source(&str_arg("h", "examples/test.html")).unwrap_or_else(|err| {
+    let html = fs::read(str_arg("h", "examples/test.html")).unwrap_or_else(|err| {
         eprintln!("Error reading HTML file: {}", err);
-        String::new()
+        Vec::new()
     });
     let css = read_source(&str_arg("c", "examples/test.css")).unwrap_or_else(|err| {
         eprintln!("Error reading CSS file: {}", err);
@@ -66,14 +87,43 @@ fn main() {
 
     // Parsing and rendering:
     /* html parsing  */
-    let root_node = html::parse(html);
-    dom::pretty_print(&root_node, 2);
+    let document = html::parse_bytes(&html);
+    for error in &document.errors {
+        eprintln!("HTML parse error at {}", error);
+    }
+    if !document.errors.is_empty() {
+        eprintln!("{} HTML parse error(s)", document.errors.len());
+    }
+    let root_node = document.root;
+    match dump_json.as_deref() {
+        Some("dom") => return print_json(&root_node),
+        // Keep stdout for the JSON.
+        Some(_) => {}
+        None if matches.opt_present("s") => dom::pretty_print_with_spans(&root_node, 2),
+        None => dom::pretty_print(&root_node, 2),
+    }
     /* css parsing  */
-    let stylesheet = css::parse(css);
+    let css = css::parse_with_errors(css);
+    for error in &css.errors {
+        eprintln!("CSS parse error at {}", error);
+    }
+    if !css.errors.is_empty() {
+        eprintln!("{} CSS parse error(s)", css.errors.len());
+    }
+    let stylesheet = css.stylesheet;
+    if dump_json.as_deref() == Some("stylesheet") {
+        return print_json(&stylesheet);
+    }
     /* styled tree */
     let style_root = style::style_tree(&root_node, &stylesheet);
+    if dump_json.as_deref() == Some("style") {
+        return print_json(&style_root);
+    }
     /* layout tree */
     let layout_root = layout::layout_tree(&style_root, viewport);
+    if dump_json.as_deref() == Some("layout") {
+        return print_json(&layout_root);
+    }
 
     // Create the output file:
     let filename = str_arg("o", if png { "output.png" } else { "output.pdf" });
@@ -105,9 +155,26 @@ fn parse_args() -> Options {
     opts.optopt("c", "css", "CSS stylesheet", "FILENAME");
     opts.optopt("o", "output", "Output file", "FILENAME");
     opts.optopt("f", "format", "Output file format", "png | pdf");
+    opts.optflag("s", "spans", "Show source locations in the DOM dump");
+    opts.optopt(
+        "j",
+        "dump-json",
+        "Print a pipeline stage as JSON instead of rendering",
+        "dom | stylesheet | style | layout",
+    );
     opts
 }
 
+fn print_json<T: serde::Serialize>(value: &T) {
+    match serde_json::to_string_pretty(value) {
+        Ok(json) => println!("{}", json),
+        Err(err) => {
+            eprintln!("Error writing JSON: {}", err);
+            std::process::exit(1);
+        }
+    }
+}
+
 fn read_source(filename: &str) -> Result<String, io::Error> {
     let mut file = File::open(filename)?;
     let mut content = String::new();
@@ -173,14 +173,3 @@ impl Canvas {
         }
     }
 }
-
-/// Trait for clamping a value between a lower and upper bound.
-trait Clamp {
-    fn clamp(self, lower: Self, upper: Self) -> Self;
-}
-impl Clamp for f32 {
-    /// Clamp a value between a lower and upper bound.
-    fn clamp(self, lower: f32, upper: f32) -> f32 {
-        self.max(lower).min(upper)
-    }
-}
@@ -0,0 +1,222 @@
+//! Turn a DOM tree back into HTML.
+// https://html.spec.whatwg.org/multipage/parsing.html#serialising-html-fragments
+
+use crate::dom::{ElementData, Namespace, Node, NodeType};
+use crate::html::{is_raw_text_element, is_void_element};
+
+/// Serializes nodes as HTML, either exactly or pretty-printed.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Serializer {
+    pretty: bool,
+}
+
+impl Serializer {
+    pub fn new() -> Self {
+        Serializer::default()
+    }
+
+    /// Put elements whose content is only other elements on lines of their own, indented by
+    /// two spaces per level. Whitespace between those elements is replaced, so the output
+    /// no longer parses back to the same tree.
+    pub fn pretty(mut self) -> Self {
+        self.pretty = true;
+        self
+    }
+
+    /// The HTML for `node` itself and its descendants.
+    /// https://w3c.github.io/DOM-Parsing/#dom-element-outerhtml
+    pub fn outer_html(&self, node: &Node) -> String {
+        let mut out = String::new();
+        self.write(&mut out, vec![Step::Node(node, None, 0)]);
+        out
+    }
+
+    /// The HTML for the children of `node`.
+    /// https://html.spec.whatwg.org/multipage/dynamic-markup-insertion.html#dom-element-innerhtml
+    pub fn inner_html(&self, node: &Node) -> String {
+        let mut out = String::new();
+        let mut steps = Vec::new();
+        self.push_children(&mut steps, node, 0);
+        self.write(&mut out, steps);
+        out
+    }
+
+    /// Write the steps, last first. Nodes wait on the stack rather than in recursive calls,
+    /// so trees of any depth can be written.
+    fn write(&self, out: &mut String, mut steps: Vec<Step>) {
+        while let Some(step) = steps.pop() {
+            let (node, parent, depth) = match step {
+                Step::Node(node, parent, depth) => (node, parent, depth),
+                Step::Markup(markup) => {
+                    out.push_str(&markup);
+                    continue;
+                }
+            };
+            match &node.node_type {
+                NodeType::Element(element) => {
+                    out.push('<');
+                    out.push_str(&element.tag_name);
+                    for name in attribute_order(element) {
+                        out.push(' ');
+                        out.push_str(name);
+                        out.push_str("=\"");
+                        out.push_str(&escape(&element.attributes[name], true));
+                        out.push('"');
+                    }
+                    out.push('>');
+                    if element.namespace == Namespace::Html && is_void_element(&element.tag_name) {
+                        continue;
+                    }
+                    // The parser drops a newline straight after these start tags, so keep one
+                    // that is part of the text.
+                    if element.namespace == Namespace::Html
+                        && matches!(&*element.tag_name, "pre" | "textarea" | "listing")
+                    {
+                        if let Some(NodeType::Text(text)) =
+                            node.children.first().map(|c| &c.node_type)
+                        {
+                            if text.starts_with('\n') {
+                                out.push('\n');
+                            }
+                        }
+                    }
+                    steps.push(Step::Markup(format!("</{}>", element.tag_name)));
+                    self.push_children(&mut steps, node, depth);
+                }
+                NodeType::Text(text) => match parent {
+                    Some(parent)
+                        if parent.namespace == Namespace::Html
+                            && (is_raw_text_element(&parent.tag_name)
+                                || parent.tag_name == "plaintext") =>
+                    {
+                        out.push_str(text)
+                    }
+                    _ => out.push_str(&escape(text, false)),
+                },
+                NodeType::Comment(data) => {
+                    out.push_str("<!--");
+                    out.push_str(data);
+                    out.push_str("-->");
+                }
+                NodeType::ProcessingInstruction(pi) => {
+                    out.push_str("<?");
+                    out.push_str(&pi.target);
+                    if !pi.data.is_empty() {
+                        out.push(' ');
+                        out.push_str(&pi.data);
+                    }
+                    out.push_str("?>");
+                }
+                NodeType::Doctype(doctype) => {
+                    out.push_str("<!DOCTYPE ");
+                    out.push_str(&doctype.name);
+                    out.push('>');
+                }
+                NodeType::DocumentFragment => self.push_children(&mut steps, node, depth),
+            }
+        }
+    }
+
+    /// Add the steps that write the children of `node`, so they are written next.
+    fn push_children<'a>(&self, steps: &mut Vec<Step<'a>>, node: &'a Node, depth: usize) {
+        let parent = match &node.node_type {
+            NodeType::Element(element) => Some(element),
+            _ => None,
+        };
+        // A template's markup is its contents.
+        let node = match parent.and_then(|element| element.template_contents.as_deref()) {
+            Some(contents) => contents,
+            None => node,
+        };
+        if self.pretty && has_block_content(node) {
+            steps.push(Step::Markup(format!("\n{}", "  ".repeat(depth))));
+            for child in node
+                .children
+                .iter()
+                .rev()
+                .filter(|child| !is_whitespace(child))
+            {
+                steps.push(Step::Node(child, parent, depth + 1));
+                steps.push(Step::Markup(format!("\n{}", "  ".repeat(depth + 1))));
+            }
+        } else {
+            steps.extend(
+                node.children
+                    .iter()
+                    .rev()
+                    .map(|child| Step::Node(child, parent, depth + 1)),
+            );
+        }
+    }
+}
+
+/// Something left for the serializer to write: a node with its parent and depth, or markup
+/// such as an end tag that follows nodes still to be written.
+enum Step<'a> {
+    Node(&'a Node, Option<&'a ElementData>, usize),
+    Markup(String),
+}
+
+/// Serialize `node` and its descendants as HTML.
+pub fn outer_html(node: &Node) -> String {
+    Serializer::new().outer_html(node)
+}
+
+/// Serialize the children of `node` as HTML.
+pub fn inner_html(node: &Node) -> String {
+    Serializer::new().inner_html(node)
+}
+
+/// Attribute names in source order, or by name for attributes that were not parsed.
+fn attribute_order(element: &ElementData) -> Vec<&str> {
+    let mut names: Vec<&str> = element.attributes.keys().map(String::as_str).collect();
+    names.sort_by_key(|&name| {
+        let offset = element
+            .attribute_spans
+            .get(name)
+            .map(|span| span.start.offset);
+        (offset.unwrap_or(usize::MAX), name)
+    });
+    names
+}
+
+/// Whether the pretty printer may lay out the children of `node` on lines of their own: they
+/// are all elements, comments or whitespace, and whitespace is not significant in `node`.
+fn has_block_content(node: &Node) -> bool {
+    let preformatted = match &node.node_type {
+        NodeType::Element(element) => {
+            matches!(
+                &*element.tag_name,
+                "pre" | "textarea" | "listing" | "plaintext"
+            ) || is_raw_text_element(&element.tag_name)
+        }
+        _ => false,
+    };
+    !preformatted
+        && node.children.iter().any(|child| !is_whitespace(child))
+        && node
+            .children
+            .iter()
+            .all(|child| !matches!(child.node_type, NodeType::Text(_)) || is_whitespace(child))
+}
+
+fn is_whitespace(node: &Node) -> bool {
+    matches!(&node.node_type, NodeType::Text(text) if text.chars().all(|c| c.is_ascii_whitespace()))
+}
+
+/// Escape text, or an attribute value, so it parses back to the same string.
+/// https://html.spec.whatwg.org/multipage/parsing.html#escapingString
+fn escape(text: &str, attribute_mode: bool) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '\u{a0}' => escaped.push_str("&nbsp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' if attribute_mode => escaped.push_str("&quot;"),
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
@@ -0,0 +1,142 @@
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use crate::dom;
+    use crate::html;
+    use crate::serializer::{self, Serializer};
+
+    #[test]
+    fn test_outer_and_inner_html() {
+        let node = dom::elem(
+            "p".to_string(),
+            [("class".to_string(), "note".to_string())]
+                .into_iter()
+                .collect(),
+            vec![
+                dom::text("Hello, ".to_string()),
+                dom::elem(
+                    "b".to_string(),
+                    HashMap::new(),
+                    vec![dom::text("world".to_string())],
+                ),
+            ],
+        );
+
+        assert_eq!(
+            serializer::outer_html(&node),
+            "<p class=\"note\">Hello, <b>world</b></p>"
+        );
+        assert_eq!(serializer::inner_html(&node), "Hello, <b>world</b>");
+    }
+
+    #[test]
+    fn test_escaping() {
+        let node = dom::elem(
+            "a".to_string(),
+            [("title".to_string(), "\"1 < 2\" & 3".to_string())]
+                .into_iter()
+                .collect(),
+            vec![dom::text("<b> & \u{a0}\"".to_string())],
+        );
+
+        assert_eq!(
+            serializer::outer_html(&node),
+            "<a title=\"&quot;1 &lt; 2&quot; &amp; 3\">&lt;b&gt; &amp; &nbsp;\"</a>"
+        );
+    }
+
+    #[test]
+    fn test_void_and_raw_text_elements() {
+        let node = dom::elem(
+            "div".to_string(),
+            HashMap::new(),
+            vec![
+                dom::elem("br".to_string(), HashMap::new(), vec![]),
+                dom::elem(
+                    "script".to_string(),
+                    HashMap::new(),
+                    vec![dom::text("if (a < b && c) {}".to_string())],
+                ),
+                dom::elem(
+                    "textarea".to_string(),
+                    HashMap::new(),
+                    vec![dom::text("\n<x>".to_string())],
+                ),
+            ],
+        );
+
+        assert_eq!(
+            serializer::outer_html(&node),
+            "<div><br><script>if (a < b && c) {}</script><textarea>\n\n&lt;x&gt;</textarea></div>"
+        );
+    }
+
+    #[test]
+    fn test_comments_and_processing_instructions() {
+        let node = dom::elem(
+            "div".to_string(),
+            HashMap::new(),
+            vec![
+                dom::comment(" note ".to_string()),
+                dom::processing_instruction("xml".to_string(), "version=\"1.0\"".to_string()),
+            ],
+        );
+
+        assert_eq!(
+            serializer::inner_html(&node),
+            "<!-- note --><?xml version=\"1.0\"?>"
+        );
+    }
+
+    #[test]
+    fn test_pretty() {
+        let root = html::parse("<ul><li>One</li><li><b>Two</b></li></ul>".to_string()).root;
+
+        assert_eq!(
+            Serializer::new().pretty().outer_html(&root),
+            "<html>\n  <head></head>\n  <body>\n    <ul>\n      <li>One</li>\n      \
+             <li>\n        <b>Two</b>\n      </li>\n    </ul>\n  </body>\n</html>"
+        );
+    }
+
+    #[test]
+    fn test_round_trip() {
+        let source = "<!DOCTYPE html><html lang=en><head><title>A &amp; B</title>\
+            <style>p > a { color: red }</style></head>\
+            <body><p id=x class='a b'>Text &lt;tag&gt; &quot;q&quot;<br>\
+            <img src=\"a.png\" alt='\"'></p><!-- c --><pre>\n\nindented</pre>\
+            <table><tr><td>1</td></tr></table>\
+            <svg viewBox=\"0 0 1 1\"><circle r=1 /><![CDATA[x<y]]></svg>\
+            <template><tr><td>cell</td></tr></template>\
+            <textarea>\nfirst line</textarea></body></html>";
+        let parsed = html::parse(source.to_string());
+        let serialized = serializer::outer_html(&parsed.root);
+        let reparsed = html::parse(serialized.clone());
+
+        assert_eq!(reparsed.root, parsed.root, "{}", serialized);
+        assert_eq!(serializer::outer_html(&reparsed.root), serialized);
+    }
+
+    #[test]
+    fn test_deep_tree() {
+        let depth = 20_000;
+        let mut node = dom::text("leaf".to_string());
+        for _ in 0..depth {
+            node = dom::elem("span".to_string(), HashMap::new(), vec![node]);
+        }
+        let copy = node.clone();
+
+        let html = serializer::outer_html(&copy);
+        assert_eq!(
+            html,
+            format!("{}leaf{}", "<span>".repeat(depth), "</span>".repeat(depth))
+        );
+        assert_eq!(
+            serializer::inner_html(&copy).len(),
+            html.len() - "<span></span>".len()
+        );
+        // Not `assert_eq!`, whose message would print the whole tree.
+        assert!(copy == node);
+    }
+}
@@ -1,21 +1,29 @@
 use crate::cssom::{Rule, Selector, SimpleSelector, Specificity, Stylesheet, Value};
-use crate::dom::{ElementData, Node, NodeType};
+use crate::dom::{self, ElementData, Namespace, Node, NodeType};
+use serde::{Serialize, Serializer};
 use std::collections::HashMap;
 
 /// Represents a map of CSS properties.
 pub type PropertyMap = HashMap<String, Value>;
 
 /// Represents a styled node in the DOM tree.
-#[derive(PartialEq, Debug)]
+#[derive(PartialEq, Debug, Serialize)]
 pub struct StyledNode<'a> {
-    /// The original node in the DOM tree.
+    /// The original node in the DOM tree. Only the node itself is serialized, since its
+    /// children are serialized as styled nodes.
+    #[serde(serialize_with = "serialize_node_type")]
     pub node: &'a Node,
     /// The specified CSS values for the node.
+    #[serde(serialize_with = "dom::serialize_sorted")]
     pub specified_values: PropertyMap,
     /// Styled children nodes.
     pub children: Vec<StyledNode<'a>>,
 }
 
+fn serialize_node_type<S: Serializer>(node: &&Node, serializer: S) -> Result<S::Ok, S::Error> {
+    node.node_type.serialize(serializer)
+}
+
 /// Represents the display property of a styled node.
 #[derive(PartialEq, Debug)]
 pub enum Display {
@@ -47,6 +55,121 @@ impl<'a> StyledNode<'a> {
             _ => Display::Inline,
         }
     }
+
+    /// The text of this subtree as it is rendered: content with `display: none` is left out,
+    /// whitespace is collapsed, and blocks go on lines of their own. Elements without a
+    /// `display` rule use the browser default, so `<head>` and `<script>` are hidden.
+    /// https://html.spec.whatwg.org/multipage/dom.html#the-innertext-idl-attribute
+    pub fn inner_text(&self) -> String {
+        let mut items = Vec::new();
+        self.collect_text(&mut items, false);
+
+        let mut text = String::new();
+        let mut breaks = 0;
+        for item in items {
+            match item {
+                TextItem::Break(count) => breaks = breaks.max(count),
+                TextItem::Text(ref run) | TextItem::Preserved(ref run) => {
+                    // Collapsible spaces at the start of a line are dropped.
+                    let line_start = text.is_empty() || breaks > 0 || text.ends_with('\n');
+                    let run = match item {
+                        TextItem::Text(_) if line_start => run.trim_start_matches(' '),
+                        _ => run,
+                    };
+                    if run.is_empty() {
+                        continue;
+                    }
+                    if breaks > 0 && !text.is_empty() {
+                        text.truncate(text.trim_end_matches([' ', '\t']).len());
+                        text.push_str(&"\n".repeat(breaks));
+                    }
+                    breaks = 0;
+                    text.push_str(run);
+                }
+            }
+        }
+        text.truncate(text.trim_end_matches([' ', '\t']).len());
+        text
+    }
+
+    fn collect_text(&self, items: &mut Vec<TextItem>, preformatted: bool) {
+        let element = match &self.node.node_type {
+            NodeType::Text(text) if preformatted => {
+                items.push(TextItem::Preserved(text.clone()));
+                return;
+            }
+            NodeType::Text(text) => {
+                let collapsed: Vec<&str> = text.split_ascii_whitespace().collect();
+                let mut run = collapsed.join(" ");
+                if text.starts_with(|c: char| c.is_ascii_whitespace()) {
+                    run.insert(0, ' ');
+                }
+                if text.ends_with(|c: char| c.is_ascii_whitespace()) && !collapsed.is_empty() {
+                    run.push(' ');
+                }
+                if !run.is_empty() {
+                    items.push(TextItem::Text(run));
+                }
+                return;
+            }
+            NodeType::Element(element) => element,
+            _ => return,
+        };
+
+        let display = match self.value("display") {
+            Some(_) => self.display(),
+            None => default_display(element),
+        };
+        let breaks = match (&*element.tag_name, display) {
+            (_, Display::None) => return,
+            ("p", _) => 2,
+            (_, Display::Block) => 1,
+            (_, Display::Inline) => 0,
+        };
+        items.push(TextItem::Break(breaks));
+        if element.tag_name == "br" {
+            items.push(TextItem::Preserved("\n".to_string()));
+        }
+        let preformatted =
+            preformatted || matches!(&*element.tag_name, "pre" | "textarea" | "listing");
+        for child in &self.children {
+            child.collect_text(items, preformatted);
+        }
+        if matches!(&*element.tag_name, "td" | "th") {
+            items.push(TextItem::Preserved("\t".to_string()));
+        }
+        items.push(TextItem::Break(breaks));
+    }
+}
+
+/// A piece of the rendered text, for `StyledNode::inner_text`.
+enum TextItem {
+    /// Text whose spaces collapse.
+    Text(String),
+    /// Text kept as it is, such as the contents of `<pre>`.
+    Preserved(String),
+    /// A place that needs at least this many line breaks, unless it is at the start or end.
+    Break(usize),
+}
+
+/// The display of an element when no rule sets it, following the HTML rendering rules.
+/// https://html.spec.whatwg.org/multipage/rendering.html#hidden-elements
+/// https://html.spec.whatwg.org/multipage/rendering.html#flow-content-3
+fn default_display(element: &ElementData) -> Display {
+    if element.attributes.contains_key("hidden") {
+        return Display::None;
+    }
+    match &*element.tag_name {
+        "area" | "base" | "basefont" | "datalist" | "head" | "link" | "meta" | "noembed"
+        | "noframes" | "param" | "rp" | "script" | "style" | "template" | "title" => Display::None,
+        "address" | "article" | "aside" | "blockquote" | "body" | "caption" | "center" | "dd"
+        | "details" | "dialog" | "dir" | "div" | "dl" | "dt" | "fieldset" | "figcaption"
+        | "figure" | "footer" | "form" | "h1" | "h2" | "h3" | "h4" | "h5" | "h6" | "header"
+        | "hgroup" | "hr" | "html" | "legend" | "li" | "listing" | "main" | "menu" | "nav"
+        | "ol" | "p" | "plaintext" | "pre" | "search" | "section" | "summary" | "table"
+        | "tbody" | "tfoot" | "thead" | "tr" | "ul" | "xmp" => Display::Block,
+        _ => Display::Inline,
+    }
 }
 
 /// Styles the entire DOM tree rooted at the given node based on the provided stylesheet.
@@ -60,6 +183,8 @@ pub fn style_tree<'a>(root: &'a Node, stylesheet: &'a Stylesheet) -> StyledNode<
             NodeType::Text(_) => HashMap::new(),
             NodeType::Comment(_) => HashMap::new(),
             NodeType::ProcessingInstruction(_) => HashMap::new(),
+            NodeType::Doctype(_) => HashMap::new(),
+            NodeType::DocumentFragment => HashMap::new(),
         },
         children: root
             .children
@@ -74,7 +199,7 @@ fn specified_values(elem: &ElementData, stylesheet: &Stylesheet) -> PropertyMap
     let mut values = HashMap::new();
     let mut rules = matching_rules(elem, stylesheet);
 
-    rules.sort_by(|&(a, _), &(b, _)| a.cmp(&b));
+    rules.sort_by_key(|&(specificity, _)| specificity);
     for (_, rule) in rules {
         for declaration in &rule.declarations {
             values.insert(declaration.name.clone(), declaration.value.clone());
@@ -100,21 +225,26 @@ fn matching_rules<'a>(elem: &ElementData, stylesheet: &'a Stylesheet) -> Vec<Mat
 fn match_rule<'a>(elem: &ElementData, rule: &'a Rule) -> Option<MatchedRule<'a>> {
     rule.selectors
         .iter()
-        .find(|selector| matches(elem, *selector))
+        .find(|selector| matches(elem, selector))
         .map(|selector| (selector.specificity(), rule))
 }
 
-/// Checks if an element matches a simple selector.
-fn matches(elem: &ElementData, selector: &Selector) -> bool {
+/// Checks if an element matches a selector.
+pub fn matches(elem: &ElementData, selector: &Selector) -> bool {
     match *selector {
         Selector::Simple(ref simple_selector) => matches_simple_selector(elem, simple_selector),
     }
 }
 
 /// Checks if an element matches a simple selector.
-fn matches_simple_selector(elem: &ElementData, selector: &SimpleSelector) -> bool {
-    // Check type selector
-    if selector.tag_name.iter().any(|name| elem.tag_name != *name) {
+pub fn matches_simple_selector(elem: &ElementData, selector: &SimpleSelector) -> bool {
+    // Check type selector. HTML element names are lowercase, and match type selectors in any
+    // case; foreign elements, such as SVG's `linearGradient`, must match exactly.
+    // https://html.spec.whatwg.org/multipage/semantics-other.html#case-sensitivity-of-selectors
+    if selector.tag_name.iter().any(|name| match elem.namespace {
+        Namespace::Html => !elem.tag_name.eq_ignore_ascii_case(name),
+        _ => elem.tag_name != *name,
+    }) {
         return false;
     }
 
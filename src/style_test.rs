@@ -2,7 +2,7 @@
 mod tests {
     use std::collections::HashMap;
 
-    use crate::{cssom, dom, style};
+    use crate::{css, cssom, dom, html, style};
 
     fn create_attrs() -> dom::AttrMap {
         [("id".to_string(), "my-id".to_string())]
@@ -157,7 +157,10 @@ mod tests {
             styled_node.node.node_type,
             dom::NodeType::Element(dom::ElementData {
                 tag_name: "div".to_string(),
-                attributes: HashMap::new()
+                namespace: dom::Namespace::Html,
+                attributes: HashMap::new(),
+                attribute_spans: HashMap::new(),
+                template_contents: None,
             })
         );
     }
@@ -251,4 +254,81 @@ mod tests {
             Some("This is a comment".to_string())
         );
     }
+
+    #[test]
+    fn type_selectors_ignore_case_for_html_elements() {
+        let color = |namespace: dom::Namespace, tag_name: &str, selector: &str| {
+            let node = dom::elem_ns(namespace, tag_name.to_string(), HashMap::new(), vec![]);
+            let stylesheet = cssom::stylesheet(vec![cssom::rule(
+                vec![cssom::Selector::Simple(cssom::simple_selector(
+                    Some(selector.to_string()),
+                    None,
+                    vec![],
+                ))],
+                vec![cssom::declaration(
+                    "color".to_string(),
+                    cssom::Value::Keyword("red".to_string()),
+                )],
+            )]);
+            style::style_tree(&node, &stylesheet).value("color")
+        };
+        let red = Some(cssom::Value::Keyword("red".to_string()));
+
+        let (html, svg) = (dom::Namespace::Html, dom::Namespace::Svg);
+        assert_eq!(color(html, "div", "DIV"), red);
+        assert_eq!(color(html, "div", "Div"), red);
+        // Foreign elements keep their case, and so must the selector.
+        assert_eq!(color(svg, "linearGradient", "linearGradient"), red);
+        assert_eq!(color(svg, "linearGradient", "lineargradient"), None);
+    }
+
+    #[test]
+    fn inner_text_follows_rendering() {
+        let root = html::parse(
+            "<head><title>Skipped</title><style>p {}</style></head>\
+             <body>\n  <h1>Title</h1>\n  <p>Some   <b>bold</b>\n text.</p><p>Next<br>line</p>\
+             <div class=hidden>Hidden</div><pre>  kept\n  as is</pre>\
+             <table><tr><td>a</td><td>b</td></tr></table> Tail </body>"
+                .to_string(),
+        )
+        .root;
+        let stylesheet = css::parse(".hidden { display: none; }".to_string());
+
+        assert_eq!(
+            style::style_tree(&root, &stylesheet).inner_text(),
+            "Title\n\nSome bold text.\n\nNext\nline\n\n  kept\n  as is\na\tb\nTail"
+        );
+    }
+
+    #[test]
+    fn styled_node_json() {
+        let node = dom::elem(
+            "div".to_string(),
+            create_attrs(),
+            vec![dom::text("Hi".to_string())],
+        );
+        let stylesheet = css::parse("div { display: block; color: red; }".to_string());
+
+        let json = serde_json::to_value(style::style_tree(&node, &stylesheet)).unwrap();
+
+        assert_eq!(
+            json,
+            serde_json::json!({
+                "node": { "Element": {
+                    "tag_name": "div",
+                    "namespace": "Html",
+                    "attributes": { "id": "my-id" },
+                } },
+                "specified_values": {
+                    "color": { "Keyword": "red" },
+                    "display": { "Keyword": "block" },
+                },
+                "children": [{
+                    "node": { "Text": "Hi" },
+                    "specified_values": {},
+                    "children": [],
+                }],
+            })
+        );
+    }
 }
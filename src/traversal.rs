@@ -0,0 +1,426 @@
+//! Walks over a `Document` that show only some nodes, like the DOM's `TreeWalker` and
+//! `NodeIterator`. Both are iterative, so they work on trees of any depth.
+// https://dom.spec.whatwg.org/#traversal
+
+use std::fmt;
+use std::ops::BitOr;
+
+use crate::document::{Document, NodeId};
+use crate::dom::NodeType;
+
+/// Which types of node a walk shows, as a mask of `NodeFilter.SHOW_*` bits.
+/// https://dom.spec.whatwg.org/#dom-nodefilter-whattoshow
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct WhatToShow(u32);
+
+impl WhatToShow {
+    pub const ALL: WhatToShow = WhatToShow(0xFFFF_FFFF);
+    pub const ELEMENT: WhatToShow = WhatToShow(0x1);
+    pub const TEXT: WhatToShow = WhatToShow(0x4);
+    pub const PROCESSING_INSTRUCTION: WhatToShow = WhatToShow(0x40);
+    pub const COMMENT: WhatToShow = WhatToShow(0x80);
+    pub const DOCUMENT_TYPE: WhatToShow = WhatToShow(0x200);
+    pub const DOCUMENT_FRAGMENT: WhatToShow = WhatToShow(0x400);
+
+    /// Whether nodes of this type are shown.
+    pub fn shows(self, node_type: &NodeType) -> bool {
+        let bit = match node_type {
+            NodeType::Element(_) => WhatToShow::ELEMENT,
+            NodeType::Text(_) => WhatToShow::TEXT,
+            NodeType::ProcessingInstruction(_) => WhatToShow::PROCESSING_INSTRUCTION,
+            NodeType::Comment(_) => WhatToShow::COMMENT,
+            NodeType::Doctype(_) => WhatToShow::DOCUMENT_TYPE,
+            NodeType::DocumentFragment => WhatToShow::DOCUMENT_FRAGMENT,
+        };
+        self.0 & bit.0 != 0
+    }
+}
+
+impl BitOr for WhatToShow {
+    type Output = WhatToShow;
+
+    fn bitor(self, other: WhatToShow) -> WhatToShow {
+        WhatToShow(self.0 | other.0)
+    }
+}
+
+/// What a filter makes of a node.
+/// https://dom.spec.whatwg.org/#interface-nodefilter
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FilterResult {
+    /// Show the node.
+    Accept,
+    /// Leave out the node and, for a `TreeWalker`, its descendants.
+    Reject,
+    /// Leave out the node, but not its descendants.
+    Skip,
+}
+
+/// A callback that decides which of the nodes picked by `WhatToShow` are shown.
+type Filter = Box<dyn FnMut(&Document, NodeId) -> FilterResult>;
+
+/// The nodes and filter of a walk.
+struct NodeFilter {
+    root: NodeId,
+    what_to_show: WhatToShow,
+    filter: Option<Filter>,
+}
+
+impl NodeFilter {
+    /// https://dom.spec.whatwg.org/#concept-node-filter
+    fn filter(&mut self, document: &Document, node: NodeId) -> FilterResult {
+        if !self.what_to_show.shows(document.node_type(node)) {
+            return FilterResult::Skip;
+        }
+        match &mut self.filter {
+            Some(filter) => filter(document, node),
+            None => FilterResult::Accept,
+        }
+    }
+}
+
+impl fmt::Debug for NodeFilter {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("NodeFilter")
+            .field("root", &self.root)
+            .field("what_to_show", &self.what_to_show)
+            .finish_non_exhaustive()
+    }
+}
+
+/// Moves around the shown nodes of a subtree from a current node, keeping their tree shape:
+/// nodes that are skipped are replaced by their children.
+/// https://dom.spec.whatwg.org/#interface-treewalker
+#[derive(Debug)]
+pub struct TreeWalker {
+    filter: NodeFilter,
+    current: NodeId,
+}
+
+impl TreeWalker {
+    /// A walker over `root` and its descendants, starting at `root`.
+    /// https://dom.spec.whatwg.org/#dom-document-createtreewalker
+    pub fn new(root: NodeId, what_to_show: WhatToShow) -> Self {
+        TreeWalker {
+            filter: NodeFilter {
+                root,
+                what_to_show,
+                filter: None,
+            },
+            current: root,
+        }
+    }
+
+    /// Also pass the nodes picked by `what_to_show` through `filter`.
+    pub fn filter<F>(mut self, filter: F) -> Self
+    where
+        F: FnMut(&Document, NodeId) -> FilterResult + 'static,
+    {
+        self.filter.filter = Some(Box::new(filter));
+        self
+    }
+
+    pub fn root(&self) -> NodeId {
+        self.filter.root
+    }
+
+    /// https://dom.spec.whatwg.org/#dom-treewalker-currentnode
+    pub fn current_node(&self) -> NodeId {
+        self.current
+    }
+
+    /// Move to `node`, which may be anywhere, even if it is not shown.
+    pub fn set_current_node(&mut self, node: NodeId) {
+        self.current = node;
+    }
+
+    /// https://dom.spec.whatwg.org/#dom-treewalker-parentnode
+    pub fn parent_node(&mut self, document: &Document) -> Option<NodeId> {
+        let mut node = self.current;
+        while node != self.filter.root {
+            node = document.parent(node)?;
+            if self.filter.filter(document, node) == FilterResult::Accept {
+                self.current = node;
+                return Some(node);
+            }
+        }
+        None
+    }
+
+    /// https://dom.spec.whatwg.org/#dom-treewalker-firstchild
+    pub fn first_child(&mut self, document: &Document) -> Option<NodeId> {
+        self.traverse_children(document, true)
+    }
+
+    /// https://dom.spec.whatwg.org/#dom-treewalker-lastchild
+    pub fn last_child(&mut self, document: &Document) -> Option<NodeId> {
+        self.traverse_children(document, false)
+    }
+
+    /// https://dom.spec.whatwg.org/#dom-treewalker-previoussibling
+    pub fn previous_sibling(&mut self, document: &Document) -> Option<NodeId> {
+        self.traverse_siblings(document, false)
+    }
+
+    /// https://dom.spec.whatwg.org/#dom-treewalker-nextsibling
+    pub fn next_sibling(&mut self, document: &Document) -> Option<NodeId> {
+        self.traverse_siblings(document, true)
+    }
+
+    /// The shown node before the current one in tree order.
+    /// https://dom.spec.whatwg.org/#dom-treewalker-previousnode
+    pub fn previous_node(&mut self, document: &Document) -> Option<NodeId> {
+        let mut node = self.current;
+        while node != self.filter.root {
+            while let Some(sibling) = document.previous_sibling(node) {
+                node = sibling;
+                let mut result = self.filter.filter(document, node);
+                while result != FilterResult::Reject {
+                    let Some(child) = document.last_child(node) else {
+                        break;
+                    };
+                    node = child;
+                    result = self.filter.filter(document, node);
+                }
+                if result == FilterResult::Accept {
+                    self.current = node;
+                    return Some(node);
+                }
+            }
+            node = document.parent(node)?;
+            if self.filter.filter(document, node) == FilterResult::Accept {
+                self.current = node;
+                return Some(node);
+            }
+        }
+        None
+    }
+
+    /// The shown node after the current one in tree order.
+    /// https://dom.spec.whatwg.org/#dom-treewalker-nextnode
+    pub fn next_node(&mut self, document: &Document) -> Option<NodeId> {
+        let mut node = self.current;
+        let mut result = FilterResult::Accept;
+        loop {
+            while result != FilterResult::Reject {
+                let Some(child) = document.first_child(node) else {
+                    break;
+                };
+                node = child;
+                result = self.filter.filter(document, node);
+                if result == FilterResult::Accept {
+                    self.current = node;
+                    return Some(node);
+                }
+            }
+            // Go on from the next sibling of the node or of its closest ancestor that has one.
+            let mut temporary = node;
+            loop {
+                if temporary == self.filter.root {
+                    return None;
+                }
+                if let Some(sibling) = document.next_sibling(temporary) {
+                    node = sibling;
+                    break;
+                }
+                temporary = document.parent(temporary)?;
+            }
+            result = self.filter.filter(document, node);
+            if result == FilterResult::Accept {
+                self.current = node;
+                return Some(node);
+            }
+        }
+    }
+
+    /// https://dom.spec.whatwg.org/#concept-traverse-children
+    fn traverse_children(&mut self, document: &Document, first: bool) -> Option<NodeId> {
+        let first_child = |node| match first {
+            true => document.first_child(node),
+            false => document.last_child(node),
+        };
+        let next_sibling = |node| match first {
+            true => document.next_sibling(node),
+            false => document.previous_sibling(node),
+        };
+
+        let mut node = first_child(self.current)?;
+        loop {
+            match self.filter.filter(document, node) {
+                FilterResult::Accept => {
+                    self.current = node;
+                    return Some(node);
+                }
+                FilterResult::Skip => {
+                    if let Some(child) = first_child(node) {
+                        node = child;
+                        continue;
+                    }
+                }
+                FilterResult::Reject => {}
+            }
+            loop {
+                if let Some(sibling) = next_sibling(node) {
+                    node = sibling;
+                    break;
+                }
+                let parent = document.parent(node)?;
+                if parent == self.filter.root || parent == self.current {
+                    return None;
+                }
+                node = parent;
+            }
+        }
+    }
+
+    /// https://dom.spec.whatwg.org/#concept-traverse-siblings
+    fn traverse_siblings(&mut self, document: &Document, next: bool) -> Option<NodeId> {
+        let next_sibling = |node| match next {
+            true => document.next_sibling(node),
+            false => document.previous_sibling(node),
+        };
+        let first_child = |node| match next {
+            true => document.first_child(node),
+            false => document.last_child(node),
+        };
+
+        let mut node = self.current;
+        if node == self.filter.root {
+            return None;
+        }
+        loop {
+            let mut sibling = next_sibling(node);
+            while let Some(candidate) = sibling {
+                node = candidate;
+                let result = self.filter.filter(document, node);
+                if result == FilterResult::Accept {
+                    self.current = node;
+                    return Some(node);
+                }
+                // Look inside skipped nodes before going on to their siblings.
+                sibling = match result {
+                    FilterResult::Reject => None,
+                    _ => first_child(node),
+                }
+                .or_else(|| next_sibling(node));
+            }
+            node = document.parent(node)?;
+            if node == self.filter.root
+                || self.filter.filter(document, node) == FilterResult::Accept
+            {
+                return None;
+            }
+        }
+    }
+}
+
+/// Goes forwards and backwards through the shown nodes of a subtree in tree order, as a flat
+/// list. The iterator does not follow changes to the tree, so its reference node should stay
+/// in the subtree.
+/// https://dom.spec.whatwg.org/#interface-nodeiterator
+#[derive(Debug)]
+pub struct NodeIterator {
+    filter: NodeFilter,
+    reference: NodeId,
+    pointer_before_reference: bool,
+}
+
+impl NodeIterator {
+    /// An iterator over `root` and its descendants, starting before `root`.
+    /// https://dom.spec.whatwg.org/#dom-document-createnodeiterator
+    pub fn new(root: NodeId, what_to_show: WhatToShow) -> Self {
+        NodeIterator {
+            filter: NodeFilter {
+                root,
+                what_to_show,
+                filter: None,
+            },
+            reference: root,
+            pointer_before_reference: true,
+        }
+    }
+
+    /// Also pass the nodes picked by `what_to_show` through `filter`. Rejecting a node skips
+    /// only that node.
+    pub fn filter<F>(mut self, filter: F) -> Self
+    where
+        F: FnMut(&Document, NodeId) -> FilterResult + 'static,
+    {
+        self.filter.filter = Some(Box::new(filter));
+        self
+    }
+
+    pub fn root(&self) -> NodeId {
+        self.filter.root
+    }
+
+    /// https://dom.spec.whatwg.org/#dom-nodeiterator-referencenode
+    pub fn reference_node(&self) -> NodeId {
+        self.reference
+    }
+
+    /// https://dom.spec.whatwg.org/#dom-nodeiterator-nextnode
+    pub fn next_node(&mut self, document: &Document) -> Option<NodeId> {
+        self.traverse(document, true)
+    }
+
+    /// https://dom.spec.whatwg.org/#dom-nodeiterator-previousnode
+    pub fn previous_node(&mut self, document: &Document) -> Option<NodeId> {
+        self.traverse(document, false)
+    }
+
+    /// https://dom.spec.whatwg.org/#concept-nodeiterator-traverse
+    fn traverse(&mut self, document: &Document, next: bool) -> Option<NodeId> {
+        let root = self.filter.root;
+        let mut node = self.reference;
+        let mut before = self.pointer_before_reference;
+        loop {
+            match (next, before) {
+                (true, false) => node = following(document, node, root)?,
+                (false, true) => node = preceding(document, node, root)?,
+                _ => before = !before,
+            }
+            if self.filter.filter(document, node) == FilterResult::Accept {
+                break;
+            }
+        }
+        self.reference = node;
+        self.pointer_before_reference = before;
+        Some(node)
+    }
+}
+
+/// The node after `node` in tree order, inside `root`.
+/// https://dom.spec.whatwg.org/#concept-tree-following
+fn following(document: &Document, node: NodeId, root: NodeId) -> Option<NodeId> {
+    if let Some(child) = document.first_child(node) {
+        return Some(child);
+    }
+    let mut node = node;
+    loop {
+        if node == root {
+            return None;
+        }
+        if let Some(sibling) = document.next_sibling(node) {
+            return Some(sibling);
+        }
+        node = document.parent(node)?;
+    }
+}
+
+/// The node before `node` in tree order, inside `root`.
+/// https://dom.spec.whatwg.org/#concept-tree-preceding
+fn preceding(document: &Document, node: NodeId, root: NodeId) -> Option<NodeId> {
+    if node == root {
+        return None;
+    }
+    match document.previous_sibling(node) {
+        Some(mut sibling) => {
+            while let Some(child) = document.last_child(sibling) {
+                sibling = child;
+            }
+            Some(sibling)
+        }
+        None => document.parent(node),
+    }
+}
@@ -0,0 +1,149 @@
+#[cfg(test)]
+mod tests {
+    use crate::document::{Document, NodeId};
+    use crate::dom::{self, NodeType};
+    use crate::html;
+    use crate::traversal::{FilterResult, NodeIterator, TreeWalker, WhatToShow};
+
+    /// `<div><p>One<!-- two --></p><ul><li>Three</li></ul><p>Four</p></div>`
+    fn create_document() -> Document {
+        let root = html::parse(
+            "<div><p>One<!-- two --></p><ul><li>Three</li></ul><p>Four</p></div>".to_string(),
+        )
+        .root;
+        let div = root.query_selector("div").unwrap().unwrap().clone();
+        Document::new(div)
+    }
+
+    fn describe(document: &Document, id: Option<NodeId>) -> String {
+        match id.map(|id| document.node_type(id)) {
+            Some(NodeType::Element(element)) => element.tag_name.clone(),
+            Some(NodeType::Text(text)) => text.clone(),
+            Some(NodeType::Comment(_)) => "comment".to_string(),
+            Some(_) => "other".to_string(),
+            None => "none".to_string(),
+        }
+    }
+
+    /// Leaves out `ul` and its subtree, or just the `ul` when `skip` is set.
+    fn without_lists(skip: bool) -> impl FnMut(&Document, NodeId) -> FilterResult {
+        move |document, id| match document.element(id) {
+            Some(element) if element.tag_name == "ul" && skip => FilterResult::Skip,
+            Some(element) if element.tag_name == "ul" => FilterResult::Reject,
+            _ => FilterResult::Accept,
+        }
+    }
+
+    #[test]
+    fn test_tree_walker_what_to_show() {
+        let document = create_document();
+        let mut walker = TreeWalker::new(document.root(), WhatToShow::TEXT | WhatToShow::COMMENT);
+        let mut nodes = Vec::new();
+        while let Some(node) = walker.next_node(&document) {
+            nodes.push(describe(&document, Some(node)));
+        }
+        assert_eq!(nodes, vec!["One", "comment", "Three", "Four"]);
+
+        let mut backwards = Vec::new();
+        while let Some(node) = walker.previous_node(&document) {
+            backwards.push(describe(&document, Some(node)));
+        }
+        assert_eq!(backwards, vec!["Three", "comment", "One"]);
+    }
+
+    #[test]
+    fn test_tree_walker_reject_and_skip() {
+        let document = create_document();
+        let root = document.root();
+        let walk = |skip: bool| {
+            let mut walker = TreeWalker::new(root, WhatToShow::ELEMENT).filter(without_lists(skip));
+            let mut nodes = Vec::new();
+            while let Some(node) = walker.next_node(&document) {
+                nodes.push(describe(&document, Some(node)));
+            }
+            nodes
+        };
+        assert_eq!(walk(false), vec!["p", "p"]);
+        assert_eq!(walk(true), vec!["p", "li", "p"]);
+
+        // Skipped nodes are replaced by their children.
+        let mut walker = TreeWalker::new(root, WhatToShow::ELEMENT).filter(without_lists(true));
+        let first = walker.first_child(&document);
+        assert_eq!(describe(&document, first), "p");
+        let li = walker.next_sibling(&document);
+        assert_eq!(describe(&document, li), "li");
+        assert_eq!(walker.parent_node(&document), Some(root));
+        let last = walker.last_child(&document);
+        assert_eq!(describe(&document, last), "p");
+        assert_eq!(walker.previous_sibling(&document), li);
+        assert_eq!(walker.first_child(&document), None);
+        assert_eq!(walker.current_node(), li.unwrap());
+    }
+
+    #[test]
+    fn test_node_iterator() {
+        let document = create_document();
+        let mut iterator =
+            NodeIterator::new(document.root(), WhatToShow::ALL).filter(without_lists(false));
+        let mut nodes = Vec::new();
+        while let Some(node) = iterator.next_node(&document) {
+            nodes.push(describe(&document, Some(node)));
+        }
+        // Rejected nodes are left out, but not their descendants.
+        assert_eq!(
+            nodes,
+            vec!["div", "p", "One", "comment", "li", "Three", "p", "Four"]
+        );
+
+        // Changing direction returns the reference node again.
+        let previous = iterator.previous_node(&document);
+        assert_eq!(describe(&document, previous), "Four");
+        let previous = iterator.previous_node(&document);
+        assert_eq!(describe(&document, previous), "p");
+        let next = iterator.next_node(&document);
+        assert_eq!(describe(&document, next), "p");
+        assert_eq!(iterator.reference_node(), next.unwrap());
+    }
+
+    #[test]
+    fn test_deep_tree() {
+        let mut document = Document::new(dom::elem("div".to_string(), Default::default(), vec![]));
+        let leaf = document.create_text("leaf");
+        let mut node = leaf;
+        for _ in 0..100_000 {
+            let parent = document.create_element("b", Default::default());
+            document.append_child(parent, node).unwrap();
+            node = parent;
+        }
+        document.append_child(document.root(), node).unwrap();
+
+        let mut walker = TreeWalker::new(document.root(), WhatToShow::TEXT);
+        assert_eq!(walker.next_node(&document), Some(leaf));
+        assert_eq!(walker.next_node(&document), None);
+        let mut iterator = NodeIterator::new(document.root(), WhatToShow::TEXT);
+        assert_eq!(iterator.next_node(&document), Some(leaf));
+        assert_eq!(iterator.previous_node(&document), Some(leaf));
+        assert_eq!(iterator.previous_node(&document), None);
+    }
+
+    #[test]
+    fn test_deep_document() {
+        // The parser limits nesting, so the tree is built by hand.
+        let depth = 20_000;
+        let mut root = dom::text("leaf".to_string());
+        for _ in 0..depth {
+            root = dom::elem("span".to_string(), Default::default(), vec![root]);
+        }
+        let mut document = Document::new(root);
+
+        let mut walker = TreeWalker::new(document.root(), WhatToShow::TEXT);
+        let leaf = walker.next_node(&document).unwrap();
+        assert_eq!(document.ancestors(leaf).count(), depth);
+        let copy = document.clone_node(document.root(), true);
+        let mut iterator = NodeIterator::new(copy, WhatToShow::TEXT);
+        let copied_leaf = iterator.next_node(&document).unwrap();
+        assert_eq!(document.text_content(copied_leaf), "leaf");
+        // Not `assert_eq!`, whose message would print the whole tree.
+        assert!(document.to_node(copy) == document.to_node(document.root()));
+    }
+}
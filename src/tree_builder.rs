@@ -0,0 +1,2949 @@
+//! HTML tree construction.
+//!
+//! Turns the tokenizer's tokens into a DOM tree the way a browser does: elements are opened
+//! and closed implicitly, `html`, `head` and `body` are created when missing, and misnested
+//! formatting tags are repaired.
+//! https://html.spec.whatwg.org/multipage/parsing.html#tree-construction
+
+use std::collections::HashMap;
+
+use crate::dom;
+use crate::encoding::Encoding;
+use crate::html::{
+    is_void_element, DoctypeToken, ParseErrorKind, ParseResult, Tag, Token, Tokenizer,
+};
+
+/// https://html.spec.whatwg.org/multipage/parsing.html#the-insertion-mode
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum InsertionMode {
+    Initial,
+    BeforeHtml,
+    BeforeHead,
+    InHead,
+    AfterHead,
+    InBody,
+    Text,
+    InTable,
+    InCaption,
+    InColumnGroup,
+    InTableBody,
+    InRow,
+    InCell,
+    InSelect,
+    InSelectInTable,
+    InTemplate,
+    AfterBody,
+    InFrameset,
+    AfterFrameset,
+    AfterAfterBody,
+    AfterAfterFrameset,
+}
+
+/// The kinds of element scope the tree builder checks.
+/// https://html.spec.whatwg.org/multipage/parsing.html#has-an-element-in-scope
+#[derive(Clone, Copy)]
+enum Scope {
+    Default,
+    ListItem,
+    Button,
+    Table,
+    Select,
+}
+
+/// An entry in the list of active formatting elements.
+/// https://html.spec.whatwg.org/multipage/parsing.html#list-of-active-formatting-elements
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Formatting {
+    Marker,
+    Element(usize),
+}
+
+/// A node under construction. Nodes refer to each other by index, so the tree can be
+/// rearranged while it is being built.
+struct TreeNode {
+    /// `None` for the document itself.
+    data: Option<dom::NodeType>,
+    parent: Option<usize>,
+    children: Vec<usize>,
+    span: Option<dom::Span>,
+    /// Whether an end tag closed the element, so its span already ends in the right place.
+    closed_by_end_tag: bool,
+    /// The `DocumentFragment` node that holds the contents of a `<template>`.
+    template_contents: Option<usize>,
+}
+
+/// The document node is always the first node.
+const DOCUMENT: usize = 0;
+
+/// How many elements may be open at once. Deeper elements become siblings of the current
+/// node instead of its children, as in browsers, so that scope checks stay cheap.
+const MAX_DEPTH: usize = 512;
+
+/// Builds a DOM tree from the tokens of a `Tokenizer`.
+pub struct TreeBuilder {
+    tokenizer: Tokenizer,
+    nodes: Vec<TreeNode>,
+    open_elements: Vec<usize>,
+    active_formatting: Vec<Formatting>,
+    mode: InsertionMode,
+    /// The mode to return to after the `Text` mode.
+    original_mode: InsertionMode,
+    /// The modes for the content of each open `<template>`, innermost last.
+    /// https://html.spec.whatwg.org/multipage/parsing.html#stack-of-template-insertion-modes
+    template_modes: Vec<InsertionMode>,
+    head: Option<usize>,
+    form: Option<usize>,
+    /// Whether nodes that would go into a table are moved before it instead.
+    foster_parenting: bool,
+    /// Whether a newline at the start of the next token is dropped, as after `<pre>`.
+    ignore_next_newline: bool,
+    /// Whether a `<frameset>` may still replace the body, because nothing has been put in it
+    /// that would show.
+    /// https://html.spec.whatwg.org/multipage/parsing.html#frameset-ok-flag
+    frameset_ok: bool,
+    doctype: Option<dom::Node>,
+    document_mode: dom::DocumentMode,
+    /// Byte offsets of the start and end of the current token.
+    token_start: usize,
+    token_end: usize,
+    /// Where the part of the current text token that is still to be inserted starts.
+    text_start: usize,
+    /// Whether the run of text the current text token is part of was reported as unexpected.
+    /// The text of a `<plaintext>` comes in as many tokens as there are chunks of input, but
+    /// is reported once.
+    text_reported: bool,
+}
+
+impl TreeBuilder {
+    /// Create a tree builder that reads its tokens from `tokenizer`.
+    pub(crate) fn new(tokenizer: Tokenizer) -> Self {
+        TreeBuilder {
+            tokenizer,
+            nodes: vec![TreeNode {
+                data: None,
+                parent: None,
+                children: Vec::new(),
+                span: None,
+                closed_by_end_tag: false,
+                template_contents: None,
+            }],
+            open_elements: Vec::new(),
+            active_formatting: Vec::new(),
+            mode: InsertionMode::Initial,
+            original_mode: InsertionMode::Initial,
+            template_modes: Vec::new(),
+            head: None,
+            form: None,
+            foster_parenting: false,
+            ignore_next_newline: false,
+            frameset_ok: true,
+            doctype: None,
+            // A document without a doctype is rendered in quirks mode.
+            document_mode: dom::DocumentMode::Quirks,
+            token_start: 0,
+            token_end: 0,
+            text_start: 0,
+            text_reported: false,
+        }
+    }
+
+    /// Consume every token and return the finished tree.
+    pub(crate) fn build(mut self) -> ParseResult {
+        loop {
+            let cdata_allowed = self.namespace(self.current_node()) != dom::Namespace::Html;
+            self.tokenizer.set_cdata_allowed(cdata_allowed);
+            let token = self.tokenizer.next_token();
+            self.token_start = self.tokenizer.token_start;
+            self.token_end = self.tokenizer.offset();
+            self.text_start = self.token_start;
+            let eof = token == Token::Eof;
+            self.process(token);
+            if eof {
+                break;
+            }
+        }
+
+        // The `html` element is always created, so the document has an element child.
+        let mut root = None;
+        let mut document_nodes = Vec::new();
+        for child in std::mem::take(&mut self.nodes[DOCUMENT].children) {
+            let node = self.take_node(child);
+            match node.node_type {
+                dom::NodeType::Element(_) if root.is_none() => root = Some(node),
+                _ => document_nodes.push(node),
+            }
+        }
+
+        ParseResult {
+            root: root.unwrap_or_else(|| dom::elem("html".to_string(), Default::default(), vec![])),
+            doctype: self.doctype,
+            document_nodes,
+            mode: self.document_mode,
+            errors: self.tokenizer.errors,
+            // Tokens are always UTF-8; `html::parse_bytes` records the original encoding.
+            encoding: Encoding::Utf8,
+        }
+    }
+
+    /// Move a node and its descendants out of the arena into a `dom::Node` tree.
+    fn take_node(&mut self, id: usize) -> dom::Node {
+        // Build children before their parents, from a stack rather than by recursion, so the
+        // tree can be any depth.
+        let mut built: HashMap<usize, dom::Node> = HashMap::new();
+        let mut stack = vec![(id, false)];
+        while let Some((id, children_built)) = stack.pop() {
+            let node = &self.nodes[id];
+            if !children_built {
+                stack.push((id, true));
+                stack.extend(
+                    node.children
+                        .iter()
+                        .chain(&node.template_contents)
+                        .map(|&child| (child, false)),
+                );
+                continue;
+            }
+            let children: Vec<dom::Node> = std::mem::take(&mut self.nodes[id].children)
+                .into_iter()
+                .filter_map(|child| built.remove(&child))
+                .collect();
+
+            // An element without an end tag ends with its last descendant.
+            let mut span = self.nodes[id].span;
+            if let (Some(span), false) = (&mut span, self.nodes[id].closed_by_end_tag) {
+                if let Some(last) = children.last().and_then(|child| child.span) {
+                    if last.end.offset > span.end.offset {
+                        span.end = last.end;
+                    }
+                }
+            }
+
+            let mut node_type = self.nodes[id]
+                .data
+                .take()
+                .unwrap_or_else(|| dom::NodeType::Comment(String::new()));
+            if let (dom::NodeType::Element(element), Some(contents)) =
+                (&mut node_type, self.nodes[id].template_contents)
+            {
+                element.template_contents = built.remove(&contents).map(Box::new);
+            }
+
+            built.insert(
+                id,
+                dom::Node {
+                    children,
+                    node_type,
+                    span,
+                },
+            );
+        }
+        built.remove(&id).expect("the root is built last")
+    }
+
+    /// Hand a token to the current insertion mode, as many times as the modes ask for it.
+    fn process(&mut self, token: Token) {
+        if !matches!(token, Token::Text(_)) {
+            self.text_reported = false;
+        }
+        let token = match token {
+            Token::Text(text) if self.ignore_next_newline => {
+                self.ignore_next_newline = false;
+                match text.strip_prefix('\n') {
+                    Some("") => return,
+                    Some(rest) => {
+                        self.text_start += 1;
+                        Token::Text(rest.to_string())
+                    }
+                    None => Token::Text(text),
+                }
+            }
+            token => {
+                self.ignore_next_newline = false;
+                token
+            }
+        };
+
+        // Only void elements may use `/>` in HTML. Foreign elements all may.
+        if let Token::StartTag(tag) = &token {
+            if tag.self_closing
+                && !is_void_element(&tag.name)
+                && !matches!(&*tag.name, "svg" | "math")
+                && !self.in_foreign_content(&token)
+            {
+                self.error(ParseErrorKind::NonVoidHtmlElementStartTagWithTrailingSolidus);
+            }
+        }
+
+        // Remember which element an end tag is likely to close, to end its span there.
+        let closing = match &token {
+            Token::EndTag(tag) => self
+                .open_elements
+                .iter()
+                .rev()
+                .find(|&&id| self.name(id).eq_ignore_ascii_case(&tag.name))
+                .copied(),
+            _ => None,
+        };
+
+        let mut next = Some(token);
+        while let Some(token) = next {
+            next = if self.in_foreign_content(&token) {
+                self.foreign_content(token)
+            } else {
+                self.process_in_mode(token)
+            };
+        }
+
+        if let Some(element) = closing {
+            if !self.open_elements.contains(&element) {
+                let end = self.tokenizer.source_position(self.token_end);
+                let node = &mut self.nodes[element];
+                if let Some(span) = &mut node.span {
+                    span.end = end;
+                }
+                node.closed_by_end_tag = true;
+            }
+        }
+    }
+
+    /// Whether a token is handled by the rules for foreign content rather than by the
+    /// insertion mode.
+    /// https://html.spec.whatwg.org/multipage/parsing.html#tree-construction-dispatcher
+    fn in_foreign_content(&self, token: &Token) -> bool {
+        let node = self.current_node();
+        if self.namespace(node) == dom::Namespace::Html {
+            return false;
+        }
+        match token {
+            Token::StartTag(tag) => {
+                !((self.is_mathml_text_integration_point(node)
+                    && !matches!(&*tag.name, "mglyph" | "malignmark"))
+                    || (self.namespace(node) == dom::Namespace::MathMl
+                        && self.name(node) == "annotation-xml"
+                        && tag.name == "svg")
+                    || self.is_html_integration_point(node))
+            }
+            Token::Text(_) => {
+                !(self.is_mathml_text_integration_point(node)
+                    || self.is_html_integration_point(node))
+            }
+            Token::Eof => false,
+            _ => true,
+        }
+    }
+
+    /// Hand a token to the current insertion mode.
+    fn process_in_mode(&mut self, token: Token) -> Option<Token> {
+        match self.mode {
+            InsertionMode::Initial => self.initial(token),
+            InsertionMode::BeforeHtml => self.before_html(token),
+            InsertionMode::BeforeHead => self.before_head(token),
+            InsertionMode::InHead => self.in_head(token),
+            InsertionMode::AfterHead => self.after_head(token),
+            InsertionMode::InBody => self.in_body(token),
+            InsertionMode::Text => self.text(token),
+            InsertionMode::InTable => self.in_table(token),
+            InsertionMode::InCaption => self.in_caption(token),
+            InsertionMode::InColumnGroup => self.in_column_group(token),
+            InsertionMode::InTableBody => self.in_table_body(token),
+            InsertionMode::InRow => self.in_row(token),
+            InsertionMode::InCell => self.in_cell(token),
+            InsertionMode::InSelect => self.in_select(token),
+            InsertionMode::InSelectInTable => self.in_select_in_table(token),
+            InsertionMode::InTemplate => self.in_template(token),
+            InsertionMode::AfterBody => self.after_body(token),
+            InsertionMode::InFrameset => self.in_frameset(token),
+            InsertionMode::AfterFrameset => self.after_frameset(token),
+            InsertionMode::AfterAfterBody => self.after_after_body(token),
+            InsertionMode::AfterAfterFrameset => self.after_after_frameset(token),
+        }
+    }
+
+    // Insertion modes. Each returns the token again when it has to be reprocessed, usually
+    // after switching to another mode.
+
+    /// https://html.spec.whatwg.org/multipage/parsing.html#the-initial-insertion-mode
+    fn initial(&mut self, token: Token) -> Option<Token> {
+        match self.skip_leading_whitespace(token)? {
+            token @ (Token::Comment(_) | Token::ProcessingInstruction { .. }) => {
+                self.insert_comment(token, Some(DOCUMENT));
+            }
+            Token::Doctype(doctype) => {
+                self.document_mode = document_mode(&doctype);
+                let mut node = dom::doctype(
+                    doctype.name.unwrap_or_default(),
+                    doctype.public_id.unwrap_or_default(),
+                    doctype.system_id.unwrap_or_default(),
+                );
+                node.span = Some(self.token_span());
+                self.doctype = Some(node);
+                self.mode = InsertionMode::BeforeHtml;
+            }
+            token => {
+                self.mode = InsertionMode::BeforeHtml;
+                return Some(token);
+            }
+        }
+        None
+    }
+
+    /// https://html.spec.whatwg.org/multipage/parsing.html#the-before-html-insertion-mode
+    fn before_html(&mut self, token: Token) -> Option<Token> {
+        match self.skip_leading_whitespace(token)? {
+            Token::Doctype(_) => self.error(ParseErrorKind::UnexpectedDoctype),
+            token @ (Token::Comment(_) | Token::ProcessingInstruction { .. }) => {
+                self.insert_comment(token, Some(DOCUMENT));
+            }
+            Token::StartTag(tag) if tag.name == "html" => {
+                let html = self.create_element(tag);
+                self.append(DOCUMENT, None, html);
+                self.open_elements.push(html);
+                self.mode = InsertionMode::BeforeHead;
+            }
+            Token::EndTag(tag) if !matches!(&*tag.name, "head" | "body" | "html" | "br") => {
+                self.error(ParseErrorKind::UnexpectedEndTag(tag.name));
+            }
+            token => {
+                let html = self.create_element(Tag::new("html"));
+                self.nodes[html].span = Some(self.implied_span());
+                self.append(DOCUMENT, None, html);
+                self.open_elements.push(html);
+                self.mode = InsertionMode::BeforeHead;
+                return Some(token);
+            }
+        }
+        None
+    }
+
+    /// https://html.spec.whatwg.org/multipage/parsing.html#the-before-head-insertion-mode
+    fn before_head(&mut self, token: Token) -> Option<Token> {
+        match self.skip_leading_whitespace(token)? {
+            token @ (Token::Comment(_) | Token::ProcessingInstruction { .. }) => {
+                self.insert_comment(token, None);
+            }
+            Token::Doctype(_) => self.error(ParseErrorKind::UnexpectedDoctype),
+            Token::StartTag(tag) if tag.name == "html" => {
+                return self.in_body(Token::StartTag(tag));
+            }
+            Token::StartTag(tag) if tag.name == "head" => {
+                self.head = Some(self.insert_element(tag));
+                self.mode = InsertionMode::InHead;
+            }
+            Token::EndTag(tag) if !matches!(&*tag.name, "head" | "body" | "html" | "br") => {
+                self.error(ParseErrorKind::UnexpectedEndTag(tag.name));
+            }
+            token => {
+                self.head = Some(self.insert_implied_element("head"));
+                self.mode = InsertionMode::InHead;
+                return Some(token);
+            }
+        }
+        None
+    }
+
+    /// https://html.spec.whatwg.org/multipage/parsing.html#parsing-main-inhead
+    fn in_head(&mut self, token: Token) -> Option<Token> {
+        let token = self.insert_leading_whitespace(token)?;
+        match token {
+            Token::Comment(_) | Token::ProcessingInstruction { .. } => {
+                self.insert_comment(token, None);
+            }
+            Token::Doctype(_) => self.error(ParseErrorKind::UnexpectedDoctype),
+            Token::StartTag(tag) => match &*tag.name {
+                "html" => return self.in_body(Token::StartTag(tag)),
+                "base" | "basefont" | "bgsound" | "link" | "meta" => {
+                    self.insert_element(tag);
+                    self.open_elements.pop();
+                }
+                "title" | "noframes" | "style" | "script" => {
+                    // The tokenizer reads the contents as text, up to the matching end tag.
+                    self.insert_element(tag);
+                    self.original_mode = self.mode;
+                    self.mode = InsertionMode::Text;
+                }
+                "template" => {
+                    self.insert_element(tag);
+                    self.active_formatting.push(Formatting::Marker);
+                    self.mode = InsertionMode::InTemplate;
+                    self.template_modes.push(InsertionMode::InTemplate);
+                }
+                "head" => self.error(ParseErrorKind::UnexpectedStartTag(tag.name)),
+                _ => return self.leave_head(Token::StartTag(tag)),
+            },
+            Token::EndTag(tag) => match &*tag.name {
+                "head" => {
+                    self.open_elements.pop();
+                    self.mode = InsertionMode::AfterHead;
+                }
+                "body" | "html" | "br" => return self.leave_head(Token::EndTag(tag)),
+                "template" => {
+                    if !self.template_is_open() {
+                        self.error(ParseErrorKind::UnexpectedEndTag(tag.name));
+                        return None;
+                    }
+                    self.generate_all_implied_end_tags();
+                    self.close_elements(&["template"], "template");
+                    self.clear_active_formatting_to_marker();
+                    self.template_modes.pop();
+                    self.reset_insertion_mode();
+                }
+                _ => self.error(ParseErrorKind::UnexpectedEndTag(tag.name)),
+            },
+            token => return self.leave_head(token),
+        }
+        None
+    }
+
+    /// Close the `head` element for a token that does not belong in it.
+    fn leave_head(&mut self, token: Token) -> Option<Token> {
+        self.open_elements.pop();
+        self.mode = InsertionMode::AfterHead;
+        Some(token)
+    }
+
+    /// https://html.spec.whatwg.org/multipage/parsing.html#the-after-head-insertion-mode
+    fn after_head(&mut self, token: Token) -> Option<Token> {
+        let token = self.insert_leading_whitespace(token)?;
+        match token {
+            Token::Comment(_) | Token::ProcessingInstruction { .. } => {
+                self.insert_comment(token, None);
+            }
+            Token::Doctype(_) => self.error(ParseErrorKind::UnexpectedDoctype),
+            Token::StartTag(tag) if tag.name == "html" => {
+                return self.in_body(Token::StartTag(tag))
+            }
+            Token::StartTag(tag) if tag.name == "body" => {
+                self.insert_element(tag);
+                self.frameset_ok = false;
+                self.mode = InsertionMode::InBody;
+            }
+            Token::StartTag(tag) if tag.name == "frameset" => {
+                self.insert_element(tag);
+                self.mode = InsertionMode::InFrameset;
+            }
+            Token::StartTag(tag)
+                if matches!(
+                    &*tag.name,
+                    "base"
+                        | "basefont"
+                        | "bgsound"
+                        | "link"
+                        | "meta"
+                        | "noframes"
+                        | "script"
+                        | "style"
+                        | "template"
+                        | "title"
+                ) =>
+            {
+                // Elements that belong in the head still go there.
+                self.error(ParseErrorKind::UnexpectedStartTag(tag.name.clone()));
+                let head = self.head.unwrap_or(DOCUMENT);
+                self.open_elements.push(head);
+                let next = self.in_head(Token::StartTag(tag));
+                self.open_elements.retain(|&id| id != head);
+                return next;
+            }
+            Token::StartTag(tag) if tag.name == "head" => {
+                self.error(ParseErrorKind::UnexpectedStartTag(tag.name));
+            }
+            Token::EndTag(tag) if tag.name == "template" => {
+                return self.in_head(Token::EndTag(tag))
+            }
+            Token::EndTag(tag) if !matches!(&*tag.name, "body" | "html" | "br") => {
+                self.error(ParseErrorKind::UnexpectedEndTag(tag.name));
+            }
+            token => {
+                self.insert_implied_element("body");
+                self.mode = InsertionMode::InBody;
+                return Some(token);
+            }
+        }
+        None
+    }
+
+    /// https://html.spec.whatwg.org/multipage/parsing.html#parsing-main-inbody
+    fn in_body(&mut self, token: Token) -> Option<Token> {
+        match token {
+            Token::Text(text) => {
+                self.reconstruct_active_formatting_elements();
+                if !text.chars().all(is_whitespace) {
+                    self.frameset_ok = false;
+                }
+                self.insert_text(&text);
+            }
+            Token::Comment(_) | Token::ProcessingInstruction { .. } => {
+                self.insert_comment(token, None);
+            }
+            Token::Doctype(_) => self.error(ParseErrorKind::UnexpectedDoctype),
+            Token::StartTag(tag) => return self.in_body_start_tag(tag),
+            Token::EndTag(tag) => return self.in_body_end_tag(tag),
+            Token::Eof if !self.template_modes.is_empty() => return self.in_template(Token::Eof),
+            Token::Eof => self.report_unclosed_elements(),
+        }
+        None
+    }
+
+    fn in_body_start_tag(&mut self, mut tag: Tag) -> Option<Token> {
+        match &*tag.name {
+            "html" => {
+                self.error(ParseErrorKind::UnexpectedStartTag(tag.name.clone()));
+                if let (Some(&html), false) = (self.open_elements.first(), self.template_is_open())
+                {
+                    self.merge_attributes(html, tag);
+                }
+            }
+            "base" | "basefont" | "bgsound" | "link" | "meta" | "noframes" | "script" | "style"
+            | "template" | "title" => return self.in_head(Token::StartTag(tag)),
+            "body" => {
+                self.error(ParseErrorKind::UnexpectedStartTag(tag.name.clone()));
+                if self.template_is_open() {
+                    return None;
+                }
+                if let Some(&body) = self.open_elements.get(1) {
+                    if self.name(body) == "body" {
+                        self.frameset_ok = false;
+                        self.merge_attributes(body, tag);
+                    }
+                }
+            }
+            "frameset" => {
+                self.error(ParseErrorKind::UnexpectedStartTag(tag.name.clone()));
+                let body = self.open_elements.get(1).copied();
+                if !self.frameset_ok || body.is_none_or(|body| self.name(body) != "body") {
+                    return None;
+                }
+                // The frameset replaces the body, which has nothing in it that would show.
+                if let Some(body) = body {
+                    if let Some(parent) = self.nodes[body].parent.take() {
+                        self.nodes[parent].children.retain(|&id| id != body);
+                    }
+                }
+                self.open_elements.truncate(1);
+                self.insert_element(tag);
+                self.mode = InsertionMode::InFrameset;
+            }
+            "address" | "article" | "aside" | "blockquote" | "center" | "details" | "dialog"
+            | "dir" | "div" | "dl" | "fieldset" | "figcaption" | "figure" | "footer" | "header"
+            | "hgroup" | "main" | "menu" | "nav" | "ol" | "p" | "search" | "section"
+            | "summary" | "ul" => {
+                self.close_p_in_button_scope();
+                self.insert_element(tag);
+            }
+            "h1" | "h2" | "h3" | "h4" | "h5" | "h6" => {
+                self.close_p_in_button_scope();
+                if is_heading(self.current_name()) {
+                    self.error(ParseErrorKind::MissingEndTag(
+                        self.current_name().to_string(),
+                    ));
+                    self.open_elements.pop();
+                }
+                self.insert_element(tag);
+            }
+            "plaintext" => {
+                self.close_p_in_button_scope();
+                self.insert_element(tag);
+                self.tokenizer.set_plaintext();
+            }
+            "pre" | "listing" => {
+                self.close_p_in_button_scope();
+                self.insert_element(tag);
+                self.ignore_next_newline = true;
+                self.frameset_ok = false;
+            }
+            "form" => {
+                // Forms in templates are not associated with the form element pointer.
+                if self.form.is_some() && !self.template_is_open() {
+                    self.error(ParseErrorKind::UnexpectedStartTag(tag.name));
+                } else {
+                    self.close_p_in_button_scope();
+                    let form = self.insert_element(tag);
+                    if !self.template_is_open() {
+                        self.form = Some(form);
+                    }
+                }
+            }
+            "li" => {
+                self.frameset_ok = false;
+                self.close_list_item(&["li"]);
+                self.close_p_in_button_scope();
+                self.insert_element(tag);
+            }
+            "dd" | "dt" => {
+                self.frameset_ok = false;
+                self.close_list_item(&["dd", "dt"]);
+                self.close_p_in_button_scope();
+                self.insert_element(tag);
+            }
+            "button" => {
+                if self.in_scope(&["button"], Scope::Default) {
+                    self.error(ParseErrorKind::MissingEndTag("button".to_string()));
+                    self.generate_implied_end_tags(None);
+                    self.pop_until(&["button"]);
+                }
+                self.reconstruct_active_formatting_elements();
+                self.insert_element(tag);
+                self.frameset_ok = false;
+            }
+            "a" => {
+                if let Some((_, a)) = self.formatting_element_after_marker("a") {
+                    // An `<a>` inside an `<a>` closes the first one.
+                    self.error(ParseErrorKind::MissingEndTag("a".to_string()));
+                    self.adoption_agency("a");
+                    self.active_formatting
+                        .retain(|&e| e != Formatting::Element(a));
+                    self.open_elements.retain(|&id| id != a);
+                }
+                self.reconstruct_active_formatting_elements();
+                let a = self.insert_element(tag);
+                self.push_active_formatting(a);
+            }
+            "b" | "big" | "code" | "em" | "font" | "i" | "s" | "small" | "strike" | "strong"
+            | "tt" | "u" => {
+                self.reconstruct_active_formatting_elements();
+                let element = self.insert_element(tag);
+                self.push_active_formatting(element);
+            }
+            "nobr" => {
+                self.reconstruct_active_formatting_elements();
+                if self.in_scope(&["nobr"], Scope::Default) {
+                    self.error(ParseErrorKind::MissingEndTag("nobr".to_string()));
+                    self.adoption_agency("nobr");
+                    self.reconstruct_active_formatting_elements();
+                }
+                let nobr = self.insert_element(tag);
+                self.push_active_formatting(nobr);
+            }
+            "applet" | "marquee" | "object" => {
+                self.reconstruct_active_formatting_elements();
+                self.insert_element(tag);
+                self.active_formatting.push(Formatting::Marker);
+                self.frameset_ok = false;
+            }
+            "table" => {
+                // Quirks mode lets a table sit inside a paragraph.
+                if self.document_mode != dom::DocumentMode::Quirks {
+                    self.close_p_in_button_scope();
+                }
+                self.insert_element(tag);
+                self.frameset_ok = false;
+                self.mode = InsertionMode::InTable;
+            }
+            "area" | "br" | "embed" | "img" | "keygen" | "wbr" | "input" => {
+                let hidden_input = tag.name == "input"
+                    && tag
+                        .attributes
+                        .get("type")
+                        .is_some_and(|value| value.eq_ignore_ascii_case("hidden"));
+                self.reconstruct_active_formatting_elements();
+                self.insert_element(tag);
+                self.open_elements.pop();
+                if !hidden_input {
+                    self.frameset_ok = false;
+                }
+            }
+            "param" | "source" | "track" => {
+                self.insert_element(tag);
+                self.open_elements.pop();
+            }
+            "hr" => {
+                self.close_p_in_button_scope();
+                self.insert_element(tag);
+                self.open_elements.pop();
+                self.frameset_ok = false;
+            }
+            "math" | "svg" => {
+                self.reconstruct_active_formatting_elements();
+                let namespace = if tag.name == "svg" {
+                    dom::Namespace::Svg
+                } else {
+                    dom::Namespace::MathMl
+                };
+                self.insert_foreign_element(tag, namespace);
+            }
+            "image" => {
+                // Don't ask.
+                self.error(ParseErrorKind::UnexpectedStartTag(tag.name));
+                tag.name = "img".to_string();
+                return Some(Token::StartTag(tag));
+            }
+            "textarea" => {
+                self.insert_element(tag);
+                self.ignore_next_newline = true;
+                self.frameset_ok = false;
+                self.original_mode = self.mode;
+                self.mode = InsertionMode::Text;
+            }
+            "xmp" | "iframe" | "noembed" => {
+                if tag.name == "xmp" {
+                    self.close_p_in_button_scope();
+                    self.reconstruct_active_formatting_elements();
+                }
+                self.insert_element(tag);
+                self.frameset_ok = false;
+                self.original_mode = self.mode;
+                self.mode = InsertionMode::Text;
+            }
+            "select" => {
+                self.reconstruct_active_formatting_elements();
+                self.insert_element(tag);
+                self.frameset_ok = false;
+                self.mode = match self.mode {
+                    InsertionMode::InTable
+                    | InsertionMode::InCaption
+                    | InsertionMode::InTableBody
+                    | InsertionMode::InRow
+                    | InsertionMode::InCell => InsertionMode::InSelectInTable,
+                    _ => InsertionMode::InSelect,
+                };
+            }
+            "optgroup" | "option" => {
+                if self.current_name() == "option" {
+                    self.open_elements.pop();
+                }
+                self.reconstruct_active_formatting_elements();
+                self.insert_element(tag);
+            }
+            "rb" | "rtc" | "rp" | "rt" => {
+                if self.in_scope(&["ruby"], Scope::Default) {
+                    let except = if matches!(&*tag.name, "rp" | "rt") {
+                        Some("rtc")
+                    } else {
+                        None
+                    };
+                    self.generate_implied_end_tags(except);
+                    if !matches!(self.current_name(), "ruby" | "rtc") {
+                        self.error(ParseErrorKind::UnexpectedStartTag(tag.name.clone()));
+                    }
+                }
+                self.insert_element(tag);
+            }
+            "caption" | "col" | "colgroup" | "frame" | "head" | "tbody" | "td" | "tfoot" | "th"
+            | "thead" | "tr" => {
+                self.error(ParseErrorKind::UnexpectedStartTag(tag.name));
+            }
+            _ => {
+                self.reconstruct_active_formatting_elements();
+                self.insert_element(tag);
+            }
+        }
+        None
+    }
+
+    fn in_body_end_tag(&mut self, tag: Tag) -> Option<Token> {
+        match &*tag.name {
+            "body" | "html" => {
+                if !self.in_scope(&["body"], Scope::Default) {
+                    self.error(ParseErrorKind::UnexpectedEndTag(tag.name));
+                    return None;
+                }
+                self.report_unclosed_elements();
+                self.mode = InsertionMode::AfterBody;
+                if tag.name == "html" {
+                    return Some(Token::EndTag(tag));
+                }
+            }
+            "address" | "article" | "aside" | "blockquote" | "button" | "center" | "details"
+            | "dialog" | "dir" | "div" | "dl" | "fieldset" | "figcaption" | "figure" | "footer"
+            | "header" | "hgroup" | "listing" | "main" | "menu" | "nav" | "ol" | "pre"
+            | "search" | "section" | "summary" | "ul" => {
+                if !self.in_scope(&[&tag.name], Scope::Default) {
+                    self.error(ParseErrorKind::UnexpectedEndTag(tag.name));
+                    return None;
+                }
+                self.generate_implied_end_tags(None);
+                self.close_elements(&[&tag.name], &tag.name);
+            }
+            "template" => return self.in_head(Token::EndTag(tag)),
+            "form" if self.template_is_open() => {
+                if !self.in_scope(&["form"], Scope::Default) {
+                    self.error(ParseErrorKind::UnexpectedEndTag(tag.name));
+                    return None;
+                }
+                self.generate_implied_end_tags(None);
+                self.close_elements(&["form"], "form");
+            }
+            "form" => {
+                let form = self.form.take();
+                match form {
+                    Some(form) if self.in_scope_where(|id| id == form, Scope::Default) => {
+                        self.generate_implied_end_tags(None);
+                        if self.current_node() != form {
+                            self.mismatched_end_tag(&tag.name);
+                        }
+                        self.open_elements.retain(|&id| id != form);
+                    }
+                    _ => self.error(ParseErrorKind::UnexpectedEndTag(tag.name)),
+                }
+            }
+            "p" => {
+                if !self.in_scope(&["p"], Scope::Button) {
+                    // A stray `</p>` makes an empty paragraph.
+                    self.error(ParseErrorKind::UnexpectedEndTag(tag.name));
+                    self.insert_implied_element("p");
+                }
+                self.close_p_element();
+            }
+            "li" | "dd" | "dt" => {
+                let scope = if tag.name == "li" {
+                    Scope::ListItem
+                } else {
+                    Scope::Default
+                };
+                if !self.in_scope(&[&tag.name], scope) {
+                    self.error(ParseErrorKind::UnexpectedEndTag(tag.name));
+                    return None;
+                }
+                self.generate_implied_end_tags(Some(&tag.name));
+                self.close_elements(&[&tag.name], &tag.name);
+            }
+            "h1" | "h2" | "h3" | "h4" | "h5" | "h6" => {
+                let headings = ["h1", "h2", "h3", "h4", "h5", "h6"];
+                if !self.in_scope(&headings, Scope::Default) {
+                    self.error(ParseErrorKind::UnexpectedEndTag(tag.name));
+                    return None;
+                }
+                self.generate_implied_end_tags(None);
+                self.close_elements(&headings, &tag.name);
+            }
+            "a" | "b" | "big" | "code" | "em" | "font" | "i" | "nobr" | "s" | "small"
+            | "strike" | "strong" | "tt" | "u" => {
+                if !self.adoption_agency(&tag.name) {
+                    self.any_other_end_tag(&tag.name);
+                }
+            }
+            "applet" | "marquee" | "object" => {
+                if !self.in_scope(&[&tag.name], Scope::Default) {
+                    self.error(ParseErrorKind::UnexpectedEndTag(tag.name));
+                    return None;
+                }
+                self.generate_implied_end_tags(None);
+                self.close_elements(&[&tag.name], &tag.name);
+                self.clear_active_formatting_to_marker();
+            }
+            "br" => {
+                // Browsers treat `</br>` as `<br>`.
+                self.error(ParseErrorKind::UnexpectedEndTag(tag.name));
+                return Some(Token::StartTag(Tag::new("br")));
+            }
+            _ => self.any_other_end_tag(&tag.name),
+        }
+        None
+    }
+
+    /// https://html.spec.whatwg.org/multipage/parsing.html#parsing-main-incdata
+    fn text(&mut self, token: Token) -> Option<Token> {
+        match token {
+            Token::Text(text) => self.insert_text(&text),
+            Token::EndTag(_) => {
+                self.open_elements.pop();
+                self.mode = self.original_mode;
+            }
+            token => {
+                // The input ended before the element's end tag.
+                self.error(ParseErrorKind::MissingEndTag(
+                    self.current_name().to_string(),
+                ));
+                self.open_elements.pop();
+                self.mode = self.original_mode;
+                return Some(token);
+            }
+        }
+        None
+    }
+
+    /// https://html.spec.whatwg.org/multipage/parsing.html#parsing-main-intable
+    fn in_table(&mut self, token: Token) -> Option<Token> {
+        match token {
+            Token::Text(text) => {
+                let in_table_context = matches!(
+                    self.current_name(),
+                    "table" | "tbody" | "tfoot" | "thead" | "tr"
+                );
+                if in_table_context && text.chars().all(is_whitespace) {
+                    self.insert_text(&text);
+                } else {
+                    self.error(ParseErrorKind::UnexpectedText);
+                    return self.foster_parent(Token::Text(text));
+                }
+            }
+            Token::Comment(_) | Token::ProcessingInstruction { .. } => {
+                self.insert_comment(token, None);
+            }
+            Token::Doctype(_) => self.error(ParseErrorKind::UnexpectedDoctype),
+            Token::StartTag(tag) => match &*tag.name {
+                "caption" => {
+                    self.clear_stack_back_to(&["table", "template", "html"]);
+                    self.active_formatting.push(Formatting::Marker);
+                    self.insert_element(tag);
+                    self.mode = InsertionMode::InCaption;
+                }
+                "colgroup" => {
+                    self.clear_stack_back_to(&["table", "template", "html"]);
+                    self.insert_element(tag);
+                    self.mode = InsertionMode::InColumnGroup;
+                }
+                "col" => {
+                    self.clear_stack_back_to(&["table", "template", "html"]);
+                    self.insert_implied_element("colgroup");
+                    self.mode = InsertionMode::InColumnGroup;
+                    return Some(Token::StartTag(tag));
+                }
+                "tbody" | "tfoot" | "thead" => {
+                    self.clear_stack_back_to(&["table", "template", "html"]);
+                    self.insert_element(tag);
+                    self.mode = InsertionMode::InTableBody;
+                }
+                "td" | "th" | "tr" => {
+                    self.clear_stack_back_to(&["table", "template", "html"]);
+                    self.insert_implied_element("tbody");
+                    self.mode = InsertionMode::InTableBody;
+                    return Some(Token::StartTag(tag));
+                }
+                "table" => {
+                    self.error(ParseErrorKind::UnexpectedStartTag(tag.name.clone()));
+                    if self.in_scope(&["table"], Scope::Table) {
+                        self.pop_until(&["table"]);
+                        self.reset_insertion_mode();
+                        return Some(Token::StartTag(tag));
+                    }
+                }
+                "style" | "script" | "template" => return self.in_head(Token::StartTag(tag)),
+                "input"
+                    if tag
+                        .attributes
+                        .get("type")
+                        .is_some_and(|t| t.eq_ignore_ascii_case("hidden")) =>
+                {
+                    self.error(ParseErrorKind::UnexpectedStartTag(tag.name.clone()));
+                    self.insert_element(tag);
+                    self.open_elements.pop();
+                }
+                "form" => {
+                    self.error(ParseErrorKind::UnexpectedStartTag(tag.name.clone()));
+                    if self.form.is_none() {
+                        self.form = Some(self.insert_element(tag));
+                        self.open_elements.pop();
+                    }
+                }
+                _ => {
+                    self.error(ParseErrorKind::UnexpectedStartTag(tag.name.clone()));
+                    return self.foster_parent(Token::StartTag(tag));
+                }
+            },
+            Token::EndTag(tag) => match &*tag.name {
+                "table" => {
+                    if !self.in_scope(&["table"], Scope::Table) {
+                        self.error(ParseErrorKind::UnexpectedEndTag(tag.name));
+                    } else {
+                        self.pop_until(&["table"]);
+                        self.reset_insertion_mode();
+                    }
+                }
+                "body" | "caption" | "col" | "colgroup" | "html" | "tbody" | "td" | "tfoot"
+                | "th" | "thead" | "tr" => self.error(ParseErrorKind::UnexpectedEndTag(tag.name)),
+                "template" => return self.in_head(Token::EndTag(tag)),
+                _ => {
+                    self.error(ParseErrorKind::UnexpectedEndTag(tag.name.clone()));
+                    return self.foster_parent(Token::EndTag(tag));
+                }
+            },
+            Token::Eof => return self.in_body(Token::Eof),
+        }
+        None
+    }
+
+    /// Process a token that does not belong in a table with the "in body" rules, moving
+    /// anything it inserts to just before the table.
+    /// https://html.spec.whatwg.org/multipage/parsing.html#foster-parent
+    fn foster_parent(&mut self, token: Token) -> Option<Token> {
+        self.foster_parenting = true;
+        let next = self.in_body(token);
+        self.foster_parenting = false;
+        next
+    }
+
+    /// https://html.spec.whatwg.org/multipage/parsing.html#parsing-main-incaption
+    fn in_caption(&mut self, token: Token) -> Option<Token> {
+        match token {
+            Token::EndTag(tag) if tag.name == "caption" => {
+                self.close_caption(&tag.name);
+            }
+            Token::StartTag(ref tag)
+                if matches!(
+                    &*tag.name,
+                    "caption"
+                        | "col"
+                        | "colgroup"
+                        | "tbody"
+                        | "td"
+                        | "tfoot"
+                        | "th"
+                        | "thead"
+                        | "tr"
+                ) =>
+            {
+                if self.close_caption(&tag.name) {
+                    return Some(token);
+                }
+            }
+            Token::EndTag(ref tag) if tag.name == "table" => {
+                if self.close_caption(&tag.name) {
+                    return Some(token);
+                }
+            }
+            Token::EndTag(tag)
+                if matches!(
+                    &*tag.name,
+                    "body"
+                        | "col"
+                        | "colgroup"
+                        | "html"
+                        | "tbody"
+                        | "td"
+                        | "tfoot"
+                        | "th"
+                        | "thead"
+                        | "tr"
+                ) =>
+            {
+                self.error(ParseErrorKind::UnexpectedEndTag(tag.name));
+            }
+            token => return self.in_body(token),
+        }
+        None
+    }
+
+    /// Close the open `caption` element, if there is one, and return to the table.
+    fn close_caption(&mut self, name: &str) -> bool {
+        if !self.in_scope(&["caption"], Scope::Table) {
+            self.error(ParseErrorKind::UnexpectedEndTag(name.to_string()));
+            return false;
+        }
+        self.generate_implied_end_tags(None);
+        self.close_elements(&["caption"], "caption");
+        self.clear_active_formatting_to_marker();
+        self.mode = InsertionMode::InTable;
+        true
+    }
+
+    /// https://html.spec.whatwg.org/multipage/parsing.html#parsing-main-incolgroup
+    fn in_column_group(&mut self, token: Token) -> Option<Token> {
+        let token = self.insert_leading_whitespace(token)?;
+        match token {
+            Token::Comment(_) | Token::ProcessingInstruction { .. } => {
+                self.insert_comment(token, None);
+            }
+            Token::Doctype(_) => self.error(ParseErrorKind::UnexpectedDoctype),
+            Token::StartTag(tag) if tag.name == "html" => {
+                return self.in_body(Token::StartTag(tag))
+            }
+            Token::StartTag(tag) if tag.name == "col" => {
+                self.insert_element(tag);
+                self.open_elements.pop();
+            }
+            Token::StartTag(tag) if tag.name == "template" => {
+                return self.in_head(Token::StartTag(tag))
+            }
+            Token::EndTag(tag) if tag.name == "template" => {
+                return self.in_head(Token::EndTag(tag))
+            }
+            Token::EndTag(tag) if tag.name == "col" => {
+                self.error(ParseErrorKind::UnexpectedEndTag(tag.name));
+            }
+            Token::EndTag(tag) if tag.name == "colgroup" => {
+                if self.current_name() != "colgroup" {
+                    self.error(ParseErrorKind::UnexpectedEndTag(tag.name));
+                } else {
+                    self.open_elements.pop();
+                    self.mode = InsertionMode::InTable;
+                }
+            }
+            Token::Eof => return self.in_body(Token::Eof),
+            token => {
+                if self.current_name() != "colgroup" {
+                    self.error(ParseErrorKind::UnexpectedText);
+                    return None;
+                }
+                self.open_elements.pop();
+                self.mode = InsertionMode::InTable;
+                return Some(token);
+            }
+        }
+        None
+    }
+
+    /// https://html.spec.whatwg.org/multipage/parsing.html#parsing-main-intbody
+    fn in_table_body(&mut self, token: Token) -> Option<Token> {
+        match token {
+            Token::StartTag(tag) if tag.name == "tr" => {
+                self.clear_stack_back_to(&["tbody", "tfoot", "thead", "template", "html"]);
+                self.insert_element(tag);
+                self.mode = InsertionMode::InRow;
+            }
+            Token::StartTag(tag) if matches!(&*tag.name, "th" | "td") => {
+                self.error(ParseErrorKind::UnexpectedStartTag(tag.name.clone()));
+                self.clear_stack_back_to(&["tbody", "tfoot", "thead", "template", "html"]);
+                self.insert_implied_element("tr");
+                self.mode = InsertionMode::InRow;
+                return Some(Token::StartTag(tag));
+            }
+            Token::EndTag(tag) if matches!(&*tag.name, "tbody" | "tfoot" | "thead") => {
+                if !self.in_scope(&[&tag.name], Scope::Table) {
+                    self.error(ParseErrorKind::UnexpectedEndTag(tag.name));
+                } else {
+                    self.clear_stack_back_to(&["tbody", "tfoot", "thead", "template", "html"]);
+                    self.open_elements.pop();
+                    self.mode = InsertionMode::InTable;
+                }
+            }
+            Token::StartTag(ref tag)
+                if matches!(
+                    &*tag.name,
+                    "caption" | "col" | "colgroup" | "tbody" | "tfoot" | "thead"
+                ) =>
+            {
+                return self.close_table_body(token);
+            }
+            Token::EndTag(ref tag) if tag.name == "table" => return self.close_table_body(token),
+            Token::EndTag(tag)
+                if matches!(
+                    &*tag.name,
+                    "body" | "caption" | "col" | "colgroup" | "html" | "td" | "th" | "tr"
+                ) =>
+            {
+                self.error(ParseErrorKind::UnexpectedEndTag(tag.name));
+            }
+            token => return self.in_table(token),
+        }
+        None
+    }
+
+    /// Close the open table section for a token that belongs directly in the table.
+    fn close_table_body(&mut self, token: Token) -> Option<Token> {
+        if !self.in_scope(&["tbody", "thead", "tfoot"], Scope::Table) {
+            self.unexpected_tag(token);
+            return None;
+        }
+        self.clear_stack_back_to(&["tbody", "tfoot", "thead", "template", "html"]);
+        self.open_elements.pop();
+        self.mode = InsertionMode::InTable;
+        Some(token)
+    }
+
+    /// https://html.spec.whatwg.org/multipage/parsing.html#parsing-main-intr
+    fn in_row(&mut self, token: Token) -> Option<Token> {
+        match token {
+            Token::StartTag(tag) if matches!(&*tag.name, "th" | "td") => {
+                self.clear_stack_back_to(&["tr", "template", "html"]);
+                self.insert_element(tag);
+                self.mode = InsertionMode::InCell;
+                self.active_formatting.push(Formatting::Marker);
+            }
+            Token::EndTag(ref tag) if tag.name == "tr" => {
+                self.close_row(token);
+            }
+            Token::StartTag(ref tag)
+                if matches!(
+                    &*tag.name,
+                    "caption" | "col" | "colgroup" | "tbody" | "tfoot" | "thead" | "tr"
+                ) =>
+            {
+                return self.close_row(token);
+            }
+            Token::EndTag(ref tag) if tag.name == "table" => return self.close_row(token),
+            Token::EndTag(ref tag) if matches!(&*tag.name, "tbody" | "tfoot" | "thead") => {
+                if !self.in_scope(&[&tag.name], Scope::Table) {
+                    self.unexpected_tag(token);
+                    return None;
+                }
+                return self.close_row(token);
+            }
+            Token::EndTag(tag)
+                if matches!(
+                    &*tag.name,
+                    "body" | "caption" | "col" | "colgroup" | "html" | "td" | "th"
+                ) =>
+            {
+                self.error(ParseErrorKind::UnexpectedEndTag(tag.name));
+            }
+            token => return self.in_table(token),
+        }
+        None
+    }
+
+    /// Close the open `tr` element. Returns the token for reprocessing, unless it was the
+    /// `</tr>` that did the closing.
+    fn close_row(&mut self, token: Token) -> Option<Token> {
+        if !self.in_scope(&["tr"], Scope::Table) {
+            self.unexpected_tag(token);
+            return None;
+        }
+        self.clear_stack_back_to(&["tr", "template", "html"]);
+        self.open_elements.pop();
+        self.mode = InsertionMode::InTableBody;
+        match token {
+            Token::EndTag(tag) if tag.name == "tr" => None,
+            token => Some(token),
+        }
+    }
+
+    /// https://html.spec.whatwg.org/multipage/parsing.html#parsing-main-intd
+    fn in_cell(&mut self, token: Token) -> Option<Token> {
+        match token {
+            Token::EndTag(tag) if matches!(&*tag.name, "td" | "th") => {
+                if !self.in_scope(&[&tag.name], Scope::Table) {
+                    self.error(ParseErrorKind::UnexpectedEndTag(tag.name));
+                } else {
+                    self.generate_implied_end_tags(None);
+                    self.close_elements(&[&tag.name], &tag.name);
+                    self.clear_active_formatting_to_marker();
+                    self.mode = InsertionMode::InRow;
+                }
+            }
+            Token::StartTag(ref tag)
+                if matches!(
+                    &*tag.name,
+                    "caption"
+                        | "col"
+                        | "colgroup"
+                        | "tbody"
+                        | "td"
+                        | "tfoot"
+                        | "th"
+                        | "thead"
+                        | "tr"
+                ) =>
+            {
+                if !self.in_scope(&["td", "th"], Scope::Table) {
+                    self.unexpected_tag(token);
+                    return None;
+                }
+                self.close_cell();
+                return Some(token);
+            }
+            Token::EndTag(tag)
+                if matches!(&*tag.name, "body" | "caption" | "col" | "colgroup" | "html") =>
+            {
+                self.error(ParseErrorKind::UnexpectedEndTag(tag.name));
+            }
+            Token::EndTag(ref tag)
+                if matches!(&*tag.name, "table" | "tbody" | "tfoot" | "thead" | "tr") =>
+            {
+                if !self.in_scope(&[&tag.name], Scope::Table) {
+                    self.unexpected_tag(token);
+                    return None;
+                }
+                self.close_cell();
+                return Some(token);
+            }
+            token => return self.in_body(token),
+        }
+        None
+    }
+
+    /// https://html.spec.whatwg.org/multipage/parsing.html#close-the-cell
+    fn close_cell(&mut self) {
+        self.generate_implied_end_tags(None);
+        let name = self.current_name().to_string();
+        self.close_elements(&["td", "th"], &name);
+        self.clear_active_formatting_to_marker();
+        self.mode = InsertionMode::InRow;
+    }
+
+    /// https://html.spec.whatwg.org/multipage/parsing.html#parsing-main-inselect
+    fn in_select(&mut self, token: Token) -> Option<Token> {
+        match token {
+            Token::Text(text) => self.insert_text(&text),
+            Token::Comment(_) | Token::ProcessingInstruction { .. } => {
+                self.insert_comment(token, None);
+            }
+            Token::Doctype(_) => self.error(ParseErrorKind::UnexpectedDoctype),
+            Token::StartTag(tag) => match &*tag.name {
+                "html" => return self.in_body(Token::StartTag(tag)),
+                "option" | "optgroup" | "hr" => {
+                    if self.current_name() == "option" {
+                        self.open_elements.pop();
+                    }
+                    if tag.name != "option" && self.current_name() == "optgroup" {
+                        self.open_elements.pop();
+                    }
+                    let void = tag.name == "hr";
+                    self.insert_element(tag);
+                    if void {
+                        self.open_elements.pop();
+                    }
+                }
+                "select" | "input" | "keygen" | "textarea" => {
+                    self.error(ParseErrorKind::UnexpectedStartTag(tag.name.clone()));
+                    if self.in_scope(&["select"], Scope::Select) {
+                        self.pop_until(&["select"]);
+                        self.reset_insertion_mode();
+                        if tag.name != "select" {
+                            return Some(Token::StartTag(tag));
+                        }
+                    }
+                }
+                "script" | "template" => return self.in_head(Token::StartTag(tag)),
+                _ => self.error(ParseErrorKind::UnexpectedStartTag(tag.name)),
+            },
+            Token::EndTag(tag) => match &*tag.name {
+                "optgroup" => {
+                    let len = self.open_elements.len();
+                    if self.current_name() == "option"
+                        && len >= 2
+                        && self.name(self.open_elements[len - 2]) == "optgroup"
+                    {
+                        self.open_elements.pop();
+                    }
+                    if self.current_name() == "optgroup" {
+                        self.open_elements.pop();
+                    } else {
+                        self.error(ParseErrorKind::UnexpectedEndTag(tag.name));
+                    }
+                }
+                "option" => {
+                    if self.current_name() == "option" {
+                        self.open_elements.pop();
+                    } else {
+                        self.error(ParseErrorKind::UnexpectedEndTag(tag.name));
+                    }
+                }
+                "select" => {
+                    if self.in_scope(&["select"], Scope::Select) {
+                        self.pop_until(&["select"]);
+                        self.reset_insertion_mode();
+                    } else {
+                        self.error(ParseErrorKind::UnexpectedEndTag(tag.name));
+                    }
+                }
+                "template" => return self.in_head(Token::EndTag(tag)),
+                _ => self.error(ParseErrorKind::UnexpectedEndTag(tag.name)),
+            },
+            Token::Eof => return self.in_body(Token::Eof),
+        }
+        None
+    }
+
+    /// https://html.spec.whatwg.org/multipage/parsing.html#parsing-main-inselectintable
+    fn in_select_in_table(&mut self, token: Token) -> Option<Token> {
+        let table_tag = |tag: &Tag| {
+            matches!(
+                &*tag.name,
+                "caption" | "table" | "tbody" | "tfoot" | "thead" | "tr" | "td" | "th"
+            )
+        };
+        match token {
+            Token::StartTag(ref tag) if table_tag(tag) => {
+                self.unexpected_tag(token.clone());
+                self.pop_until(&["select"]);
+                self.reset_insertion_mode();
+                Some(token)
+            }
+            Token::EndTag(ref tag) if table_tag(tag) => {
+                self.unexpected_tag(token.clone());
+                if !self.in_scope(&[&tag.name], Scope::Table) {
+                    return None;
+                }
+                self.pop_until(&["select"]);
+                self.reset_insertion_mode();
+                Some(token)
+            }
+            token => self.in_select(token),
+        }
+    }
+
+    /// https://html.spec.whatwg.org/multipage/parsing.html#parsing-main-intemplate
+    fn in_template(&mut self, token: Token) -> Option<Token> {
+        match token {
+            Token::Text(_)
+            | Token::Comment(_)
+            | Token::ProcessingInstruction { .. }
+            | Token::Doctype(_) => return self.in_body(token),
+            Token::StartTag(ref tag)
+                if matches!(
+                    &*tag.name,
+                    "base"
+                        | "basefont"
+                        | "bgsound"
+                        | "link"
+                        | "meta"
+                        | "noframes"
+                        | "script"
+                        | "style"
+                        | "template"
+                        | "title"
+                ) =>
+            {
+                return self.in_head(token)
+            }
+            Token::EndTag(ref tag) if tag.name == "template" => return self.in_head(token),
+            Token::StartTag(ref tag) => {
+                // The first start tag decides what kind of content the template holds.
+                let mode = match &*tag.name {
+                    "caption" | "colgroup" | "tbody" | "tfoot" | "thead" => InsertionMode::InTable,
+                    "col" => InsertionMode::InColumnGroup,
+                    "tr" => InsertionMode::InTableBody,
+                    "td" | "th" => InsertionMode::InRow,
+                    _ => InsertionMode::InBody,
+                };
+                self.template_modes.pop();
+                self.template_modes.push(mode);
+                self.mode = mode;
+                return Some(token);
+            }
+            Token::EndTag(tag) => self.error(ParseErrorKind::UnexpectedEndTag(tag.name)),
+            Token::Eof => {
+                if !self.template_is_open() {
+                    return None;
+                }
+                self.error(ParseErrorKind::MissingEndTag("template".to_string()));
+                self.pop_until(&["template"]);
+                self.clear_active_formatting_to_marker();
+                self.template_modes.pop();
+                self.reset_insertion_mode();
+                return Some(Token::Eof);
+            }
+        }
+        None
+    }
+
+    /// https://html.spec.whatwg.org/multipage/parsing.html#parsing-main-afterbody
+    fn after_body(&mut self, token: Token) -> Option<Token> {
+        match token {
+            Token::Text(text) if text.chars().all(is_whitespace) => {
+                return self.in_body(Token::Text(text));
+            }
+            Token::Comment(_) | Token::ProcessingInstruction { .. } => {
+                // Comments after `</body>` go at the end of the `html` element.
+                let html = self.open_elements.first().copied();
+                self.insert_comment(token, html);
+            }
+            Token::Doctype(_) => self.error(ParseErrorKind::UnexpectedDoctype),
+            Token::StartTag(tag) if tag.name == "html" => {
+                return self.in_body(Token::StartTag(tag))
+            }
+            Token::EndTag(tag) if tag.name == "html" => self.mode = InsertionMode::AfterAfterBody,
+            Token::Eof => {}
+            token => {
+                self.unexpected_tag(token.clone());
+                self.mode = InsertionMode::InBody;
+                return Some(token);
+            }
+        }
+        None
+    }
+
+    /// https://html.spec.whatwg.org/multipage/parsing.html#the-after-after-body-insertion-mode
+    fn after_after_body(&mut self, token: Token) -> Option<Token> {
+        match token {
+            Token::Comment(_) | Token::ProcessingInstruction { .. } => {
+                self.insert_comment(token, Some(DOCUMENT));
+            }
+            Token::Text(text) if text.chars().all(is_whitespace) => {
+                return self.in_body(Token::Text(text));
+            }
+            Token::Doctype(_) => return self.in_body(token),
+            Token::StartTag(tag) if tag.name == "html" => {
+                return self.in_body(Token::StartTag(tag))
+            }
+            Token::Eof => {}
+            token => {
+                self.unexpected_tag(token.clone());
+                self.mode = InsertionMode::InBody;
+                return Some(token);
+            }
+        }
+        None
+    }
+
+    /// https://html.spec.whatwg.org/multipage/parsing.html#parsing-main-inframeset
+    fn in_frameset(&mut self, token: Token) -> Option<Token> {
+        match token {
+            Token::Text(text) => self.insert_whitespace(&text),
+            Token::Comment(_) | Token::ProcessingInstruction { .. } => {
+                self.insert_comment(token, None);
+            }
+            Token::Doctype(_) => self.error(ParseErrorKind::UnexpectedDoctype),
+            Token::StartTag(tag) => match &*tag.name {
+                "html" => return self.in_body(Token::StartTag(tag)),
+                "frameset" => {
+                    self.insert_element(tag);
+                }
+                "frame" => {
+                    self.insert_element(tag);
+                    self.open_elements.pop();
+                }
+                "noframes" => return self.in_head(Token::StartTag(tag)),
+                _ => self.error(ParseErrorKind::UnexpectedStartTag(tag.name)),
+            },
+            Token::EndTag(tag) if tag.name == "frameset" => {
+                if self.open_elements.len() <= 1 {
+                    self.error(ParseErrorKind::UnexpectedEndTag(tag.name));
+                    return None;
+                }
+                self.open_elements.pop();
+                if self.current_name() != "frameset" {
+                    self.mode = InsertionMode::AfterFrameset;
+                }
+            }
+            Token::EndTag(tag) => self.error(ParseErrorKind::UnexpectedEndTag(tag.name)),
+            Token::Eof => self.report_unclosed_elements(),
+        }
+        None
+    }
+
+    /// https://html.spec.whatwg.org/multipage/parsing.html#parsing-main-afterframeset
+    fn after_frameset(&mut self, token: Token) -> Option<Token> {
+        match token {
+            Token::Text(text) => self.insert_whitespace(&text),
+            Token::Comment(_) | Token::ProcessingInstruction { .. } => {
+                self.insert_comment(token, None);
+            }
+            Token::Doctype(_) => self.error(ParseErrorKind::UnexpectedDoctype),
+            Token::StartTag(tag) if tag.name == "html" => {
+                return self.in_body(Token::StartTag(tag))
+            }
+            Token::StartTag(tag) if tag.name == "noframes" => {
+                return self.in_head(Token::StartTag(tag))
+            }
+            Token::EndTag(tag) if tag.name == "html" => {
+                self.mode = InsertionMode::AfterAfterFrameset;
+            }
+            Token::Eof => {}
+            token => self.unexpected_tag(token),
+        }
+        None
+    }
+
+    /// https://html.spec.whatwg.org/multipage/parsing.html#the-after-after-frameset-insertion-mode
+    fn after_after_frameset(&mut self, token: Token) -> Option<Token> {
+        match token {
+            Token::Comment(_) | Token::ProcessingInstruction { .. } => {
+                self.insert_comment(token, Some(DOCUMENT));
+            }
+            Token::Text(text) if text.chars().all(is_whitespace) => {
+                return self.in_body(Token::Text(text));
+            }
+            Token::Text(text) => self.insert_whitespace(&text),
+            Token::Doctype(_) => return self.in_body(token),
+            Token::StartTag(tag) if tag.name == "html" => {
+                return self.in_body(Token::StartTag(tag))
+            }
+            Token::StartTag(tag) if tag.name == "noframes" => {
+                return self.in_head(Token::StartTag(tag))
+            }
+            Token::Eof => {}
+            token => self.unexpected_tag(token),
+        }
+        None
+    }
+
+    /// https://html.spec.whatwg.org/multipage/parsing.html#parsing-main-inforeign
+    fn foreign_content(&mut self, token: Token) -> Option<Token> {
+        match token {
+            Token::Text(text) => {
+                if !text.chars().all(is_whitespace) {
+                    self.frameset_ok = false;
+                }
+                self.insert_text(&text);
+            }
+            Token::Comment(_) | Token::ProcessingInstruction { .. } => {
+                self.insert_comment(token, None);
+            }
+            Token::Doctype(_) => self.error(ParseErrorKind::UnexpectedDoctype),
+            Token::StartTag(tag) if breaks_out_of_foreign_content(&tag) => {
+                self.error(ParseErrorKind::UnexpectedStartTag(tag.name.clone()));
+                self.close_foreign_content();
+                // Reprocessed as HTML, since the current node may still be foreign.
+                return self.process_in_mode(Token::StartTag(tag));
+            }
+            Token::EndTag(tag) if matches!(&*tag.name, "br" | "p") => {
+                self.error(ParseErrorKind::UnexpectedEndTag(tag.name.clone()));
+                self.close_foreign_content();
+                return self.process_in_mode(Token::EndTag(tag));
+            }
+            Token::StartTag(tag) => {
+                let namespace = self.namespace(self.current_node());
+                self.insert_foreign_element(tag, namespace);
+            }
+            Token::EndTag(tag) => return self.foreign_end_tag(tag),
+            Token::Eof => {}
+        }
+        None
+    }
+
+    /// Close the nearest open element with the end tag's name, as long as it is foreign.
+    /// Otherwise the end tag is for the insertion mode to deal with.
+    fn foreign_end_tag(&mut self, tag: Tag) -> Option<Token> {
+        if !self.current_name().eq_ignore_ascii_case(&tag.name) {
+            self.mismatched_end_tag(&tag.name);
+        }
+        for i in (1..self.open_elements.len()).rev() {
+            let node = self.open_elements[i];
+            if self.name(node).eq_ignore_ascii_case(&tag.name) {
+                self.open_elements.truncate(i);
+                return None;
+            }
+            if self.namespace(self.open_elements[i - 1]) == dom::Namespace::Html {
+                return self.process_in_mode(Token::EndTag(tag));
+            }
+        }
+        None
+    }
+
+    /// Pop foreign elements until HTML can be inserted again.
+    fn close_foreign_content(&mut self) {
+        while let Some(&node) = self.open_elements.last() {
+            if self.namespace(node) == dom::Namespace::Html
+                || self.is_mathml_text_integration_point(node)
+                || self.is_html_integration_point(node)
+            {
+                break;
+            }
+            self.open_elements.pop();
+        }
+    }
+
+    // The tree.
+
+    /// Add a node to the arena, without attaching it anywhere.
+    /// The node spans the current token.
+    fn new_node(&mut self, data: dom::NodeType) -> usize {
+        self.nodes.push(TreeNode {
+            data: Some(data),
+            parent: None,
+            children: Vec::new(),
+            span: Some(self.token_span()),
+            closed_by_end_tag: false,
+            template_contents: None,
+        });
+        self.nodes.len() - 1
+    }
+
+    /// The span of the current token.
+    fn token_span(&self) -> dom::Span {
+        dom::Span {
+            start: self.tokenizer.source_position(self.token_start),
+            end: self.tokenizer.source_position(self.token_end),
+        }
+    }
+
+    /// An empty span at the start of the current token, for nodes that are not in the source.
+    fn implied_span(&self) -> dom::Span {
+        let start = self.tokenizer.source_position(self.token_start);
+        dom::Span { start, end: start }
+    }
+
+    /// Create an HTML element for a start tag.
+    fn create_element(&mut self, tag: Tag) -> usize {
+        self.create_element_in(tag, dom::Namespace::Html)
+    }
+
+    /// Create an element for a start tag. A `<template>` also gets a fragment for its
+    /// contents.
+    fn create_element_in(&mut self, tag: Tag, namespace: dom::Namespace) -> usize {
+        let template = namespace == dom::Namespace::Html && tag.name == "template";
+        let element = self.new_node(dom::NodeType::Element(dom::ElementData {
+            tag_name: tag.name,
+            namespace,
+            attributes: tag.attributes,
+            attribute_spans: tag.attribute_spans,
+            template_contents: None,
+        }));
+        if template {
+            let contents = self.new_node(dom::NodeType::DocumentFragment);
+            self.nodes[contents].span = None;
+            self.nodes[element].template_contents = Some(contents);
+        }
+        element
+    }
+
+    /// Create a new element with the same name and attributes as `id`.
+    fn clone_element(&mut self, id: usize) -> usize {
+        let data = match &self.nodes[id].data {
+            Some(dom::NodeType::Element(element)) => dom::NodeType::Element(dom::ElementData {
+                tag_name: element.tag_name.clone(),
+                namespace: element.namespace,
+                attributes: element.attributes.clone(),
+                attribute_spans: element.attribute_spans.clone(),
+                template_contents: None,
+            }),
+            _ => dom::NodeType::Text(String::new()),
+        };
+        let clone = self.new_node(data);
+        self.nodes[clone].span = Some(self.implied_span());
+        clone
+    }
+
+    /// Move `child` into `parent`, before `before` or else as the last child.
+    fn append(&mut self, parent: usize, before: Option<usize>, child: usize) {
+        if let Some(old_parent) = self.nodes[child].parent.take() {
+            self.nodes[old_parent].children.retain(|&id| id != child);
+        }
+        let children = &mut self.nodes[parent].children;
+        let index = before
+            .and_then(|before| children.iter().position(|&id| id == before))
+            .unwrap_or(children.len());
+        children.insert(index, child);
+        self.nodes[child].parent = Some(parent);
+    }
+
+    /// Where a new node goes: its parent, and the sibling it goes before, if any.
+    /// https://html.spec.whatwg.org/multipage/parsing.html#appropriate-place-for-inserting-a-node
+    fn appropriate_place(&self, override_target: Option<usize>) -> (usize, Option<usize>) {
+        let target = override_target.unwrap_or_else(|| self.current_node());
+        let (parent, before) = if !self.foster_parenting
+            || !matches!(
+                self.name(target),
+                "table" | "tbody" | "tfoot" | "thead" | "tr"
+            ) {
+            (target, None)
+        } else {
+            // Foster parenting: put the node just before the table it would have gone into,
+            // unless a template was opened inside that table.
+            let last = |name| {
+                self.open_elements
+                    .iter()
+                    .rposition(|&id| self.name(id) == name)
+            };
+            match (last("template"), last("table")) {
+                (Some(template), table) if table.is_none_or(|table| template > table) => {
+                    (self.open_elements[template], None)
+                }
+                (_, Some(i)) => match self.nodes[self.open_elements[i]].parent {
+                    Some(parent) => (parent, Some(self.open_elements[i])),
+                    None => (self.open_elements[i.saturating_sub(1)], None),
+                },
+                (_, None) => (
+                    self.open_elements.first().copied().unwrap_or(DOCUMENT),
+                    None,
+                ),
+            }
+        };
+
+        // Nodes inserted into a template go into its contents.
+        match self.nodes[parent].template_contents {
+            Some(contents) => (contents, None),
+            None => (parent, before),
+        }
+    }
+
+    /// Close the current node if the stack of open elements is full, so the next element is
+    /// inserted next to it. Elements the insertion mode depends on, such as tables, stay open.
+    fn limit_depth(&mut self) {
+        let keeps_mode = matches!(
+            self.html_name(self.current_node()),
+            "html"
+                | "head"
+                | "body"
+                | "frameset"
+                | "template"
+                | "table"
+                | "caption"
+                | "colgroup"
+                | "tbody"
+                | "thead"
+                | "tfoot"
+                | "tr"
+                | "td"
+                | "th"
+                | "select"
+        );
+        if self.open_elements.len() >= MAX_DEPTH && !keeps_mode {
+            self.open_elements.pop();
+        }
+    }
+
+    /// Insert an element for a start tag and push it onto the stack of open elements.
+    fn insert_element(&mut self, tag: Tag) -> usize {
+        self.limit_depth();
+        let (parent, before) = self.appropriate_place(None);
+        let element = self.create_element(tag);
+        self.append(parent, before, element);
+        self.open_elements.push(element);
+        element
+    }
+
+    /// Insert an SVG or MathML element, giving back the case the tokenizer took from its
+    /// names. A self-closing foreign element is closed straight away.
+    /// https://html.spec.whatwg.org/multipage/parsing.html#insert-a-foreign-element
+    fn insert_foreign_element(&mut self, mut tag: Tag, namespace: dom::Namespace) {
+        adjust_foreign_names(&mut tag, namespace);
+        let self_closing = tag.self_closing;
+        self.limit_depth();
+        let (parent, before) = self.appropriate_place(None);
+        let element = self.create_element_in(tag, namespace);
+        self.append(parent, before, element);
+        self.open_elements.push(element);
+        // Elements like `<style>` hold markup, not raw text, outside HTML.
+        self.tokenizer.cancel_raw_text();
+        if self_closing {
+            self.open_elements.pop();
+        }
+    }
+
+    /// Insert an element the markup left out, such as `tbody` in `<table><tr>`.
+    fn insert_implied_element(&mut self, name: &str) -> usize {
+        let element = self.insert_element(Tag::new(name));
+        self.nodes[element].span = Some(self.implied_span());
+        element
+    }
+
+    /// Insert a comment or processing instruction into `parent`, or where a node would
+    /// normally go.
+    fn insert_comment(&mut self, token: Token, parent: Option<usize>) {
+        let data = match token {
+            Token::Comment(data) => dom::NodeType::Comment(data),
+            Token::ProcessingInstruction { target, data } => {
+                dom::NodeType::ProcessingInstruction(dom::ProcessingInstructionData {
+                    target,
+                    data,
+                })
+            }
+            _ => return,
+        };
+        let (parent, before) = match parent {
+            Some(parent) => (parent, None),
+            None => self.appropriate_place(None),
+        };
+        let node = self.new_node(data);
+        self.append(parent, before, node);
+    }
+
+    /// Insert the rest of the current text token.
+    fn insert_text(&mut self, text: &str) {
+        self.insert_text_until(text, self.token_end);
+    }
+
+    /// Insert text that ends at byte offset `end` of the source, joining it to a text node
+    /// right before it.
+    fn insert_text_until(&mut self, text: &str, end: usize) {
+        let (parent, before) = self.appropriate_place(None);
+        if text.is_empty() || parent == DOCUMENT {
+            return;
+        }
+
+        let siblings = &self.nodes[parent].children;
+        let previous = match before.and_then(|before| siblings.iter().position(|&id| id == before))
+        {
+            Some(index) => index.checked_sub(1).map(|i| siblings[i]),
+            None => siblings.last().copied(),
+        };
+        if let Some(previous) = previous {
+            if let Some(dom::NodeType::Text(data)) = &mut self.nodes[previous].data {
+                data.push_str(text);
+                if let Some(span) = &mut self.nodes[previous].span {
+                    span.end = self.tokenizer.source_position(end);
+                }
+                return;
+            }
+        }
+
+        let node = self.new_node(dom::NodeType::Text(text.to_string()));
+        self.nodes[node].span = Some(dom::Span {
+            start: self.tokenizer.source_position(self.text_start),
+            end: self.tokenizer.source_position(end),
+        });
+        self.append(parent, before, node);
+    }
+
+    /// Drop the whitespace at the start of a text token, and the token itself if nothing is
+    /// left.
+    fn skip_leading_whitespace(&mut self, token: Token) -> Option<Token> {
+        match token {
+            Token::Text(text) => {
+                let rest = text.trim_start_matches(is_whitespace);
+                self.text_start += text.len() - rest.len();
+                if rest.is_empty() {
+                    None
+                } else {
+                    Some(Token::Text(rest.to_string()))
+                }
+            }
+            token => Some(token),
+        }
+    }
+
+    /// Insert the whitespace at the start of a text token, and return what is left of the
+    /// token, if anything.
+    fn insert_leading_whitespace(&mut self, token: Token) -> Option<Token> {
+        match token {
+            Token::Text(text) => {
+                let rest = text.trim_start_matches(is_whitespace);
+                let whitespace = &text[..text.len() - rest.len()];
+                let end = self.text_start + whitespace.len();
+                self.insert_text_until(whitespace, end);
+                self.text_start = end;
+                if rest.is_empty() {
+                    None
+                } else {
+                    Some(Token::Text(rest.to_string()))
+                }
+            }
+            token => Some(token),
+        }
+    }
+
+    /// Insert the whitespace of a text token where only whitespace is allowed, as in a
+    /// frameset, and drop the other characters.
+    fn insert_whitespace(&mut self, text: &str) {
+        let whitespace: String = text.chars().filter(|&c| is_whitespace(c)).collect();
+        if whitespace.len() < text.len() {
+            self.error(ParseErrorKind::UnexpectedText);
+        }
+        self.insert_text(&whitespace);
+    }
+
+    /// Add the attributes of `tag` that `element` does not already have.
+    fn merge_attributes(&mut self, element: usize, tag: Tag) {
+        if let Some(dom::NodeType::Element(data)) = &mut self.nodes[element].data {
+            for (name, value) in tag.attributes {
+                if let Some(&span) = tag.attribute_spans.get(&name) {
+                    data.attribute_spans.entry(name.clone()).or_insert(span);
+                }
+                data.attributes.entry(name).or_insert(value);
+            }
+        }
+    }
+
+    // The stack of open elements.
+
+    /// The most recently opened element that is still open.
+    fn current_node(&self) -> usize {
+        self.open_elements.last().copied().unwrap_or(DOCUMENT)
+    }
+
+    /// The tag name of an element, or `""` for any other node.
+    fn name(&self, id: usize) -> &str {
+        match &self.nodes[id].data {
+            Some(dom::NodeType::Element(element)) => &element.tag_name,
+            _ => "",
+        }
+    }
+
+    fn current_name(&self) -> &str {
+        self.name(self.current_node())
+    }
+
+    /// The tag name of an HTML element, or `""` for any other node, so that foreign
+    /// elements never match HTML names like `p` or `table`.
+    fn html_name(&self, id: usize) -> &str {
+        match self.namespace(id) {
+            dom::Namespace::Html => self.name(id),
+            _ => "",
+        }
+    }
+
+    /// The namespace of an element, or HTML for any other node.
+    fn namespace(&self, id: usize) -> dom::Namespace {
+        match &self.nodes[id].data {
+            Some(dom::NodeType::Element(element)) => element.namespace,
+            _ => dom::Namespace::Html,
+        }
+    }
+
+    /// https://html.spec.whatwg.org/multipage/parsing.html#mathml-text-integration-point
+    fn is_mathml_text_integration_point(&self, id: usize) -> bool {
+        self.namespace(id) == dom::Namespace::MathMl
+            && matches!(self.name(id), "mi" | "mo" | "mn" | "ms" | "mtext")
+    }
+
+    /// Whether HTML may appear inside a foreign element.
+    /// https://html.spec.whatwg.org/multipage/parsing.html#html-integration-point
+    fn is_html_integration_point(&self, id: usize) -> bool {
+        match &self.nodes[id].data {
+            Some(dom::NodeType::Element(element)) => match element.namespace {
+                dom::Namespace::MathMl => {
+                    element.tag_name == "annotation-xml"
+                        && element.attributes.get("encoding").is_some_and(|encoding| {
+                            encoding.eq_ignore_ascii_case("text/html")
+                                || encoding.eq_ignore_ascii_case("application/xhtml+xml")
+                        })
+                }
+                dom::Namespace::Svg => {
+                    matches!(&*element.tag_name, "foreignObject" | "desc" | "title")
+                }
+                _ => false,
+            },
+            _ => false,
+        }
+    }
+
+    /// Foreign elements that stop a scope check, like `td` does in HTML.
+    fn is_foreign_scope_boundary(&self, id: usize) -> bool {
+        self.is_mathml_text_integration_point(id)
+            || match self.namespace(id) {
+                dom::Namespace::MathMl => self.name(id) == "annotation-xml",
+                dom::Namespace::Svg => {
+                    matches!(self.name(id), "foreignObject" | "desc" | "title")
+                }
+                _ => false,
+            }
+    }
+
+    /// https://html.spec.whatwg.org/multipage/parsing.html#special
+    fn is_special_element(&self, id: usize) -> bool {
+        is_special(self.html_name(id)) || self.is_foreign_scope_boundary(id)
+    }
+
+    /// Pop elements until one with one of the given names has been popped.
+    fn pop_until(&mut self, names: &[&str]) {
+        while let Some(id) = self.open_elements.pop() {
+            if names.contains(&self.html_name(id)) {
+                break;
+            }
+        }
+    }
+
+    /// Pop elements until one named in `names` has been popped, reporting an error if the
+    /// current node is not it. `found` is the name of the tag doing the closing.
+    fn close_elements(&mut self, names: &[&str], found: &str) {
+        if !names.contains(&self.html_name(self.current_node())) {
+            self.mismatched_end_tag(found);
+        }
+        self.pop_until(names);
+    }
+
+    /// Pop elements back to one of the given table context elements.
+    /// https://html.spec.whatwg.org/multipage/parsing.html#clear-the-stack-back-to-a-table-context
+    fn clear_stack_back_to(&mut self, names: &[&str]) {
+        while !self.open_elements.is_empty()
+            && !names.contains(&self.html_name(self.current_node()))
+        {
+            self.open_elements.pop();
+        }
+    }
+
+    /// Whether an element matching `target` is open, with no scope boundary above it.
+    /// https://html.spec.whatwg.org/multipage/parsing.html#has-an-element-in-the-specific-scope
+    fn in_scope_where<F>(&self, target: F, scope: Scope) -> bool
+    where
+        F: Fn(usize) -> bool,
+    {
+        for &id in self.open_elements.iter().rev() {
+            if target(id) {
+                return true;
+            }
+            let name = self.html_name(id);
+            let default = is_scope_boundary(name) || self.is_foreign_scope_boundary(id);
+            let boundary = match scope {
+                Scope::Default => default,
+                Scope::ListItem => default || matches!(name, "ol" | "ul"),
+                Scope::Button => default || name == "button",
+                Scope::Table => matches!(name, "html" | "table" | "template"),
+                Scope::Select => !matches!(name, "optgroup" | "option"),
+            };
+            if boundary {
+                return false;
+            }
+        }
+        false
+    }
+
+    /// Whether an element with one of the given names is in scope.
+    fn in_scope(&self, names: &[&str], scope: Scope) -> bool {
+        self.in_scope_where(|id| names.contains(&self.html_name(id)), scope)
+    }
+
+    /// Close elements that may be closed implicitly, such as `p` and `li`, except `except`.
+    /// https://html.spec.whatwg.org/multipage/parsing.html#generate-implied-end-tags
+    fn generate_implied_end_tags(&mut self, except: Option<&str>) {
+        loop {
+            let name = self.html_name(self.current_node());
+            let implied = matches!(
+                name,
+                "dd" | "dt" | "li" | "optgroup" | "option" | "p" | "rb" | "rp" | "rt" | "rtc"
+            );
+            if !implied || Some(name) == except {
+                break;
+            }
+            self.open_elements.pop();
+        }
+    }
+
+    /// Close every element that may be closed implicitly, including table parts.
+    /// https://html.spec.whatwg.org/multipage/parsing.html#generate-all-implied-end-tags-thoroughly
+    fn generate_all_implied_end_tags(&mut self) {
+        while matches!(
+            self.html_name(self.current_node()),
+            "caption"
+                | "colgroup"
+                | "dd"
+                | "dt"
+                | "li"
+                | "optgroup"
+                | "option"
+                | "p"
+                | "rb"
+                | "rp"
+                | "rt"
+                | "rtc"
+                | "tbody"
+                | "td"
+                | "tfoot"
+                | "th"
+                | "thead"
+                | "tr"
+        ) {
+            self.open_elements.pop();
+        }
+    }
+
+    /// Whether a `<template>` is open, so its contents are being parsed.
+    fn template_is_open(&self) -> bool {
+        self.open_elements
+            .iter()
+            .any(|&id| self.name(id) == "template" && self.namespace(id) == dom::Namespace::Html)
+    }
+
+    /// https://html.spec.whatwg.org/multipage/parsing.html#close-a-p-element
+    fn close_p_element(&mut self) {
+        self.generate_implied_end_tags(Some("p"));
+        self.close_elements(&["p"], "p");
+    }
+
+    fn close_p_in_button_scope(&mut self) {
+        if self.in_scope(&["p"], Scope::Button) {
+            self.close_p_element();
+        }
+    }
+
+    /// Close an open `li`, or `dd` and `dt`, before starting a new one.
+    fn close_list_item(&mut self, names: &[&str]) {
+        for i in (0..self.open_elements.len()).rev() {
+            let node = self.open_elements[i];
+            let name = self.html_name(node).to_string();
+            if names.contains(&&*name) {
+                self.generate_implied_end_tags(Some(&name));
+                self.close_elements(&[&name], &name);
+                return;
+            }
+            if self.is_special_element(node) && !matches!(&*name, "address" | "div" | "p") {
+                return;
+            }
+        }
+    }
+
+    /// Close the nearest open element with the given name, unless a special element is
+    /// in the way.
+    /// https://html.spec.whatwg.org/multipage/parsing.html#any-other-end-tag
+    fn any_other_end_tag(&mut self, name: &str) {
+        for i in (0..self.open_elements.len()).rev() {
+            let node = self.open_elements[i];
+            // Names in foreign content keep their case, but end tags still match any case.
+            if self.name(node).eq_ignore_ascii_case(name) {
+                self.generate_implied_end_tags(Some(name));
+                if self.current_node() != node {
+                    self.mismatched_end_tag(name);
+                }
+                self.open_elements.truncate(i);
+                return;
+            }
+            if self.is_special_element(node) {
+                self.error(ParseErrorKind::UnexpectedEndTag(name.to_string()));
+                return;
+            }
+        }
+    }
+
+    /// Choose the insertion mode from the elements that are open.
+    /// https://html.spec.whatwg.org/multipage/parsing.html#reset-the-insertion-mode-appropriately
+    fn reset_insertion_mode(&mut self) {
+        for (i, &node) in self.open_elements.iter().enumerate().rev() {
+            let last = i == 0;
+            self.mode = match self.html_name(node) {
+                "select" => {
+                    let in_table = self.open_elements[..i]
+                        .iter()
+                        .any(|&id| self.html_name(id) == "table");
+                    if in_table {
+                        InsertionMode::InSelectInTable
+                    } else {
+                        InsertionMode::InSelect
+                    }
+                }
+                "template" => *self
+                    .template_modes
+                    .last()
+                    .unwrap_or(&InsertionMode::InTemplate),
+                "td" | "th" if !last => InsertionMode::InCell,
+                "tr" => InsertionMode::InRow,
+                "tbody" | "thead" | "tfoot" => InsertionMode::InTableBody,
+                "caption" => InsertionMode::InCaption,
+                "colgroup" => InsertionMode::InColumnGroup,
+                "table" => InsertionMode::InTable,
+                "head" if !last => InsertionMode::InHead,
+                "body" => InsertionMode::InBody,
+                "frameset" => InsertionMode::InFrameset,
+                "html" if self.head.is_none() => InsertionMode::BeforeHead,
+                "html" => InsertionMode::AfterHead,
+                _ if last => InsertionMode::InBody,
+                _ => continue,
+            };
+            return;
+        }
+        self.mode = InsertionMode::InBody;
+    }
+
+    // The list of active formatting elements.
+
+    /// The last element with the given name after the last marker, with its index in the list.
+    fn formatting_element_after_marker(&self, name: &str) -> Option<(usize, usize)> {
+        for (i, &entry) in self.active_formatting.iter().enumerate().rev() {
+            match entry {
+                Formatting::Marker => return None,
+                Formatting::Element(id) if self.name(id) == name => return Some((i, id)),
+                Formatting::Element(_) => {}
+            }
+        }
+        None
+    }
+
+    /// Add a formatting element, keeping at most three identical ones after the last marker.
+    /// https://html.spec.whatwg.org/multipage/parsing.html#push-onto-the-list-of-active-formatting-elements
+    fn push_active_formatting(&mut self, element: usize) {
+        let mut identical = Vec::new();
+        for (i, &entry) in self.active_formatting.iter().enumerate().rev() {
+            match entry {
+                Formatting::Marker => break,
+                Formatting::Element(id) if self.nodes[id].data == self.nodes[element].data => {
+                    identical.push(i);
+                }
+                Formatting::Element(_) => {}
+            }
+        }
+        if identical.len() >= 3 {
+            if let Some(&earliest) = identical.last() {
+                self.active_formatting.remove(earliest);
+            }
+        }
+        self.active_formatting.push(Formatting::Element(element));
+    }
+
+    fn clear_active_formatting_to_marker(&mut self) {
+        while let Some(entry) = self.active_formatting.pop() {
+            if entry == Formatting::Marker {
+                break;
+            }
+        }
+    }
+
+    /// Reopen formatting elements that were closed implicitly, so that in
+    /// `<p><b>bold<p>still bold` the second paragraph gets its own `b`.
+    /// https://html.spec.whatwg.org/multipage/parsing.html#reconstruct-the-active-formatting-elements
+    fn reconstruct_active_formatting_elements(&mut self) {
+        let is_open_or_marker = |builder: &Self, entry: Formatting| match entry {
+            Formatting::Marker => true,
+            Formatting::Element(id) => builder.open_elements.contains(&id),
+        };
+
+        let mut i = match self.active_formatting.last() {
+            Some(&entry) if !is_open_or_marker(self, entry) => self.active_formatting.len() - 1,
+            _ => return,
+        };
+        while i > 0 && !is_open_or_marker(self, self.active_formatting[i - 1]) {
+            i -= 1;
+        }
+
+        for entry in i..self.active_formatting.len() {
+            if let Formatting::Element(id) = self.active_formatting[entry] {
+                self.limit_depth();
+                let (parent, before) = self.appropriate_place(None);
+                let element = self.clone_element(id);
+                self.append(parent, before, element);
+                self.open_elements.push(element);
+                self.active_formatting[entry] = Formatting::Element(element);
+            }
+        }
+    }
+
+    /// Close the formatting element `subject`, repairing misnested markup such as
+    /// `<b>1<p>2</b>3</p>`. Returns false if the caller should treat the end tag as any
+    /// other end tag.
+    /// https://html.spec.whatwg.org/multipage/parsing.html#adoption-agency-algorithm
+    fn adoption_agency(&mut self, subject: &str) -> bool {
+        let current = self.current_node();
+        if self.name(current) == subject
+            && !self
+                .active_formatting
+                .contains(&Formatting::Element(current))
+        {
+            self.open_elements.pop();
+            return true;
+        }
+
+        for _ in 0..8 {
+            let (formatting_index, formatting_element) =
+                match self.formatting_element_after_marker(subject) {
+                    Some(found) => found,
+                    None => return false,
+                };
+            let formatting_stack_index = match self
+                .open_elements
+                .iter()
+                .position(|&id| id == formatting_element)
+            {
+                Some(i) => i,
+                None => {
+                    self.error(ParseErrorKind::UnexpectedEndTag(subject.to_string()));
+                    self.active_formatting.remove(formatting_index);
+                    return true;
+                }
+            };
+            if !self.in_scope_where(|id| id == formatting_element, Scope::Default) {
+                self.error(ParseErrorKind::UnexpectedEndTag(subject.to_string()));
+                return true;
+            }
+            if formatting_element != self.current_node() {
+                self.mismatched_end_tag(subject);
+            }
+
+            // The furthest block is the first special element opened inside the formatting
+            // element. Without one, closing the formatting element is enough.
+            let furthest_block_index = match self.open_elements[formatting_stack_index + 1..]
+                .iter()
+                .position(|&id| self.is_special_element(id))
+            {
+                Some(i) => formatting_stack_index + 1 + i,
+                None => {
+                    self.open_elements.truncate(formatting_stack_index);
+                    self.active_formatting.remove(formatting_index);
+                    return true;
+                }
+            };
+            let furthest_block = self.open_elements[furthest_block_index];
+            let common_ancestor = self.open_elements[formatting_stack_index.saturating_sub(1)];
+            let mut bookmark = formatting_index;
+
+            // Clone the formatting elements between the two, so they wrap the moved content.
+            let mut node_index = furthest_block_index;
+            let mut last_node = furthest_block;
+            let mut inner_loop_counter = 0;
+            loop {
+                inner_loop_counter += 1;
+                node_index -= 1;
+                let node = self.open_elements[node_index];
+                if node == formatting_element {
+                    break;
+                }
+
+                let mut entry = self
+                    .active_formatting
+                    .iter()
+                    .position(|&e| e == Formatting::Element(node));
+                if inner_loop_counter > 3 {
+                    if let Some(i) = entry.take() {
+                        self.active_formatting.remove(i);
+                        if i < bookmark {
+                            bookmark -= 1;
+                        }
+                    }
+                }
+                let entry = match entry {
+                    Some(i) => i,
+                    None => {
+                        self.open_elements.remove(node_index);
+                        continue;
+                    }
+                };
+
+                let element = self.clone_element(node);
+                self.active_formatting[entry] = Formatting::Element(element);
+                self.open_elements[node_index] = element;
+                if last_node == furthest_block {
+                    bookmark = entry + 1;
+                }
+                self.append(element, None, last_node);
+                last_node = element;
+            }
+
+            let (parent, before) = self.appropriate_place(Some(common_ancestor));
+            self.append(parent, before, last_node);
+
+            // A new copy of the formatting element takes over the furthest block's children.
+            let element = self.clone_element(formatting_element);
+            let children = std::mem::take(&mut self.nodes[furthest_block].children);
+            for &child in &children {
+                self.nodes[child].parent = Some(element);
+            }
+            self.nodes[element].children = children;
+            self.append(furthest_block, None, element);
+
+            if let Some(i) = self
+                .active_formatting
+                .iter()
+                .position(|&e| e == Formatting::Element(formatting_element))
+            {
+                self.active_formatting.remove(i);
+                if i < bookmark {
+                    bookmark -= 1;
+                }
+            }
+            let bookmark = bookmark.min(self.active_formatting.len());
+            self.active_formatting
+                .insert(bookmark, Formatting::Element(element));
+
+            self.open_elements.retain(|&id| id != formatting_element);
+            if let Some(i) = self
+                .open_elements
+                .iter()
+                .position(|&id| id == furthest_block)
+            {
+                self.open_elements.insert(i + 1, element);
+            }
+        }
+        true
+    }
+
+    // Errors.
+
+    /// Record a parse error at the start of the current token.
+    fn error(&mut self, kind: ParseErrorKind) {
+        if kind == ParseErrorKind::UnexpectedText {
+            if self.text_reported {
+                return;
+            }
+            self.text_reported = true;
+        }
+        let offset = self.tokenizer.token_start;
+        self.tokenizer.error_at(kind, offset);
+    }
+
+    /// Report an end tag that closes elements other than the current node.
+    fn mismatched_end_tag(&mut self, found: &str) {
+        let expected = self.current_name().to_string();
+        self.error(ParseErrorKind::MismatchedEndTag {
+            expected,
+            found: found.to_string(),
+        });
+    }
+
+    /// Report a tag that is not allowed where it appears.
+    fn unexpected_tag(&mut self, token: Token) {
+        match token {
+            Token::StartTag(tag) => self.error(ParseErrorKind::UnexpectedStartTag(tag.name)),
+            Token::EndTag(tag) => self.error(ParseErrorKind::UnexpectedEndTag(tag.name)),
+            Token::Text(_) => self.error(ParseErrorKind::UnexpectedText),
+            _ => {}
+        }
+    }
+
+    /// Report the open elements that should have been closed by now.
+    fn report_unclosed_elements(&mut self) {
+        for i in (0..self.open_elements.len()).rev() {
+            let name = self.name(self.open_elements[i]);
+            if !matches!(
+                name,
+                "dd" | "dt"
+                    | "li"
+                    | "optgroup"
+                    | "option"
+                    | "p"
+                    | "rb"
+                    | "rp"
+                    | "rt"
+                    | "rtc"
+                    | "tbody"
+                    | "td"
+                    | "tfoot"
+                    | "th"
+                    | "thead"
+                    | "tr"
+                    | "body"
+                    | "html"
+            ) {
+                self.error(ParseErrorKind::MissingEndTag(name.to_string()));
+            }
+        }
+    }
+}
+
+/// ASCII whitespace, which the tree builder often treats differently from other text.
+fn is_whitespace(c: char) -> bool {
+    c.is_ascii_whitespace()
+}
+
+fn is_heading(name: &str) -> bool {
+    matches!(name, "h1" | "h2" | "h3" | "h4" | "h5" | "h6")
+}
+
+/// Start tags that end foreign content, because they are almost certainly HTML.
+/// https://html.spec.whatwg.org/multipage/parsing.html#parsing-main-inforeign
+fn breaks_out_of_foreign_content(tag: &Tag) -> bool {
+    is_heading(&tag.name)
+        || matches!(
+            &*tag.name,
+            "b" | "big"
+                | "blockquote"
+                | "body"
+                | "br"
+                | "center"
+                | "code"
+                | "dd"
+                | "div"
+                | "dl"
+                | "dt"
+                | "em"
+                | "embed"
+                | "head"
+                | "hr"
+                | "i"
+                | "img"
+                | "li"
+                | "listing"
+                | "menu"
+                | "meta"
+                | "nobr"
+                | "ol"
+                | "p"
+                | "pre"
+                | "ruby"
+                | "s"
+                | "small"
+                | "span"
+                | "strong"
+                | "strike"
+                | "sub"
+                | "sup"
+                | "table"
+                | "tt"
+                | "u"
+                | "ul"
+                | "var"
+        )
+        || (tag.name == "font"
+            && ["color", "face", "size"]
+                .iter()
+                .any(|&name| tag.attributes.contains_key(name)))
+}
+
+/// Restore the case of SVG and MathML names, which the tokenizer lowercases.
+/// https://html.spec.whatwg.org/multipage/parsing.html#adjust-svg-attributes
+fn adjust_foreign_names(tag: &mut Tag, namespace: dom::Namespace) {
+    let attributes: &[(&str, &str)] = match namespace {
+        dom::Namespace::Svg => {
+            if let Some(&(_, name)) = SVG_TAG_NAMES.iter().find(|(from, _)| *from == tag.name) {
+                tag.name = name.to_string();
+            }
+            SVG_ATTRIBUTES
+        }
+        dom::Namespace::MathMl => &[("definitionurl", "definitionURL")],
+        _ => &[],
+    };
+    for (from, to) in attributes {
+        if let Some(value) = tag.attributes.remove(*from) {
+            tag.attributes.insert(to.to_string(), value);
+        }
+        if let Some(span) = tag.attribute_spans.remove(*from) {
+            tag.attribute_spans.insert(to.to_string(), span);
+        }
+    }
+}
+
+/// Elements that stop a scope check.
+/// https://html.spec.whatwg.org/multipage/parsing.html#has-an-element-in-scope
+fn is_scope_boundary(name: &str) -> bool {
+    matches!(
+        name,
+        "applet" | "caption" | "html" | "table" | "td" | "th" | "marquee" | "object" | "template"
+    )
+}
+
+/// Elements with special parsing rules, which end tags cannot close implicitly.
+/// https://html.spec.whatwg.org/multipage/parsing.html#special
+fn is_special(name: &str) -> bool {
+    is_heading(name)
+        || matches!(
+            name,
+            "address"
+                | "applet"
+                | "area"
+                | "article"
+                | "aside"
+                | "base"
+                | "basefont"
+                | "bgsound"
+                | "blockquote"
+                | "body"
+                | "br"
+                | "button"
+                | "caption"
+                | "center"
+                | "col"
+                | "colgroup"
+                | "dd"
+                | "details"
+                | "dir"
+                | "div"
+                | "dl"
+                | "dt"
+                | "embed"
+                | "fieldset"
+                | "figcaption"
+                | "figure"
+                | "footer"
+                | "form"
+                | "frame"
+                | "frameset"
+                | "head"
+                | "header"
+                | "hgroup"
+                | "hr"
+                | "html"
+                | "iframe"
+                | "img"
+                | "input"
+                | "keygen"
+                | "li"
+                | "link"
+                | "listing"
+                | "main"
+                | "marquee"
+                | "menu"
+                | "meta"
+                | "nav"
+                | "noembed"
+                | "noframes"
+                | "noscript"
+                | "object"
+                | "ol"
+                | "p"
+                | "param"
+                | "plaintext"
+                | "pre"
+                | "script"
+                | "search"
+                | "section"
+                | "select"
+                | "source"
+                | "style"
+                | "summary"
+                | "table"
+                | "tbody"
+                | "td"
+                | "template"
+                | "textarea"
+                | "tfoot"
+                | "th"
+                | "thead"
+                | "title"
+                | "tr"
+                | "track"
+                | "ul"
+                | "wbr"
+                | "xmp"
+        )
+}
+
+/// Decide the document mode from its doctype.
+/// https://html.spec.whatwg.org/multipage/parsing.html#the-initial-insertion-mode
+fn document_mode(doctype: &DoctypeToken) -> dom::DocumentMode {
+    let public_id = doctype.public_id.as_deref().map(str::to_ascii_lowercase);
+    let system_id = doctype.system_id.as_deref().map(str::to_ascii_lowercase);
+    let public_starts_with = |prefixes: &[&str]| {
+        public_id.as_deref().is_some_and(|id| {
+            prefixes
+                .iter()
+                .any(|p| id.starts_with(&p.to_ascii_lowercase()))
+        })
+    };
+    let html401 = [
+        "-//W3C//DTD HTML 4.01 Frameset//",
+        "-//W3C//DTD HTML 4.01 Transitional//",
+    ];
+
+    if doctype.force_quirks
+        || doctype.name.as_deref() != Some("html")
+        || public_id
+            .as_deref()
+            .is_some_and(|id| QUIRKY_PUBLIC_IDS.contains(&id))
+        || system_id.as_deref() == Some(QUIRKY_SYSTEM_ID)
+        || public_starts_with(QUIRKY_PUBLIC_ID_PREFIXES)
+        || (system_id.is_none() && public_starts_with(&html401))
+    {
+        dom::DocumentMode::Quirks
+    } else if public_starts_with(&[
+        "-//W3C//DTD XHTML 1.0 Frameset//",
+        "-//W3C//DTD XHTML 1.0 Transitional//",
+    ]) || (system_id.is_some() && public_starts_with(&html401))
+    {
+        dom::DocumentMode::LimitedQuirks
+    } else {
+        dom::DocumentMode::NoQuirks
+    }
+}
+
+/// Public identifiers, in lowercase, that put a document in quirks mode.
+const QUIRKY_PUBLIC_IDS: &[&str] = &[
+    "-//w3o//dtd w3 html strict 3.0//en//",
+    "-/w3c/dtd html 4.0 transitional/en",
+    "html",
+];
+
+/// A system identifier, in lowercase, that puts a document in quirks mode.
+const QUIRKY_SYSTEM_ID: &str = "http://www.ibm.com/data/dtd/v11/ibmxhtml1-transitional.dtd";
+
+/// Public identifier prefixes that put a document in quirks mode.
+const QUIRKY_PUBLIC_ID_PREFIXES: &[&str] = &[
+    "+//Silmaril//dtd html Pro v0r11 19970101//",
+    "-//AS//DTD HTML 3.0 asWedit + extensions//",
+    "-//AdvaSoft Ltd//DTD HTML 3.0 asWedit + extensions//",
+    "-//IETF//DTD HTML 2.0 Level 1//",
+    "-//IETF//DTD HTML 2.0 Level 2//",
+    "-//IETF//DTD HTML 2.0 Strict Level 1//",
+    "-//IETF//DTD HTML 2.0 Strict Level 2//",
+    "-//IETF//DTD HTML 2.0 Strict//",
+    "-//IETF//DTD HTML 2.0//",
+    "-//IETF//DTD HTML 2.1E//",
+    "-//IETF//DTD HTML 3.0//",
+    "-//IETF//DTD HTML 3.2 Final//",
+    "-//IETF//DTD HTML 3.2//",
+    "-//IETF//DTD HTML 3//",
+    "-//IETF//DTD HTML Level 0//",
+    "-//IETF//DTD HTML Level 1//",
+    "-//IETF//DTD HTML Level 2//",
+    "-//IETF//DTD HTML Level 3//",
+    "-//IETF//DTD HTML Strict Level 0//",
+    "-//IETF//DTD HTML Strict Level 1//",
+    "-//IETF//DTD HTML Strict Level 2//",
+    "-//IETF//DTD HTML Strict Level 3//",
+    "-//IETF//DTD HTML Strict//",
+    "-//IETF//DTD HTML//",
+    "-//Metrius//DTD Metrius Presentational//",
+    "-//Microsoft//DTD Internet Explorer 2.0 HTML Strict//",
+    "-//Microsoft//DTD Internet Explorer 2.0 HTML//",
+    "-//Microsoft//DTD Internet Explorer 2.0 Tables//",
+    "-//Microsoft//DTD Internet Explorer 3.0 HTML Strict//",
+    "-//Microsoft//DTD Internet Explorer 3.0 HTML//",
+    "-//Microsoft//DTD Internet Explorer 3.0 Tables//",
+    "-//Netscape Comm. Corp.//DTD HTML//",
+    "-//Netscape Comm. Corp.//DTD Strict HTML//",
+    "-//O'Reilly and Associates//DTD HTML 2.0//",
+    "-//O'Reilly and Associates//DTD HTML Extended 1.0//",
+    "-//O'Reilly and Associates//DTD HTML Extended Relaxed 1.0//",
+    "-//SQ//DTD HTML 2.0 HoTMetaL + extensions//",
+    "-//SoftQuad Software//DTD HoTMetaL PRO 6.0::19990601::extensions to HTML 4.0//",
+    "-//SoftQuad//DTD HoTMetaL PRO 4.0::19970916::extensions to HTML 4.0//",
+    "-//Spyglass//DTD HTML 2.0 Extended//",
+    "-//Sun Microsystems Corp.//DTD HotJava HTML//",
+    "-//Sun Microsystems Corp.//DTD HotJava Strict HTML//",
+    "-//W3C//DTD HTML 3 1995-03-24//",
+    "-//W3C//DTD HTML 3.2 Draft//",
+    "-//W3C//DTD HTML 3.2 Final//",
+    "-//W3C//DTD HTML 3.2//",
+    "-//W3C//DTD HTML 3.2S Draft//",
+    "-//W3C//DTD HTML 4.0 Frameset//",
+    "-//W3C//DTD HTML 4.0 Transitional//",
+    "-//W3C//DTD HTML Experimental 19960712//",
+    "-//W3C//DTD HTML Experimental 970421//",
+    "-//W3C//DTD W3 HTML//",
+    "-//W3O//DTD W3 HTML 3.0//",
+    "-//WebTechs//DTD Mozilla HTML 2.0//",
+    "-//WebTechs//DTD Mozilla HTML//",
+];
+
+/// SVG element names with capitals, by their lowercase form.
+/// https://html.spec.whatwg.org/multipage/parsing.html#parsing-main-inforeign
+const SVG_TAG_NAMES: &[(&str, &str)] = &[
+    ("altglyph", "altGlyph"),
+    ("altglyphdef", "altGlyphDef"),
+    ("altglyphitem", "altGlyphItem"),
+    ("animatecolor", "animateColor"),
+    ("animatemotion", "animateMotion"),
+    ("animatetransform", "animateTransform"),
+    ("clippath", "clipPath"),
+    ("feblend", "feBlend"),
+    ("fecolormatrix", "feColorMatrix"),
+    ("fecomponenttransfer", "feComponentTransfer"),
+    ("fecomposite", "feComposite"),
+    ("feconvolvematrix", "feConvolveMatrix"),
+    ("fediffuselighting", "feDiffuseLighting"),
+    ("fedisplacementmap", "feDisplacementMap"),
+    ("fedistantlight", "feDistantLight"),
+    ("fedropshadow", "feDropShadow"),
+    ("feflood", "feFlood"),
+    ("fefunca", "feFuncA"),
+    ("fefuncb", "feFuncB"),
+    ("fefuncg", "feFuncG"),
+    ("fefuncr", "feFuncR"),
+    ("fegaussianblur", "feGaussianBlur"),
+    ("feimage", "feImage"),
+    ("femerge", "feMerge"),
+    ("femergenode", "feMergeNode"),
+    ("femorphology", "feMorphology"),
+    ("feoffset", "feOffset"),
+    ("fepointlight", "fePointLight"),
+    ("fespecularlighting", "feSpecularLighting"),
+    ("fespotlight", "feSpotLight"),
+    ("fetile", "feTile"),
+    ("feturbulence", "feTurbulence"),
+    ("foreignobject", "foreignObject"),
+    ("glyphref", "glyphRef"),
+    ("lineargradient", "linearGradient"),
+    ("radialgradient", "radialGradient"),
+    ("textpath", "textPath"),
+];
+
+/// SVG attribute names with capitals, by their lowercase form.
+/// https://html.spec.whatwg.org/multipage/parsing.html#adjust-svg-attributes
+const SVG_ATTRIBUTES: &[(&str, &str)] = &[
+    ("attributename", "attributeName"),
+    ("attributetype", "attributeType"),
+    ("basefrequency", "baseFrequency"),
+    ("baseprofile", "baseProfile"),
+    ("calcmode", "calcMode"),
+    ("clippathunits", "clipPathUnits"),
+    ("diffuseconstant", "diffuseConstant"),
+    ("edgemode", "edgeMode"),
+    ("filterunits", "filterUnits"),
+    ("glyphref", "glyphRef"),
+    ("gradienttransform", "gradientTransform"),
+    ("gradientunits", "gradientUnits"),
+    ("kernelmatrix", "kernelMatrix"),
+    ("kernelunitlength", "kernelUnitLength"),
+    ("keypoints", "keyPoints"),
+    ("keysplines", "keySplines"),
+    ("keytimes", "keyTimes"),
+    ("lengthadjust", "lengthAdjust"),
+    ("limitingconeangle", "limitingConeAngle"),
+    ("markerheight", "markerHeight"),
+    ("markerunits", "markerUnits"),
+    ("markerwidth", "markerWidth"),
+    ("maskcontentunits", "maskContentUnits"),
+    ("maskunits", "maskUnits"),
+    ("numoctaves", "numOctaves"),
+    ("pathlength", "pathLength"),
+    ("patterncontentunits", "patternContentUnits"),
+    ("patterntransform", "patternTransform"),
+    ("patternunits", "patternUnits"),
+    ("pointsatx", "pointsAtX"),
+    ("pointsaty", "pointsAtY"),
+    ("pointsatz", "pointsAtZ"),
+    ("preservealpha", "preserveAlpha"),
+    ("preserveaspectratio", "preserveAspectRatio"),
+    ("primitiveunits", "primitiveUnits"),
+    ("refx", "refX"),
+    ("refy", "refY"),
+    ("repeatcount", "repeatCount"),
+    ("repeatdur", "repeatDur"),
+    ("requiredextensions", "requiredExtensions"),
+    ("requiredfeatures", "requiredFeatures"),
+    ("specularconstant", "specularConstant"),
+    ("specularexponent", "specularExponent"),
+    ("spreadmethod", "spreadMethod"),
+    ("startoffset", "startOffset"),
+    ("stddeviation", "stdDeviation"),
+    ("stitchtiles", "stitchTiles"),
+    ("surfacescale", "surfaceScale"),
+    ("systemlanguage", "systemLanguage"),
+    ("tablevalues", "tableValues"),
+    ("targetx", "targetX"),
+    ("targety", "targetY"),
+    ("textlength", "textLength"),
+    ("viewbox", "viewBox"),
+    ("viewtarget", "viewTarget"),
+    ("xchannelselector", "xChannelSelector"),
+    ("ychannelselector", "yChannelSelector"),
+    ("zoomandpan", "zoomAndPan"),
+];